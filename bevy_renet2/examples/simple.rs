@@ -168,6 +168,8 @@ fn server_update_system(
                 let message = bincode::serialize(&ServerMessages::PlayerDisconnected { id: *client_id }).unwrap();
                 server.broadcast_message(DefaultChannel::ReliableOrdered, message);
             }
+            ServerEvent::ClientRateLimited { .. } => {}
+            ServerEvent::PingCompleted { .. } => {}
         }
     }
 
@@ -95,6 +95,8 @@ fn create_configs(channels: &[Channel]) -> Vec<ChannelConfig> {
         let config = ChannelConfig {
             channel_id: index as u8,
             max_memory_usage_bytes: 5 * 1024 * 1024,
+            max_message_size: 5 * 1024 * 1024,
+            max_partial_messages: 64,
             send_type,
         };
 
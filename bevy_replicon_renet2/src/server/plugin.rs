@@ -71,6 +71,12 @@ impl RepliconRenetServerPlugin {
                     commands.entity(client_entity).despawn();
                     log::debug!("disconnecting `{client_entity}` with `{network_id:?}`: {reason}");
                 }
+                ServerEvent::ClientRateLimited { client_id, channel_id } => {
+                    log::warn!("client `{client_id}` exceeded the rate limit of channel {channel_id}");
+                }
+                ServerEvent::PingCompleted { client_id, rtt, .. } => {
+                    log::debug!("client `{client_id}` answered a ping in {rtt:?}");
+                }
             };
         }
     }
@@ -188,6 +188,8 @@ fn server_update_system(
                 let message = bincode::serialize(&ServerMessages::PlayerRemove { id: *client_id }).unwrap();
                 server.broadcast_message(ServerChannel::ServerMessages, message);
             }
+            ServerEvent::ClientRateLimited { .. } => {}
+            ServerEvent::PingCompleted { .. } => {}
         }
     }
 
@@ -67,15 +67,19 @@ impl ClientChannel {
             ChannelConfig {
                 channel_id: Self::Input.into(),
                 max_memory_usage_bytes: 5 * 1024 * 1024,
+                max_message_size: 5 * 1024 * 1024,
+                max_partial_messages: 64,
                 send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::ZERO,
+                    resend_time: Duration::from_millis(300),
                 },
             },
             ChannelConfig {
                 channel_id: Self::Command.into(),
                 max_memory_usage_bytes: 5 * 1024 * 1024,
+                max_message_size: 5 * 1024 * 1024,
+                max_partial_messages: 64,
                 send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::ZERO,
+                    resend_time: Duration::from_millis(300),
                 },
             },
         ]
@@ -97,11 +101,15 @@ impl ServerChannel {
             ChannelConfig {
                 channel_id: Self::NetworkedEntities.into(),
                 max_memory_usage_bytes: 10 * 1024 * 1024,
+                max_message_size: 10 * 1024 * 1024,
+                max_partial_messages: 64,
                 send_type: SendType::Unreliable,
             },
             ChannelConfig {
                 channel_id: Self::ServerMessages.into(),
                 max_memory_usage_bytes: 10 * 1024 * 1024,
+                max_message_size: 10 * 1024 * 1024,
+                max_partial_messages: 64,
                 send_type: SendType::ReliableOrdered {
                     resend_time: Duration::from_millis(200),
                 },
@@ -115,6 +123,7 @@ pub fn connection_config() -> ConnectionConfig {
         available_bytes_per_tick: 1024 * 1024,
         client_channels_config: ClientChannel::channels_config(),
         server_channels_config: ServerChannel::channels_config(),
+        heartbeat_interval: Some(Duration::from_secs(1)),
     }
 }
 
@@ -83,6 +83,8 @@ impl ChatServer {
                         .unwrap();
                     self.server.broadcast_message(DefaultChannel::ReliableOrdered, message);
                 }
+                ServerEvent::ClientRateLimited { .. } => {}
+                ServerEvent::PingCompleted { .. } => {}
             }
         }
 
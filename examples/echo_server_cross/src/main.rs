@@ -124,6 +124,8 @@ fn run_renet_server(mut transport: NetcodeServerTransport) {
                 ServerEvent::ClientDisconnected { client_id, reason } => {
                     info!("Client {} disconnected: {}", client_id, reason);
                 }
+                ServerEvent::ClientRateLimited { .. } => {}
+                ServerEvent::PingCompleted { .. } => {}
             }
         }
 
@@ -4,6 +4,6 @@ use libfuzzer_sys::fuzz_target;
 use renet2::{ConnectionConfig, RenetClient};
 
 fuzz_target!(|data: &[u8]| {
-    let mut connection = RenetClient::new(ConnectionConfig::default());
+    let mut connection = RenetClient::new(ConnectionConfig::test(), false);
     connection.process_packet(data);
 });
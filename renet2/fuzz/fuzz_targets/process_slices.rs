@@ -0,0 +1,46 @@
+#![no_main]
+
+use std::time::Duration;
+
+use libfuzzer_sys::fuzz_target;
+use renet2::{ChannelConfig, ConnectionConfig, RenetClient, SendType};
+
+// Feeds many packets to channels with small message limits,
+// so slices claiming big messages or many partial messages are exercised.
+fuzz_target!(|packets: Vec<Vec<u8>>| {
+    let channels = vec![
+        ChannelConfig {
+            channel_id: 0,
+            max_memory_usage_bytes: 64 * 1024,
+            max_message_size: 8 * 1024,
+            max_partial_messages: 4,
+            send_type: SendType::Unreliable,
+        },
+        ChannelConfig {
+            channel_id: 1,
+            max_memory_usage_bytes: 64 * 1024,
+            max_message_size: 8 * 1024,
+            max_partial_messages: 4,
+            send_type: SendType::ReliableUnordered {
+                resend_time: Duration::from_millis(300),
+            },
+        },
+        ChannelConfig {
+            channel_id: 2,
+            max_memory_usage_bytes: 64 * 1024,
+            max_message_size: 8 * 1024,
+            max_partial_messages: 4,
+            send_type: SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        },
+    ];
+    let mut connection = RenetClient::new(ConnectionConfig::from_shared_channels(channels), false);
+    for packet in packets.iter() {
+        connection.process_packet(packet);
+        connection.update(Duration::from_millis(100));
+        for channel_id in 0..3 {
+            while connection.receive_message(channel_id).is_some() {}
+        }
+    }
+});
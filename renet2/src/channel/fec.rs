@@ -0,0 +1,266 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use octets::OctetsMut;
+
+use crate::packet::Packet;
+
+/// Maximum number of packets protected by a single parity packet.
+///
+/// The parity packet is as big as the biggest packet in its group plus a few bytes per packet,
+/// so bigger groups could exceed the maximum payload size of the transport.
+pub(crate) const MAX_FEC_GROUP_SIZE: usize = 8;
+
+// Received packets and parity groups are kept for this long waiting for the rest of their group.
+const FEC_WINDOW: Duration = Duration::from_secs(1);
+const MAX_RECEIVED_PACKETS: usize = 256;
+const MAX_PARITY_GROUPS: usize = 32;
+
+/// Generates XOR parity data over groups of packets sent by an unreliable channel.
+#[derive(Debug)]
+pub(crate) struct FecEncoder {
+    group_size: usize,
+    protected: Vec<(u64, usize)>,
+    parity: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct ParityGroup {
+    protected: Vec<(u64, usize)>,
+    parity: Bytes,
+    received_at: Duration,
+}
+
+/// Recovers lost packets of an unreliable channel from the parity data sent by a [`FecEncoder`].
+#[derive(Debug)]
+pub(crate) struct FecDecoder {
+    channel_id: u8,
+    // Serialized packets that were received or recovered recently
+    received: BTreeMap<u64, (Vec<u8>, Duration)>,
+    parity_groups: VecDeque<ParityGroup>,
+    packets_recovered: u64,
+}
+
+impl FecEncoder {
+    pub fn new(group_size: usize) -> Self {
+        Self {
+            group_size,
+            protected: Vec::with_capacity(group_size),
+            parity: Vec::new(),
+        }
+    }
+
+    /// Adds a packet to the current group.
+    /// Returns the protected packets and their parity data when the group is complete.
+    pub fn add_packet(&mut self, packet: &Packet) -> Option<(Vec<(u64, usize)>, Bytes)> {
+        let mut buffer = [0u8; 1400];
+        let mut oct = OctetsMut::with_slice(&mut buffer);
+        // The packet will also fail to serialize when sent, disconnecting the client
+        let len = packet.to_bytes(&mut oct).ok()?;
+
+        xor_into(&mut self.parity, &buffer[..len]);
+        self.protected.push((packet.sequence(), len));
+        if self.protected.len() < self.group_size {
+            return None;
+        }
+
+        let protected = std::mem::replace(&mut self.protected, Vec::with_capacity(self.group_size));
+        let parity = std::mem::take(&mut self.parity);
+        Some((protected, parity.into()))
+    }
+}
+
+impl FecDecoder {
+    pub fn new(channel_id: u8) -> Self {
+        Self {
+            channel_id,
+            received: BTreeMap::new(),
+            parity_groups: VecDeque::new(),
+            packets_recovered: 0,
+        }
+    }
+
+    pub fn packets_recovered(&self) -> u64 {
+        self.packets_recovered
+    }
+
+    /// Stores a received packet so it can be used to recover other packets of its group.
+    /// Returns false if the packet was already received or recovered.
+    pub fn process_packet(&mut self, packet: &Packet, current_time: Duration) -> bool {
+        let sequence = packet.sequence();
+        if self.received.contains_key(&sequence) {
+            return false;
+        }
+
+        let mut buffer = [0u8; 1400];
+        let mut oct = OctetsMut::with_slice(&mut buffer);
+        let Ok(len) = packet.to_bytes(&mut oct) else {
+            return true;
+        };
+
+        self.received.insert(sequence, (buffer[..len].to_vec(), current_time));
+        if self.received.len() > MAX_RECEIVED_PACKETS {
+            self.received.pop_first();
+        }
+
+        true
+    }
+
+    /// Stores the parity data of a group of packets.
+    pub fn process_parity(&mut self, protected: Vec<(u64, usize)>, parity: Bytes, current_time: Duration) {
+        self.parity_groups.push_back(ParityGroup {
+            protected,
+            parity,
+            received_at: current_time,
+        });
+        if self.parity_groups.len() > MAX_PARITY_GROUPS {
+            self.parity_groups.pop_front();
+        }
+    }
+
+    /// Recovers the packets of groups where only one packet is missing.
+    pub fn recover_packets(&mut self, current_time: Duration) -> Vec<Packet> {
+        let mut recovered_packets = Vec::new();
+        let received = &mut self.received;
+        let channel_id = self.channel_id;
+        self.parity_groups.retain(|group| {
+            let mut missing = group.protected.iter().filter(|(sequence, _)| !received.contains_key(sequence));
+            let Some(&(missing_sequence, missing_len)) = missing.next() else {
+                // Nothing to recover
+                return false;
+            };
+            if missing.next().is_some() {
+                // Can't recover yet, the other packets may still arrive
+                return true;
+            }
+
+            let mut data = group.parity.to_vec();
+            for (sequence, len) in group.protected.iter() {
+                if *sequence == missing_sequence {
+                    continue;
+                }
+                let (packet, _) = &received[sequence];
+                if packet.len() != *len {
+                    log::debug!("discarded parity group for channel {channel_id} with mismatched packet length");
+                    return false;
+                }
+                xor_into(&mut data, packet);
+            }
+            data.truncate(missing_len);
+
+            let mut oct = octets::Octets::with_slice(&data);
+            match Packet::from_bytes(&mut oct) {
+                Ok(
+                    packet @ (Packet::SmallUnreliable {
+                        channel_id: packet_channel_id,
+                        ..
+                    }
+                    | Packet::UnreliableSlice {
+                        channel_id: packet_channel_id,
+                        ..
+                    }),
+                ) if packet_channel_id == channel_id && packet.sequence() == missing_sequence => {
+                    received.insert(missing_sequence, (data, current_time));
+                    recovered_packets.push(packet);
+                }
+                _ => log::debug!("failed to recover packet {missing_sequence} for channel {channel_id}"),
+            }
+
+            false
+        });
+
+        self.packets_recovered += recovered_packets.len() as u64;
+        recovered_packets
+    }
+
+    /// Discards received packets and parity groups that are too old to be useful.
+    pub fn discard_old(&mut self, current_time: Duration) {
+        self.received.retain(|_, (_, received_at)| current_time - *received_at < FEC_WINDOW);
+        self.parity_groups.retain(|group| current_time - group.received_at < FEC_WINDOW);
+    }
+}
+
+fn xor_into(parity: &mut Vec<u8>, data: &[u8]) {
+    if parity.len() < data.len() {
+        parity.resize(data.len(), 0);
+    }
+    for (p, d) in parity.iter_mut().zip(data) {
+        *p ^= d;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_packet(sequence: u64, message: &[u8]) -> Packet {
+        Packet::SmallUnreliable {
+            sequence,
+            channel_id: 0,
+            messages: vec![message.to_vec().into()],
+        }
+    }
+
+    #[test]
+    fn recover_lost_packet() {
+        let mut encoder = FecEncoder::new(3);
+        let mut decoder = FecDecoder::new(0);
+        let packets = [small_packet(0, &[1; 10]), small_packet(2, &[2; 100]), small_packet(3, &[3; 50])];
+
+        assert!(encoder.add_packet(&packets[0]).is_none());
+        assert!(encoder.add_packet(&packets[1]).is_none());
+        let (protected, parity) = encoder.add_packet(&packets[2]).unwrap();
+        assert_eq!(protected.iter().map(|(sequence, _)| *sequence).collect::<Vec<_>>(), vec![0, 2, 3]);
+
+        // Second packet is lost
+        assert!(decoder.process_packet(&packets[0], Duration::ZERO));
+        assert!(decoder.process_packet(&packets[2], Duration::ZERO));
+        decoder.process_parity(protected, parity, Duration::ZERO);
+
+        let recovered = decoder.recover_packets(Duration::ZERO);
+        assert_eq!(recovered, vec![small_packet(2, &[2; 100])]);
+        assert_eq!(decoder.packets_recovered(), 1);
+
+        // The lost packet arrives late and is ignored
+        assert!(!decoder.process_packet(&packets[1], Duration::ZERO));
+    }
+
+    #[test]
+    fn wait_for_missing_packets() {
+        let mut encoder = FecEncoder::new(3);
+        let mut decoder = FecDecoder::new(0);
+        let packets = [small_packet(0, &[1; 10]), small_packet(1, &[2; 10]), small_packet(2, &[3; 10])];
+
+        encoder.add_packet(&packets[0]);
+        encoder.add_packet(&packets[1]);
+        let (protected, parity) = encoder.add_packet(&packets[2]).unwrap();
+
+        // Two packets are missing, nothing can be recovered yet
+        decoder.process_packet(&packets[0], Duration::ZERO);
+        decoder.process_parity(protected, parity, Duration::ZERO);
+        assert!(decoder.recover_packets(Duration::ZERO).is_empty());
+
+        // A delayed packet arrives
+        decoder.process_packet(&packets[1], Duration::ZERO);
+        assert_eq!(decoder.recover_packets(Duration::ZERO), vec![small_packet(2, &[3; 10])]);
+    }
+
+    #[test]
+    fn discard_old_groups() {
+        let mut encoder = FecEncoder::new(2);
+        let mut decoder = FecDecoder::new(0);
+        let packets = [small_packet(0, &[1; 10]), small_packet(1, &[2; 10])];
+
+        encoder.add_packet(&packets[0]);
+        let (protected, parity) = encoder.add_packet(&packets[1]).unwrap();
+        decoder.process_parity(protected, parity, Duration::ZERO);
+
+        decoder.discard_old(FEC_WINDOW);
+        decoder.process_packet(&packets[0], FEC_WINDOW);
+        assert!(decoder.recover_packets(FEC_WINDOW).is_empty());
+        assert_eq!(decoder.packets_recovered(), 0);
+    }
+}
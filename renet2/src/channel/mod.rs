@@ -1,3 +1,4 @@
+pub(crate) mod fec;
 pub(crate) mod reliable;
 pub(crate) mod slice_constructor;
 pub(crate) mod unreliable;
@@ -7,16 +8,31 @@ use std::time::Duration;
 pub(crate) use slice_constructor::SliceConstructor;
 
 /// Delivery guarantee of a channel
+///
+/// With the `serde` feature, resend times are written as human-friendly durations such as `"300ms"`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SendType {
     // Messages can be lost or received out of order.
     Unreliable,
+    /// Messages can be lost or received out of order, but a lost packet can be recovered from parity data.
+    ///
+    /// A parity packet is sent after every `group_size` packets, so the bandwidth overhead is `1 / group_size`.
+    /// One lost packet can be recovered in each group. The group size must be between 2 and 8.
+    ///
+    /// The last packets sent wait until the group is complete before being protected,
+    /// so this is best suited for channels that send packets every tick.
+    UnreliableFec {
+        group_size: usize,
+    },
     /// Messages are guaranteed to be received and in the same order they were sent.
     ReliableOrdered {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
         resend_time: Duration,
     },
     /// Messages are guaranteed to be received but may be in an different order that they were sent.
     ReliableUnordered {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
         resend_time: Duration,
     },
 }
@@ -24,6 +40,7 @@ pub enum SendType {
 /// Configuration of a channel for a server or client
 /// Channels are unidirectional and message based.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChannelConfig {
     /// Channel identifier, must be unique within its own list,
     /// but it can be repeated between the server and client lists.
@@ -32,10 +49,71 @@ pub struct ChannelConfig {
     /// Unreliable channels will drop new messages when this value is reached.
     /// Reliable channels will cause a disconnect when this value is reached.
     pub max_memory_usage_bytes: usize,
+    /// Maximum size in bytes of a single message.
+    /// Unreliable channels will drop sent messages above this size.
+    /// Reliable channels will cause a disconnect when sending a message above this size.
+    /// Receiving a message above this size will cause a disconnect.
+    /// Default: 5 MiB
+    #[cfg_attr(feature = "serde", serde(default = "default_max_message_size"))]
+    pub max_message_size: usize,
+    /// Maximum number of sliced messages that may be partially received at the same time.
+    /// Unreliable channels will drop the oldest partial message when this value is reached.
+    /// Reliable channels will only send the slices of this many messages at a time,
+    /// and will cause a disconnect when receiving more partial messages.
+    /// Default: 64
+    #[cfg_attr(feature = "serde", serde(default = "default_max_partial_messages"))]
+    pub max_partial_messages: usize,
     /// Delivery guarantee of the channel.
     pub send_type: SendType,
 }
 
+#[cfg(feature = "serde")]
+fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
+}
+
+#[cfg(feature = "serde")]
+fn default_max_partial_messages() -> usize {
+    DEFAULT_MAX_PARTIAL_MESSAGES
+}
+
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 5 * 1024 * 1024;
+const DEFAULT_MAX_PARTIAL_MESSAGES: usize = 64;
+
+/// Identifies a message sent with [`RenetClient::send_message_with_handle`](crate::RenetClient::send_message_with_handle)
+/// while it is pending in its channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MessageHandle {
+    channel_id: u8,
+    message_id: u64,
+}
+
+impl MessageHandle {
+    pub(crate) fn new(channel_id: u8, message_id: u64) -> Self {
+        Self { channel_id, message_id }
+    }
+
+    pub(crate) fn message_id(&self) -> u64 {
+        self.message_id
+    }
+
+    /// Returns the channel of the message.
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+}
+
+/// A message waiting in a send channel.
+///
+/// Unreliable messages are pending until they are sent, and reliable messages until they are acked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingMessage {
+    pub handle: MessageHandle,
+    pub size_bytes: usize,
+    /// Whether the message, or any of its slices, was already sent at least once.
+    pub sent: bool,
+}
+
 /// Utility enumerator when using the default channels configuration.
 /// The default configuration has 3 channels: unreliable, reliable ordered, and reliable unordered.
 pub enum DefaultChannel {
@@ -60,11 +138,15 @@ impl DefaultChannel {
             ChannelConfig {
                 channel_id: 0,
                 max_memory_usage_bytes: 5 * 1024 * 1024,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                max_partial_messages: DEFAULT_MAX_PARTIAL_MESSAGES,
                 send_type: SendType::Unreliable,
             },
             ChannelConfig {
                 channel_id: 1,
                 max_memory_usage_bytes: 5 * 1024 * 1024,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                max_partial_messages: DEFAULT_MAX_PARTIAL_MESSAGES,
                 send_type: SendType::ReliableUnordered {
                     resend_time: Duration::from_millis(300),
                 },
@@ -72,6 +154,8 @@ impl DefaultChannel {
             ChannelConfig {
                 channel_id: 2,
                 max_memory_usage_bytes: 5 * 1024 * 1024,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                max_partial_messages: DEFAULT_MAX_PARTIAL_MESSAGES,
                 send_type: SendType::ReliableOrdered {
                     resend_time: Duration::from_millis(300),
                 },
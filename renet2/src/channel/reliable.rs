@@ -5,7 +5,7 @@ use std::{
 
 use bytes::Bytes;
 
-use super::SliceConstructor;
+use super::{MessageHandle, PendingMessage, SliceConstructor};
 use crate::{
     error::ChannelError,
     packet::{Packet, Slice, SLICE_SIZE},
@@ -25,6 +25,10 @@ enum UnackedMessage {
         acked: Vec<bool>,
         last_sent: Vec<Option<Duration>>,
     },
+    // The message was cancelled, the receiver is told to skip it
+    Cancelled {
+        last_sent: Option<Duration>,
+    },
 }
 
 #[derive(Debug)]
@@ -35,6 +39,10 @@ pub struct SendChannelReliable {
     resend_time: Duration,
     max_memory_usage_bytes: usize,
     memory_usage_bytes: usize,
+    max_message_size: usize,
+    max_partial_messages: usize,
+    cancellable: bool,
+    num_resends: u64,
 }
 
 #[derive(Debug)]
@@ -43,9 +51,17 @@ enum ReliableOrder {
     Unordered {
         most_recent_message_id: u64,
         received_messages: BTreeSet<u64>,
+        // Cancelled messages that are not yet the oldest pending message, each uses `CANCELLED_MESSAGE_BYTES` of memory
+        cancelled_messages: BTreeSet<u64>,
     },
 }
 
+// Memory charged for each cancelled message id kept by the receiver
+const CANCELLED_MESSAGE_BYTES: usize = std::mem::size_of::<u64>();
+
+// Cancelled message ids can be at most this far ahead of the oldest pending message
+const MAX_CANCELLED_MESSAGE_DISTANCE: u64 = 1 << 16;
+
 #[derive(Debug)]
 pub struct ReceiveChannelReliable {
     slices: HashMap<u64, SliceConstructor>,
@@ -54,6 +70,8 @@ pub struct ReceiveChannelReliable {
     reliable_order: ReliableOrder,
     memory_usage_bytes: usize,
     max_memory_usage_bytes: usize,
+    max_message_size: usize,
+    max_partial_messages: usize,
 }
 
 impl UnackedMessage {
@@ -80,15 +98,84 @@ impl SendChannelReliable {
             resend_time,
             max_memory_usage_bytes,
             memory_usage_bytes: 0,
+            max_message_size: usize::MAX,
+            max_partial_messages: usize::MAX,
+            cancellable: false,
+            num_resends: 0,
         }
     }
 
+    /// Limits the size of messages and the number of sliced messages in flight at the same time.
+    pub fn with_message_limits(mut self, max_message_size: usize, max_partial_messages: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self.max_partial_messages = max_partial_messages;
+        self
+    }
+
+    /// Allows cancelling messages that were not acked yet.
+    /// Only for unordered channels, since ordered receivers can't skip messages.
+    pub fn with_cancellation(mut self) -> Self {
+        self.cancellable = true;
+        self
+    }
+
     pub fn available_memory(&self) -> usize {
         self.max_memory_usage_bytes - self.memory_usage_bytes
     }
 
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
+    /// Number of messages and message slices that were sent again because they weren't acked in time.
+    pub fn num_resends(&self) -> u64 {
+        self.num_resends
+    }
+
     pub fn can_send_message(&self, size_bytes: usize) -> bool {
-        size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
+        size_bytes <= self.max_message_size && size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
+    }
+
+    /// Returns the number of message bytes that are waiting to be sent or resent.
+    pub fn bytes_to_send(&self, current_time: Duration) -> u64 {
+        let is_due = |last_sent: Option<Duration>| match last_sent {
+            Some(last_sent) => current_time - last_sent >= self.resend_time,
+            None => true,
+        };
+
+        let mut bytes = 0;
+        let mut num_sliced_messages = 0;
+        for unacked_message in self.unacked_messages.values() {
+            match unacked_message {
+                UnackedMessage::Small { message, last_sent } => {
+                    if is_due(*last_sent) {
+                        bytes += message.len() as u64;
+                    }
+                }
+                UnackedMessage::Cancelled { .. } => {}
+                UnackedMessage::Sliced {
+                    message,
+                    num_slices,
+                    acked,
+                    last_sent,
+                    ..
+                } => {
+                    num_sliced_messages += 1;
+                    if num_sliced_messages > self.max_partial_messages {
+                        continue;
+                    }
+
+                    for i in 0..*num_slices {
+                        if !acked[i] && is_due(last_sent[i]) {
+                            let end = if i == *num_slices - 1 { message.len() } else { (i + 1) * SLICE_SIZE };
+                            bytes += (end - i * SLICE_SIZE) as u64;
+                        }
+                    }
+                }
+            }
+        }
+
+        bytes
     }
 
     pub fn get_packets_to_send(&mut self, packet_sequence: &mut u64, available_bytes: &mut u64, current_time: Duration) -> Vec<Packet> {
@@ -100,6 +187,9 @@ impl SendChannelReliable {
 
         let mut small_messages: Vec<(u64, Bytes)> = vec![];
         let mut small_messages_bytes = 0;
+        let mut num_sliced_messages = 0;
+        let mut cancelled_messages: Vec<u64> = vec![];
+        let mut cancelled_messages_bytes = 0;
 
         'messages: for (&message_id, unacked_message) in self.unacked_messages.iter_mut() {
             match unacked_message {
@@ -113,6 +203,7 @@ impl SendChannelReliable {
                         if current_time - *last_sent < self.resend_time {
                             continue;
                         }
+                        self.num_resends += 1;
                     }
 
                     *available_bytes -= message.len() as u64;
@@ -135,6 +226,34 @@ impl SendChannelReliable {
 
                     continue;
                 }
+                UnackedMessage::Cancelled { last_sent } => {
+                    if let Some(last_sent) = last_sent {
+                        if current_time - *last_sent < self.resend_time {
+                            continue;
+                        }
+                    }
+
+                    let serialized_size = octets::varint_len(message_id);
+                    if *available_bytes < serialized_size as u64 {
+                        // Skip cancellation, no bytes available to send it
+                        continue;
+                    }
+                    *available_bytes -= serialized_size as u64;
+
+                    if cancelled_messages_bytes + serialized_size > SLICE_SIZE {
+                        packets.push(Packet::ReliableCancel {
+                            sequence: *packet_sequence,
+                            channel_id: self.channel_id,
+                            message_ids: std::mem::take(&mut cancelled_messages),
+                        });
+                        cancelled_messages_bytes = 0;
+                        *packet_sequence += 1;
+                    }
+
+                    cancelled_messages_bytes += serialized_size;
+                    cancelled_messages.push(message_id);
+                    *last_sent = Some(current_time);
+                }
                 UnackedMessage::Sliced {
                     message,
                     num_slices,
@@ -143,13 +262,15 @@ impl SendChannelReliable {
                     next_slice_to_send,
                     ..
                 } => {
+                    // Only send slices of the oldest sliced messages,
+                    // so the receiver never has more partial messages than it accepts
+                    num_sliced_messages += 1;
+                    if num_sliced_messages > self.max_partial_messages {
+                        continue;
+                    }
+
                     let start_index = *next_slice_to_send;
                     for i in 0..*num_slices {
-                        if *available_bytes < SLICE_SIZE as u64 {
-                            // Skip message, no bytes available to send a slice
-                            continue 'messages;
-                        }
-
                         let i = (start_index + i) % *num_slices;
                         if acked[i] {
                             continue;
@@ -163,6 +284,14 @@ impl SendChannelReliable {
 
                         let start = i * SLICE_SIZE;
                         let end = if i == *num_slices - 1 { message.len() } else { (i + 1) * SLICE_SIZE };
+                        if *available_bytes < (end - start) as u64 {
+                            // Skip message, no bytes available to send the slice
+                            continue 'messages;
+                        }
+
+                        if last_sent[i].is_some() {
+                            self.num_resends += 1;
+                        }
 
                         let payload = message.slice(start..end);
                         *available_bytes -= payload.len() as u64;
@@ -198,10 +327,24 @@ impl SendChannelReliable {
             *packet_sequence += 1;
         }
 
+        if !cancelled_messages.is_empty() {
+            packets.push(Packet::ReliableCancel {
+                sequence: *packet_sequence,
+                channel_id: self.channel_id,
+                message_ids: cancelled_messages,
+            });
+            *packet_sequence += 1;
+        }
+
         packets
     }
 
-    pub fn send_message(&mut self, message: Bytes) -> Result<(), ChannelError> {
+    /// Queues a message, returns its id.
+    pub fn send_message(&mut self, message: Bytes) -> Result<u64, ChannelError> {
+        if message.len() > self.max_message_size {
+            return Err(ChannelError::MessageAboveMaxSize);
+        }
+
         if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
             return Err(ChannelError::ReliableChannelMaxMemoryReached);
         }
@@ -213,19 +356,60 @@ impl SendChannelReliable {
             UnackedMessage::Small { message, last_sent: None }
         };
 
-        self.unacked_messages.insert(self.next_reliable_message_id, unacked_message);
+        let message_id = self.next_reliable_message_id;
+        self.unacked_messages.insert(message_id, unacked_message);
         self.next_reliable_message_id += 1;
 
-        Ok(())
+        Ok(message_id)
     }
 
-    pub fn process_message_ack(&mut self, message_id: u64) {
-        if self.unacked_messages.contains_key(&message_id) {
-            let unacked_message = self.unacked_messages.remove(&message_id).unwrap();
-            let UnackedMessage::Small { message: payload, .. } = unacked_message else {
-                unreachable!("called ack on small message but found sliced");
+    pub fn pending_messages(&self) -> Vec<PendingMessage> {
+        let mut pending_messages = Vec::new();
+        for (&message_id, unacked_message) in self.unacked_messages.iter() {
+            let (size_bytes, sent) = match unacked_message {
+                UnackedMessage::Small { message, last_sent } => (message.len(), last_sent.is_some()),
+                UnackedMessage::Sliced { message, last_sent, .. } => (message.len(), last_sent.iter().any(Option::is_some)),
+                UnackedMessage::Cancelled { .. } => continue,
             };
-            self.memory_usage_bytes -= payload.len();
+
+            pending_messages.push(PendingMessage {
+                handle: MessageHandle::new(self.channel_id, message_id),
+                size_bytes,
+                sent,
+            });
+        }
+
+        pending_messages
+    }
+
+    /// Cancels a message that was not acked yet, releasing its memory.
+    /// Returns false if the channel doesn't allow cancellation or the message is not pending.
+    pub fn cancel_message(&mut self, message_id: u64) -> bool {
+        if !self.cancellable {
+            return false;
+        }
+
+        let Some(unacked_message) = self.unacked_messages.get_mut(&message_id) else {
+            return false;
+        };
+
+        match unacked_message {
+            UnackedMessage::Small { message, .. } | UnackedMessage::Sliced { message, .. } => {
+                self.memory_usage_bytes -= message.len();
+            }
+            UnackedMessage::Cancelled { .. } => return false,
+        }
+
+        *unacked_message = UnackedMessage::Cancelled { last_sent: None };
+        true
+    }
+
+    pub fn process_message_ack(&mut self, message_id: u64) {
+        match self.unacked_messages.remove(&message_id) {
+            Some(UnackedMessage::Small { message: payload, .. }) => self.memory_usage_bytes -= payload.len(),
+            // Either the cancelled message itself or its cancellation was received
+            Some(UnackedMessage::Cancelled { .. }) | None => {}
+            Some(UnackedMessage::Sliced { .. }) => unreachable!("called ack on small message but found sliced"),
         }
     }
 
@@ -242,6 +426,10 @@ impl SendChannelReliable {
             ..
         } = unacked_message
         else {
+            if matches!(unacked_message, UnackedMessage::Cancelled { .. }) {
+                // Slice was sent before the message was cancelled
+                return;
+            }
             unreachable!("called ack on sliced message but found small");
         };
 
@@ -266,6 +454,7 @@ impl ReceiveChannelReliable {
             false => ReliableOrder::Unordered {
                 most_recent_message_id: 0,
                 received_messages: BTreeSet::new(),
+                cancelled_messages: BTreeSet::new(),
             },
         };
         Self {
@@ -275,15 +464,28 @@ impl ReceiveChannelReliable {
             reliable_order,
             memory_usage_bytes: 0,
             max_memory_usage_bytes,
+            max_message_size: usize::MAX,
+            max_partial_messages: usize::MAX,
         }
     }
 
+    /// Limits the size of received messages and the number of partially received sliced messages.
+    pub fn with_message_limits(mut self, max_message_size: usize, max_partial_messages: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self.max_partial_messages = max_partial_messages;
+        self
+    }
+
     pub fn process_message(&mut self, message: Bytes, message_id: u64) -> Result<(), ChannelError> {
         if message_id < self.oldest_pending_message_id {
             // Discard old message already received
             return Ok(());
         }
 
+        if message.len() > self.max_message_size {
+            return Err(ChannelError::MessageAboveMaxSize);
+        }
+
         match &mut self.reliable_order {
             ReliableOrder::Ordered => {
                 if let btree_map::Entry::Vacant(entry) = self.messages.entry(message_id) {
@@ -298,12 +500,13 @@ impl ReceiveChannelReliable {
             ReliableOrder::Unordered {
                 most_recent_message_id,
                 received_messages,
+                cancelled_messages,
             } => {
                 if *most_recent_message_id < message_id {
                     *most_recent_message_id = message_id;
                 }
 
-                if !received_messages.contains(&message_id) {
+                if !received_messages.contains(&message_id) && !cancelled_messages.contains(&message_id) {
                     if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
                         return Err(ChannelError::ReliableChannelMaxMemoryReached);
                     }
@@ -324,7 +527,26 @@ impl ReceiveChannelReliable {
             return Ok(());
         }
 
+        if let ReliableOrder::Unordered {
+            received_messages,
+            cancelled_messages,
+            ..
+        } = &self.reliable_order
+        {
+            if received_messages.contains(&slice.message_id) || cancelled_messages.contains(&slice.message_id) {
+                // Message already assembled and received
+                return Ok(());
+            }
+        }
+
         if !self.slices.contains_key(&slice.message_id) {
+            if slice.num_slices > self.max_message_size.div_ceil(SLICE_SIZE) {
+                return Err(ChannelError::MessageAboveMaxSize);
+            }
+            if self.slices.len() >= self.max_partial_messages {
+                return Err(ChannelError::TooManyPartialMessages);
+            }
+
             let message_len = slice.num_slices * SLICE_SIZE;
             if self.memory_usage_bytes + message_len > self.max_memory_usage_bytes {
                 return Err(ChannelError::ReliableChannelMaxMemoryReached);
@@ -347,6 +569,76 @@ impl ReceiveChannelReliable {
         Ok(())
     }
 
+    /// Skips a message that was cancelled by the sender.
+    ///
+    /// Cancelled messages use memory until all the messages before them are received,
+    /// and they can't be too far ahead of the oldest pending message.
+    pub fn process_cancel(&mut self, message_id: u64) -> Result<(), ChannelError> {
+        let ReliableOrder::Unordered {
+            received_messages,
+            cancelled_messages,
+            ..
+        } = &mut self.reliable_order
+        else {
+            return Err(ChannelError::InvalidCancelledMessage);
+        };
+
+        if message_id < self.oldest_pending_message_id
+            || received_messages.contains(&message_id)
+            || cancelled_messages.contains(&message_id)
+        {
+            // Message already received
+            return Ok(());
+        }
+
+        if message_id - self.oldest_pending_message_id >= MAX_CANCELLED_MESSAGE_DISTANCE {
+            return Err(ChannelError::CancelledMessageTooFarAhead);
+        }
+
+        if let Some(slice_constructor) = self.slices.remove(&message_id) {
+            self.memory_usage_bytes -= slice_constructor.num_slices * SLICE_SIZE;
+        }
+
+        if self.memory_usage_bytes + CANCELLED_MESSAGE_BYTES > self.max_memory_usage_bytes {
+            return Err(ChannelError::ReliableChannelMaxMemoryReached);
+        }
+        self.memory_usage_bytes += CANCELLED_MESSAGE_BYTES;
+
+        cancelled_messages.insert(message_id);
+        if self.oldest_pending_message_id == message_id {
+            Self::remove_received_messages(
+                &mut self.oldest_pending_message_id,
+                received_messages,
+                cancelled_messages,
+                &mut self.memory_usage_bytes,
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
+    // Remove all next items that could have been received out of order or cancelled,
+    // until we find an message that was not received
+    fn remove_received_messages(
+        oldest_pending_message_id: &mut u64,
+        received_messages: &mut BTreeSet<u64>,
+        cancelled_messages: &mut BTreeSet<u64>,
+        memory_usage_bytes: &mut usize,
+    ) {
+        loop {
+            if cancelled_messages.remove(oldest_pending_message_id) {
+                *memory_usage_bytes -= CANCELLED_MESSAGE_BYTES;
+            } else if !received_messages.remove(oldest_pending_message_id) {
+                break;
+            }
+            *oldest_pending_message_id += 1;
+        }
+    }
+
     pub fn receive_message(&mut self) -> Option<Bytes> {
         match &mut self.reliable_order {
             ReliableOrder::Ordered => {
@@ -356,16 +648,20 @@ impl ReceiveChannelReliable {
                 self.memory_usage_bytes -= message.len();
                 Some(message)
             }
-            ReliableOrder::Unordered { received_messages, .. } => {
+            ReliableOrder::Unordered {
+                received_messages,
+                cancelled_messages,
+                ..
+            } => {
                 let (message_id, message) = self.messages.pop_first()?;
 
                 if self.oldest_pending_message_id == message_id {
-                    // Remove all next items that could have been received out of order,
-                    // until we find an message that was not received
-                    while received_messages.contains(&self.oldest_pending_message_id) {
-                        received_messages.remove(&self.oldest_pending_message_id);
-                        self.oldest_pending_message_id += 1;
-                    }
+                    Self::remove_received_messages(
+                        &mut self.oldest_pending_message_id,
+                        received_messages,
+                        cancelled_messages,
+                        &mut self.memory_usage_bytes,
+                    );
                 }
 
                 self.memory_usage_bytes -= message.len();
@@ -481,6 +777,7 @@ mod tests {
             ReliableOrder::Unordered {
                 most_recent_message_id,
                 received_messages,
+                ..
             } => {
                 assert_eq!(*most_recent_message_id, 2);
                 assert!(received_messages.is_empty());
@@ -645,4 +942,194 @@ mod tests {
             assert!(len < 1300);
         }
     }
+
+    #[test]
+    fn max_message_size() {
+        let resend_time = Duration::from_millis(100);
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, true).with_message_limits(SLICE_SIZE * 2, usize::MAX);
+        let mut send = SendChannelReliable::new(0, resend_time, usize::MAX).with_message_limits(SLICE_SIZE * 2, usize::MAX);
+
+        assert!(send.can_send_message(SLICE_SIZE * 2));
+        assert!(!send.can_send_message(SLICE_SIZE * 2 + 1));
+        assert_eq!(
+            send.send_message(vec![0; SLICE_SIZE * 2 + 1].into()),
+            Err(ChannelError::MessageAboveMaxSize)
+        );
+
+        // Peer claims more slices than the maximum message size allows
+        let slice = Slice {
+            message_id: 0,
+            slice_index: 0,
+            num_slices: 3,
+            payload: vec![0; SLICE_SIZE].into(),
+        };
+        assert_eq!(recv.process_slice(slice), Err(ChannelError::MessageAboveMaxSize));
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    #[test]
+    fn max_partial_messages() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let current_time: Duration = Duration::ZERO;
+        let resend_time = Duration::from_millis(100);
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, false).with_message_limits(usize::MAX, 2);
+        let mut send = SendChannelReliable::new(0, resend_time, usize::MAX).with_message_limits(usize::MAX, 2);
+
+        for _ in 0..3 {
+            send.send_message(vec![5; SLICE_SIZE * 2].into()).unwrap();
+        }
+
+        // Only the slices of the first two messages are sent
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
+        assert_eq!(packets.len(), 4);
+        for packet in packets {
+            let Packet::ReliableSlice { slice, .. } = packet else {
+                unreachable!();
+            };
+            assert!(slice.message_id < 2);
+            // Only the first slice of each message arrives
+            if slice.slice_index == 0 {
+                recv.process_slice(slice).unwrap();
+            }
+        }
+
+        let slice = Slice {
+            message_id: 2,
+            slice_index: 0,
+            num_slices: 2,
+            payload: vec![5; SLICE_SIZE].into(),
+        };
+        assert_eq!(recv.process_slice(slice), Err(ChannelError::TooManyPartialMessages));
+
+        // Once the first message is acked, the third one can be sent
+        send.process_slice_message_ack(0, 0);
+        send.process_slice_message_ack(0, 1);
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time + resend_time);
+        assert!(packets
+            .iter()
+            .any(|packet| matches!(packet, Packet::ReliableSlice { slice, .. } if slice.message_id == 2)));
+    }
+
+    #[test]
+    fn ignore_slices_of_received_unordered_message() {
+        let mut recv = ReceiveChannelReliable::new(usize::MAX, false).with_message_limits(usize::MAX, 1);
+        let slices: Vec<Slice> = (0..2)
+            .map(|slice_index| Slice {
+                message_id: 1,
+                slice_index,
+                num_slices: 2,
+                payload: vec![5; SLICE_SIZE].into(),
+            })
+            .collect();
+
+        for slice in slices.iter() {
+            recv.process_slice(slice.clone()).unwrap();
+        }
+        assert!(recv.receive_message().is_some());
+
+        // A resent slice of the received message doesn't start a new partial message
+        recv.process_slice(slices[0].clone()).unwrap();
+        assert_eq!(recv.memory_usage(), 0);
+    }
+
+    #[test]
+    fn cancel_message() {
+        let max_memory: usize = 10000;
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let current_time: Duration = Duration::ZERO;
+        let resend_time = Duration::from_millis(100);
+        let mut recv = ReceiveChannelReliable::new(max_memory, false);
+        let mut send = SendChannelReliable::new(0, resend_time, max_memory).with_cancellation();
+
+        let small_message = Bytes::from(vec![1, 2, 3]);
+        let sliced_message = Bytes::from(vec![5; SLICE_SIZE * 2 + 10]);
+        send.send_message(small_message.clone()).unwrap();
+        send.send_message(sliced_message.clone()).unwrap();
+        send.send_message(small_message.clone()).unwrap();
+
+        // Only the first slice of the sliced message arrives
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
+        for packet in packets {
+            match packet {
+                Packet::SmallReliable { messages, .. } => {
+                    for (message_id, message) in messages {
+                        recv.process_message(message, message_id).unwrap();
+                    }
+                }
+                Packet::ReliableSlice { slice, .. } if slice.slice_index == 0 => recv.process_slice(slice).unwrap(),
+                _ => {}
+            }
+        }
+        assert_eq!(recv.memory_usage(), small_message.len() * 2 + SLICE_SIZE * 3);
+
+        assert!(send.cancel_message(1));
+        assert!(!send.cancel_message(1));
+        assert_eq!(send.memory_usage(), small_message.len() * 2);
+        let pending: Vec<u64> = send.pending_messages().iter().map(|pending| pending.handle.message_id()).collect();
+        assert_eq!(pending, vec![0, 2]);
+
+        // Cancellations use the available bytes
+        let mut no_bytes = 0;
+        assert!(send.get_packets_to_send(&mut sequence, &mut no_bytes, current_time).is_empty());
+
+        // The cancellation is sent once there are bytes available, and the receiver releases the partial message
+        let mut available_bytes = 10;
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes, current_time);
+        assert_eq!(available_bytes, 9);
+        assert_eq!(packets.len(), 1);
+        let Packet::ReliableCancel { message_ids, .. } = &packets[0] else {
+            unreachable!();
+        };
+        assert_eq!(message_ids, &vec![1]);
+        recv.process_cancel(1).unwrap();
+        assert_eq!(recv.receive_message(), Some(small_message.clone()));
+        assert_eq!(recv.receive_message(), Some(small_message.clone()));
+        assert_eq!(recv.receive_message(), None);
+        assert_eq!(recv.memory_usage(), 0);
+        assert_eq!(recv.oldest_pending_message_id, 3);
+
+        // Acks of slices sent before cancelling are ignored
+        send.process_slice_message_ack(1, 0);
+        send.process_message_ack(1);
+        send.process_message_ack(0);
+        send.process_message_ack(2);
+        assert!(send.unacked_messages.is_empty());
+
+        // Ordered channels can't cancel messages
+        let mut send = SendChannelReliable::new(0, resend_time, max_memory);
+        send.send_message(small_message).unwrap();
+        assert!(!send.cancel_message(0));
+        let mut recv = ReceiveChannelReliable::new(max_memory, true);
+        assert_eq!(recv.process_cancel(0), Err(ChannelError::InvalidCancelledMessage));
+    }
+
+    #[test]
+    fn cancel_message_flood() {
+        let max_memory: usize = 1000;
+        let mut recv = ReceiveChannelReliable::new(max_memory, false);
+
+        // Cancelled messages ahead of the oldest pending message use memory until it is received
+        for message_id in 1..=10 {
+            recv.process_cancel(message_id).unwrap();
+        }
+        assert_eq!(recv.memory_usage(), 10 * CANCELLED_MESSAGE_BYTES);
+
+        recv.process_message(Bytes::from(vec![1]), 0).unwrap();
+        assert_eq!(recv.receive_message(), Some(Bytes::from(vec![1])));
+        assert_eq!(recv.oldest_pending_message_id, 11);
+        assert_eq!(recv.memory_usage(), 0);
+
+        assert_eq!(
+            recv.process_cancel(11 + MAX_CANCELLED_MESSAGE_DISTANCE),
+            Err(ChannelError::CancelledMessageTooFarAhead)
+        );
+        assert_eq!(recv.process_cancel(u64::MAX), Err(ChannelError::CancelledMessageTooFarAhead));
+
+        // A flood of cancelled messages is limited by the channel memory
+        let result = (12..12 + u16::MAX as u64).try_for_each(|message_id| recv.process_cancel(message_id));
+        assert_eq!(result, Err(ChannelError::ReliableChannelMaxMemoryReached));
+        assert!(recv.memory_usage() <= max_memory);
+    }
 }
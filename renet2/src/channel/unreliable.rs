@@ -6,7 +6,10 @@ use std::{
 use bytes::Bytes;
 
 use crate::{
-    channel::SliceConstructor,
+    channel::{
+        fec::{FecDecoder, FecEncoder},
+        MessageHandle, PendingMessage, SliceConstructor,
+    },
     error::ChannelError,
     packet::{Packet, Slice, SLICE_SIZE},
 };
@@ -14,10 +17,13 @@ use crate::{
 #[derive(Debug)]
 pub struct SendChannelUnreliable {
     channel_id: u8,
-    unreliable_messages: VecDeque<Bytes>,
+    unreliable_messages: VecDeque<(u64, Bytes)>,
+    next_message_id: u64,
     sliced_message_id: u64,
     max_memory_usage_bytes: usize,
     memory_usage_bytes: usize,
+    max_message_size: usize,
+    fec: Option<FecEncoder>,
 }
 
 #[derive(Debug)]
@@ -28,6 +34,9 @@ pub struct ReceiveChannelUnreliable {
     slices_last_received: BTreeMap<u64, Duration>,
     max_memory_usage_bytes: usize,
     memory_usage_bytes: usize,
+    max_message_size: usize,
+    max_partial_messages: usize,
+    fec: Option<FecDecoder>,
 }
 
 impl SendChannelUnreliable {
@@ -35,14 +44,33 @@ impl SendChannelUnreliable {
         Self {
             channel_id,
             unreliable_messages: VecDeque::new(),
+            next_message_id: 0,
             sliced_message_id: 0,
             max_memory_usage_bytes,
             memory_usage_bytes: 0,
+            max_message_size: usize::MAX,
+            fec: None,
         }
     }
 
+    /// Limits the size of messages, bigger messages are dropped.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Enables forward error correction, sending a parity packet after every `group_size` packets.
+    pub fn with_fec(mut self, group_size: usize) -> Self {
+        self.fec = Some(FecEncoder::new(group_size));
+        self
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
     pub fn can_send_message(&self, size_bytes: usize) -> bool {
-        size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
+        size_bytes <= self.max_message_size && size_bytes + self.memory_usage_bytes <= self.max_memory_usage_bytes
     }
 
     pub fn available_memory(&self) -> usize {
@@ -54,7 +82,7 @@ impl SendChannelUnreliable {
         let mut small_messages: Vec<Bytes> = vec![];
         let mut small_messages_bytes = 0;
 
-        while let Some(message) = self.unreliable_messages.pop_front() {
+        while let Some((_, message)) = self.unreliable_messages.pop_front() {
             self.memory_usage_bytes -= message.len();
             if *available_bytes < message.len() as u64 {
                 // Drop message, no available bytes to send
@@ -113,16 +141,48 @@ impl SendChannelUnreliable {
             *packet_sequence += 1;
         }
 
+        if let Some(fec) = &mut self.fec {
+            let mut parity_packets: Vec<Packet> = vec![];
+            for packet in packets.iter() {
+                let Some((protected, parity)) = fec.add_packet(packet) else {
+                    continue;
+                };
+                if *available_bytes < parity.len() as u64 {
+                    // Drop parity, no available bytes to send
+                    continue;
+                }
+
+                *available_bytes -= parity.len() as u64;
+                parity_packets.push(Packet::UnreliableParity {
+                    sequence: *packet_sequence,
+                    channel_id: self.channel_id,
+                    protected,
+                    parity,
+                });
+                *packet_sequence += 1;
+            }
+            packets.append(&mut parity_packets);
+        }
+
         packets
     }
 
-    pub fn send_message(&mut self, message: Bytes) {
+    /// Queues a message, returns its id or None if it was dropped.
+    pub fn send_message(&mut self, message: Bytes) -> Option<u64> {
+        if message.len() > self.max_message_size {
+            log::warn!(
+                "dropped unreliable message sent because it is above the maximum message size of channel {}",
+                self.channel_id
+            );
+            return None;
+        }
+
         if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
             log::warn!(
                 "dropped unreliable message sent because channel {} is memory limited",
                 self.channel_id
             );
-            return;
+            return None;
         }
 
         let num_fragments = message.len() / SLICE_SIZE;
@@ -132,8 +192,34 @@ impl SendChannelUnreliable {
                 Consider breaking your message into smaller ones or using a reliable channel");
         }
 
+        let message_id = self.next_message_id;
+        self.next_message_id += 1;
         self.memory_usage_bytes += message.len();
-        self.unreliable_messages.push_back(message);
+        self.unreliable_messages.push_back((message_id, message));
+
+        Some(message_id)
+    }
+
+    pub fn pending_messages(&self) -> Vec<PendingMessage> {
+        self.unreliable_messages
+            .iter()
+            .map(|(message_id, message)| PendingMessage {
+                handle: MessageHandle::new(self.channel_id, *message_id),
+                size_bytes: message.len(),
+                sent: false,
+            })
+            .collect()
+    }
+
+    /// Removes a message that was not sent yet, returns false if it is not queued.
+    pub fn cancel_message(&mut self, message_id: u64) -> bool {
+        let Some(index) = self.unreliable_messages.iter().position(|(id, _)| *id == message_id) else {
+            return false;
+        };
+
+        let (_, message) = self.unreliable_messages.remove(index).unwrap();
+        self.memory_usage_bytes -= message.len();
+        true
     }
 }
 
@@ -146,24 +232,115 @@ impl ReceiveChannelUnreliable {
             messages: VecDeque::new(),
             memory_usage_bytes: 0,
             max_memory_usage_bytes,
+            max_message_size: usize::MAX,
+            max_partial_messages: usize::MAX,
+            fec: None,
+        }
+    }
+
+    /// Limits the size of received messages and the number of partially received sliced messages.
+    pub fn with_message_limits(mut self, max_message_size: usize, max_partial_messages: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self.max_partial_messages = max_partial_messages;
+        self
+    }
+
+    /// Enables recovery of lost packets from the parity packets sent by a channel with forward error correction.
+    pub fn with_fec(mut self) -> Self {
+        self.fec = Some(FecDecoder::new(self.channel_id));
+        self
+    }
+
+    /// Returns the number of lost packets that were recovered from parity packets.
+    pub fn packets_recovered(&self) -> u64 {
+        self.fec.as_ref().map_or(0, FecDecoder::packets_recovered)
+    }
+
+    /// Processes an unreliable packet, recovering lost packets when forward error correction is enabled.
+    pub fn process_packet(&mut self, packet: Packet, current_time: Duration) -> Result<(), ChannelError> {
+        let Some(fec) = &mut self.fec else {
+            return self.process_data_packet(packet, current_time);
+        };
+
+        match packet {
+            Packet::UnreliableParity { protected, parity, .. } => fec.process_parity(protected, parity, current_time),
+            packet => {
+                if !fec.process_packet(&packet, current_time) {
+                    // Packet was already recovered from parity data
+                    return Ok(());
+                }
+                self.process_data_packet(packet, current_time)?;
+            }
+        }
+
+        let recovered_packets = match &mut self.fec {
+            Some(fec) => fec.recover_packets(current_time),
+            None => vec![],
+        };
+        for packet in recovered_packets {
+            self.process_data_packet(packet, current_time)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_data_packet(&mut self, packet: Packet, current_time: Duration) -> Result<(), ChannelError> {
+        match packet {
+            Packet::SmallUnreliable { messages, .. } => {
+                for message in messages {
+                    self.process_message(message)?;
+                }
+                Ok(())
+            }
+            Packet::UnreliableSlice { slice, .. } => self.process_slice(slice, current_time),
+            Packet::UnreliableParity { .. } => {
+                log::debug!(
+                    "ignored parity packet received because channel {} has no forward error correction",
+                    self.channel_id
+                );
+                Ok(())
+            }
+            _ => Ok(()),
         }
     }
 
-    pub fn process_message(&mut self, message: Bytes) {
+    pub fn process_message(&mut self, message: Bytes) -> Result<(), ChannelError> {
+        if message.len() > self.max_message_size {
+            return Err(ChannelError::MessageAboveMaxSize);
+        }
+
         if self.memory_usage_bytes + message.len() > self.max_memory_usage_bytes {
             log::warn!(
                 "dropped unreliable message received because channel {} is memory limited",
                 self.channel_id
             );
-            return;
+            return Ok(());
         }
 
         self.memory_usage_bytes += message.len();
         self.messages.push_back(message);
+
+        Ok(())
     }
 
     pub fn process_slice(&mut self, slice: Slice, current_time: Duration) -> Result<(), ChannelError> {
         if !self.slices.contains_key(&slice.message_id) {
+            if slice.num_slices > self.max_message_size.div_ceil(SLICE_SIZE) {
+                return Err(ChannelError::MessageAboveMaxSize);
+            }
+
+            if self.slices.len() >= self.max_partial_messages {
+                // Partial messages may never complete when slices are lost, so the oldest one is dropped instead of erroring
+                if let Some((message_id, oldest)) = self.slices.pop_first() {
+                    log::warn!(
+                        "dropped partial unreliable message {message_id} because channel {} has too many partial messages",
+                        self.channel_id
+                    );
+                    self.slices_last_received.remove(&message_id);
+                    self.memory_usage_bytes -= oldest.num_slices * SLICE_SIZE;
+                }
+            }
+
             let message_len = slice.num_slices * SLICE_SIZE;
             if self.memory_usage_bytes + message_len > self.max_memory_usage_bytes {
                 log::warn!(
@@ -185,6 +362,9 @@ impl ReceiveChannelUnreliable {
             self.slices.remove(&slice.message_id);
             self.slices_last_received.remove(&slice.message_id);
             self.memory_usage_bytes -= slice.num_slices * SLICE_SIZE;
+            if message.len() > self.max_message_size {
+                return Err(ChannelError::MessageAboveMaxSize);
+            }
             self.memory_usage_bytes += message.len();
             self.messages.push_back(message);
         } else {
@@ -214,6 +394,16 @@ impl ReceiveChannelUnreliable {
         }
     }
 
+    pub fn discard_old_fec_data(&mut self, current_time: Duration) {
+        if let Some(fec) = &mut self.fec {
+            fec.discard_old(current_time);
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage_bytes
+    }
+
     pub fn receive_message(&mut self) -> Option<Bytes> {
         if let Some(message) = self.messages.pop_front() {
             self.memory_usage_bytes -= message.len();
@@ -250,7 +440,7 @@ mod tests {
                 unreachable!();
             };
             for message in messages {
-                recv.process_message(message);
+                recv.process_message(message).unwrap();
             }
         }
 
@@ -316,7 +506,7 @@ mod tests {
             // Second message was dropped
             assert_eq!(messages.len(), 1);
             for message in messages {
-                recv.process_message(message);
+                recv.process_message(message).unwrap();
             }
         }
 
@@ -379,4 +569,106 @@ mod tests {
             assert!(len < 1300);
         }
     }
+
+    #[test]
+    fn fec_recover_lost_slice() {
+        let max_memory: usize = 100000;
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let current_time = Duration::ZERO;
+        let mut recv = ReceiveChannelUnreliable::new(0, max_memory).with_fec();
+        let mut send = SendChannelUnreliable::new(0, max_memory).with_fec(4);
+
+        let message = vec![5; SLICE_SIZE * 4];
+        send.send_message(message.clone().into());
+
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        assert_eq!(packets.len(), 5);
+        assert!(matches!(packets[4], Packet::UnreliableParity { .. }));
+
+        let mut buffer = [0u8; 1400];
+        for (i, packet) in packets.into_iter().enumerate() {
+            let mut oct = OctetsMut::with_slice(&mut buffer);
+            let len = packet.to_bytes(&mut oct).unwrap();
+            assert!(len < 1300);
+
+            // Lose the second slice
+            if i == 1 {
+                continue;
+            }
+            recv.process_packet(packet, current_time).unwrap();
+        }
+
+        assert_eq!(recv.receive_message().unwrap(), message);
+        assert!(recv.receive_message().is_none());
+        assert_eq!(recv.packets_recovered(), 1);
+    }
+
+    #[test]
+    fn max_message_size() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let current_time = Duration::ZERO;
+        let mut recv = ReceiveChannelUnreliable::new(0, usize::MAX).with_message_limits(10, usize::MAX);
+        let mut send = SendChannelUnreliable::new(0, usize::MAX).with_max_message_size(10);
+
+        assert!(!send.can_send_message(11));
+        send.send_message(vec![0; 11].into());
+        assert!(send.get_packets_to_send(&mut sequence, &mut available_bytes).is_empty());
+
+        assert_eq!(recv.process_message(vec![0; 11].into()), Err(ChannelError::MessageAboveMaxSize));
+        let slice = Slice {
+            message_id: 0,
+            slice_index: 0,
+            num_slices: 2,
+            payload: vec![0; SLICE_SIZE].into(),
+        };
+        assert_eq!(recv.process_slice(slice, current_time), Err(ChannelError::MessageAboveMaxSize));
+    }
+
+    #[test]
+    fn max_partial_messages() {
+        let current_time = Duration::ZERO;
+        let mut recv = ReceiveChannelUnreliable::new(0, usize::MAX).with_message_limits(usize::MAX, 2);
+
+        for message_id in 0..3 {
+            let slice = Slice {
+                message_id,
+                slice_index: 0,
+                num_slices: 2,
+                payload: vec![5; SLICE_SIZE].into(),
+            };
+            recv.process_slice(slice, current_time).unwrap();
+        }
+
+        // The oldest partial message was dropped
+        assert_eq!(recv.slices.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(recv.memory_usage(), 2 * 2 * SLICE_SIZE);
+    }
+
+    #[test]
+    fn cancel_message() {
+        let mut available_bytes = u64::MAX;
+        let mut sequence: u64 = 0;
+        let mut send = SendChannelUnreliable::new(0, 10000);
+
+        let first = send.send_message(vec![1, 2, 3].into()).unwrap();
+        let second = send.send_message(vec![4, 5].into()).unwrap();
+        assert_eq!(send.pending_messages().len(), 2);
+
+        assert!(send.cancel_message(first));
+        assert!(!send.cancel_message(first));
+        assert_eq!(send.memory_usage(), 2);
+        assert_eq!(send.pending_messages()[0].handle.message_id(), second);
+
+        let packets = send.get_packets_to_send(&mut sequence, &mut available_bytes);
+        let [Packet::SmallUnreliable { messages, .. }] = &packets[..] else {
+            unreachable!();
+        };
+        assert_eq!(messages, &vec![Bytes::from(vec![4, 5])]);
+
+        // Sent messages can't be cancelled
+        assert!(!send.cancel_message(second));
+        assert!(send.pending_messages().is_empty());
+    }
 }
@@ -21,6 +21,10 @@ pub enum DisconnectReason {
     SendChannelError { channel_id: u8, error: ChannelError },
     /// Error occurred in a receive channel
     ReceiveChannelError { channel_id: u8, error: ChannelError },
+    /// Client exceeded the rate limit of a channel
+    RateLimited { channel_id: u8 },
+    /// The client and the server were built with different channel configurations
+    ChannelConfigMismatch,
 }
 
 impl std::error::Error for DisconnectReason {}
@@ -32,6 +36,14 @@ pub enum ChannelError {
     ReliableChannelMaxMemoryReached,
     /// Received an invalid slice message in the channel.
     InvalidSliceMessage,
+    /// Message is bigger than the maximum message size of the channel.
+    MessageAboveMaxSize,
+    /// Received a slice of a new message while the channel already has the maximum number of partially received messages.
+    TooManyPartialMessages,
+    /// Received a cancelled message in a channel that doesn't allow cancellation.
+    InvalidCancelledMessage,
+    /// Received a cancelled message too far ahead of the oldest message that was not received yet.
+    CancelledMessageTooFarAhead,
 }
 
 impl fmt::Display for ChannelError {
@@ -41,6 +53,10 @@ impl fmt::Display for ChannelError {
         match *self {
             ReliableChannelMaxMemoryReached => write!(fmt, "reliable channel memory usage was exausted"),
             InvalidSliceMessage => write!(fmt, "received an invalid slice packet"),
+            MessageAboveMaxSize => write!(fmt, "message is above the maximum message size of the channel"),
+            TooManyPartialMessages => write!(fmt, "too many partially received messages in the channel"),
+            InvalidCancelledMessage => write!(fmt, "received a cancelled message in a channel without cancellation"),
+            CancelledMessageTooFarAhead => write!(fmt, "received a cancelled message too far ahead of the pending messages"),
         }
     }
 }
@@ -58,6 +74,8 @@ impl fmt::Display for DisconnectReason {
             ReceivedInvalidChannelId(id) => write!(fmt, "received message with invalid channel {id}"),
             SendChannelError { channel_id, error } => write!(fmt, "send channel {channel_id} with error: {error}"),
             ReceiveChannelError { channel_id, error } => write!(fmt, "receive channel {channel_id} with error: {error}"),
+            RateLimited { channel_id } => write!(fmt, "exceeded the rate limit of channel {channel_id}"),
+            ChannelConfigMismatch => write!(fmt, "the client and the server have different channel configurations"),
         }
     }
 }
@@ -74,3 +92,103 @@ impl fmt::Display for ClientNotFound {
         write!(fmt, "client with given id was not found")
     }
 }
+
+/// Possible errors when managing client groups in the server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupError {
+    /// Group with the given id was not found
+    GroupNotFound,
+    /// Client with the given id was not found
+    ClientNotFound,
+}
+
+impl std::error::Error for GroupError {}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use GroupError::*;
+
+        match *self {
+            GroupNotFound => write!(fmt, "group with given id was not found"),
+            ClientNotFound => write!(fmt, "client with given id was not found"),
+        }
+    }
+}
+
+/// Direction of the channels of a [`ConnectionConfig`](crate::ConnectionConfig).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelDirection {
+    /// Channels in `server_channels_config`
+    ServerToClient,
+    /// Channels in `client_channels_config`
+    ClientToServer,
+}
+
+impl fmt::Display for ChannelDirection {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChannelDirection::ServerToClient => write!(fmt, "server"),
+            ChannelDirection::ClientToServer => write!(fmt, "client"),
+        }
+    }
+}
+
+/// Possible errors in a [`ConnectionConfig`](crate::ConnectionConfig), returned by
+/// [`ConnectionConfig::validate`](crate::ConnectionConfig::validate).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The number of bytes available per tick is zero
+    ZeroAvailableBytesPerTick,
+    /// The heartbeat interval is zero
+    ZeroHeartbeatInterval,
+    /// The channel id is used by more than one channel in the same direction
+    DuplicateChannelId { direction: ChannelDirection, channel_id: u8 },
+    /// The maximum memory usage of the channel is zero
+    ZeroMaxMemoryUsage { direction: ChannelDirection, channel_id: u8 },
+    /// The maximum message size of the channel is zero
+    ZeroMaxMessageSize { direction: ChannelDirection, channel_id: u8 },
+    /// The maximum number of partial messages of the channel is zero
+    ZeroMaxPartialMessages { direction: ChannelDirection, channel_id: u8 },
+    /// The resend time of the reliable channel is zero
+    ZeroResendTime { direction: ChannelDirection, channel_id: u8 },
+    /// The forward error correction group size of the channel is not between 2 and 8
+    InvalidFecGroupSize {
+        direction: ChannelDirection,
+        channel_id: u8,
+        group_size: usize,
+    },
+}
+
+impl std::error::Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use ConfigError::*;
+
+        match *self {
+            ZeroAvailableBytesPerTick => write!(fmt, "available bytes per tick must not be zero"),
+            ZeroHeartbeatInterval => write!(fmt, "heartbeat interval must not be zero"),
+            DuplicateChannelId { direction, channel_id } => write!(fmt, "duplicate {direction} channel {channel_id}"),
+            ZeroMaxMemoryUsage { direction, channel_id } => {
+                write!(fmt, "{direction} channel {channel_id} has a maximum memory usage of zero")
+            }
+            ZeroMaxMessageSize { direction, channel_id } => {
+                write!(fmt, "{direction} channel {channel_id} has a maximum message size of zero")
+            }
+            ZeroMaxPartialMessages { direction, channel_id } => {
+                write!(fmt, "{direction} channel {channel_id} has a maximum of zero partial messages")
+            }
+            ZeroResendTime { direction, channel_id } => {
+                write!(fmt, "{direction} channel {channel_id} has a resend time of zero")
+            }
+            InvalidFecGroupSize {
+                direction,
+                channel_id,
+                group_size,
+            } => write!(
+                fmt,
+                "{direction} channel {channel_id} has an invalid fec group size {group_size}, it must be between 2 and 8"
+            ),
+        }
+    }
+}
@@ -3,17 +3,26 @@
 mod channel;
 mod connection_stats;
 mod error;
+mod lockstep;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod packet;
+mod rate_limit;
 mod remote_connection;
 mod server;
 
-pub use channel::{ChannelConfig, DefaultChannel, SendType};
-pub use error::{ChannelError, ClientNotFound, DisconnectReason};
+pub use channel::{ChannelConfig, DefaultChannel, MessageHandle, PendingMessage, SendType};
+pub use error::{ChannelDirection, ChannelError, ClientNotFound, ConfigError, DisconnectReason, GroupError};
+pub use lockstep::{LockstepInputs, LockstepLag};
 pub use packet::Payload;
-pub use remote_connection::{ConnectionConfig, NetworkInfo, RenetClient, RenetConnectionStatus};
+pub use rate_limit::{RateLimit, RateLimitPolicy};
+pub use remote_connection::{ConnectionConfig, NetworkInfo, PingResult, RenetClient, RenetConnectionStatus};
 pub use server::{RenetServer, ServerEvent};
 
 pub use bytes::Bytes;
 
 /// Unique identifier for clients.
 pub type ClientId = u64;
+
+/// Unique identifier for client groups in a [`RenetServer`].
+pub type GroupId = u64;
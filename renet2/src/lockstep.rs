@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+use crate::{ClientId, RenetClient, RenetServer};
+
+// Size of the frame number prefixed to inputs
+const FRAME_BYTES: usize = 8;
+
+/// Lag of a client in a [`LockstepInputs`] buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockstepLag {
+    /// Latest frame received from the client.
+    pub latest_frame: Option<u64>,
+    /// Number of frames between the latest frame received from any client and from this client.
+    pub frames_behind: u64,
+    /// Round-trip time of the client connection, in seconds.
+    pub rtt: f64,
+}
+
+/// Buffers the inputs of clients per simulation frame for deterministic lockstep.
+///
+/// Clients send their input for each frame with [`LockstepInputs::send_input`] over a
+/// [`SendType::ReliableOrdered`](crate::SendType::ReliableOrdered) channel. The server receives them with
+/// [`LockstepInputs::receive`], and advances the simulation with [`LockstepInputs::take_frame`] once
+/// the inputs of all the clients for the next frame are present.
+#[derive(Debug)]
+pub struct LockstepInputs {
+    channel_id: u8,
+    max_frames_ahead: u64,
+    next_frame: u64,
+    inputs: BTreeMap<u64, HashMap<ClientId, Bytes>>,
+    latest_frames: HashMap<ClientId, Option<u64>>,
+    current_time: Duration,
+    waiting_since: Duration,
+}
+
+impl LockstepInputs {
+    /// Creates a buffer for the inputs received in a channel, starting at frame 0.
+    pub fn new<I: Into<u8>>(channel_id: I) -> Self {
+        Self {
+            channel_id: channel_id.into(),
+            max_frames_ahead: 256,
+            next_frame: 0,
+            inputs: BTreeMap::new(),
+            latest_frames: HashMap::new(),
+            current_time: Duration::ZERO,
+            waiting_since: Duration::ZERO,
+        }
+    }
+
+    /// Sets how many frames after the next frame can be buffered, inputs for later frames are dropped.
+    /// Default: 256
+    pub fn with_max_frames_ahead(mut self, max_frames_ahead: u64) -> Self {
+        self.max_frames_ahead = max_frames_ahead;
+        self
+    }
+
+    /// Sends the input of the client for a frame.
+    pub fn send_input<I: Into<u8>>(client: &mut RenetClient, channel_id: I, frame: u64, input: &[u8]) {
+        let mut message = Vec::with_capacity(FRAME_BYTES + input.len());
+        message.extend_from_slice(&frame.to_le_bytes());
+        message.extend_from_slice(input);
+        client.send_message(channel_id, message);
+    }
+
+    /// Adds a client to the simulation, frames are not ready until its inputs are received.
+    pub fn add_client(&mut self, client_id: ClientId) {
+        self.latest_frames.entry(client_id).or_insert(None);
+    }
+
+    /// Removes a client from the simulation, discarding its buffered inputs.
+    pub fn remove_client(&mut self, client_id: ClientId) {
+        self.latest_frames.remove(&client_id);
+        for inputs in self.inputs.values_mut() {
+            inputs.remove(&client_id);
+        }
+    }
+
+    /// Returns the clients in the simulation.
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.latest_frames.keys().copied().collect()
+    }
+
+    /// Advances the time used for stall detection.
+    /// Should be called every tick
+    pub fn update(&mut self, duration: Duration) {
+        self.current_time += duration;
+    }
+
+    /// Receives the inputs sent by the clients in the simulation.
+    pub fn receive(&mut self, server: &mut RenetServer) {
+        for (&client_id, latest_frame) in self.latest_frames.iter_mut() {
+            while let Some(message) = server.receive_message(client_id, self.channel_id) {
+                if message.len() < FRAME_BYTES {
+                    log::warn!("dropped lockstep input from client {client_id} without a frame number");
+                    continue;
+                }
+
+                let frame = u64::from_le_bytes(message[..FRAME_BYTES].try_into().unwrap());
+                if frame < self.next_frame {
+                    log::debug!("dropped lockstep input from client {client_id} for past frame {frame}");
+                    continue;
+                }
+                if frame - self.next_frame > self.max_frames_ahead {
+                    log::warn!("dropped lockstep input from client {client_id} for frame {frame}, too far ahead");
+                    continue;
+                }
+
+                if latest_frame.is_none_or(|latest_frame| latest_frame < frame) {
+                    *latest_frame = Some(frame);
+                }
+                self.inputs
+                    .entry(frame)
+                    .or_default()
+                    .entry(client_id)
+                    .or_insert_with(|| message.slice(FRAME_BYTES..));
+            }
+        }
+    }
+
+    /// Returns the next frame to be taken.
+    pub fn next_frame(&self) -> u64 {
+        self.next_frame
+    }
+
+    /// Returns whether the inputs of all clients for a frame are present.
+    pub fn is_frame_ready(&self, frame: u64) -> bool {
+        if frame < self.next_frame {
+            return false;
+        }
+
+        let num_inputs = self.inputs.get(&frame).map_or(0, HashMap::len);
+        num_inputs == self.latest_frames.len()
+    }
+
+    /// Returns the clients whose input for a frame is missing.
+    pub fn missing_clients(&self, frame: u64) -> Vec<ClientId> {
+        let inputs = self.inputs.get(&frame);
+        self.latest_frames
+            .keys()
+            .filter(|client_id| inputs.is_none_or(|inputs| !inputs.contains_key(client_id)))
+            .copied()
+            .collect()
+    }
+
+    /// Takes the inputs of all clients for the next frame if they are present, and advances to the following frame.
+    pub fn take_frame(&mut self) -> Option<HashMap<ClientId, Bytes>> {
+        if !self.is_frame_ready(self.next_frame) {
+            return None;
+        }
+
+        let inputs = self.inputs.remove(&self.next_frame).unwrap_or_default();
+        self.next_frame += 1;
+        self.waiting_since = self.current_time;
+        Some(inputs)
+    }
+
+    /// Returns how long the next frame has been waiting for inputs.
+    ///
+    /// This is reset when a frame is taken, a long wait means that the simulation is stalled by
+    /// the clients returned by [`Self::missing_clients`].
+    pub fn waiting_time(&self) -> Duration {
+        self.current_time - self.waiting_since
+    }
+
+    /// Returns whether the next frame has been waiting for inputs for at least the given duration.
+    pub fn is_stalled(&self, threshold: Duration) -> bool {
+        !self.is_frame_ready(self.next_frame) && self.waiting_time() >= threshold
+    }
+
+    /// Returns the lag of a client in the simulation, or None if it is not in the simulation.
+    pub fn client_lag(&self, server: &RenetServer, client_id: ClientId) -> Option<LockstepLag> {
+        let latest_frame = *self.latest_frames.get(&client_id)?;
+        let newest_frame = self.latest_frames.values().flatten().max().copied();
+        let frames_behind = match (newest_frame, latest_frame) {
+            (Some(newest_frame), Some(latest_frame)) => newest_frame - latest_frame,
+            (Some(newest_frame), None) => (newest_frame + 1).saturating_sub(self.next_frame),
+            (None, _) => 0,
+        };
+
+        Some(LockstepLag {
+            latest_frame,
+            frames_behind,
+            rtt: server.rtt(client_id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConnectionConfig, DefaultChannel};
+
+    #[test]
+    fn lockstep_frames() {
+        let mut server = RenetServer::new(ConnectionConfig::test());
+        let mut clients: Vec<RenetClient> = (0..2).map(|client_id| server.new_local_client(client_id)).collect();
+        let mut lockstep = LockstepInputs::new(DefaultChannel::ReliableOrdered).with_max_frames_ahead(4);
+        lockstep.add_client(0);
+        lockstep.add_client(1);
+
+        LockstepInputs::send_input(&mut clients[0], DefaultChannel::ReliableOrdered, 0, b"a0");
+        LockstepInputs::send_input(&mut clients[0], DefaultChannel::ReliableOrdered, 1, b"a1");
+        LockstepInputs::send_input(&mut clients[0], DefaultChannel::ReliableOrdered, 10, b"far");
+        LockstepInputs::send_input(&mut clients[1], DefaultChannel::ReliableOrdered, 0, b"b0");
+        for (client_id, client) in clients.iter_mut().enumerate() {
+            server.process_local_client(client_id as u64, client).unwrap();
+        }
+        lockstep.receive(&mut server);
+
+        assert!(lockstep.is_frame_ready(0));
+        assert!(!lockstep.is_frame_ready(1));
+        assert_eq!(lockstep.missing_clients(1), vec![1]);
+        let lag = lockstep.client_lag(&server, 1).unwrap();
+        assert_eq!(lag.latest_frame, Some(0));
+        assert_eq!(lag.frames_behind, 1);
+
+        let inputs = lockstep.take_frame().unwrap();
+        assert_eq!(inputs[&0], b"a0"[..]);
+        assert_eq!(inputs[&1], b"b0"[..]);
+        assert_eq!(lockstep.next_frame(), 1);
+
+        // Frame 1 stalls waiting for the second client
+        assert!(lockstep.take_frame().is_none());
+        lockstep.update(Duration::from_millis(500));
+        assert!(lockstep.is_stalled(Duration::from_millis(500)));
+
+        // Removing the stalled client unblocks the simulation
+        lockstep.remove_client(1);
+        assert!(!lockstep.is_stalled(Duration::from_millis(500)));
+        assert_eq!(lockstep.take_frame().unwrap()[&0], b"a1"[..]);
+        assert_eq!(lockstep.waiting_time(), Duration::ZERO);
+
+        // The input too far ahead was dropped
+        assert!(lockstep.inputs.is_empty());
+        assert!(lockstep.client_lag(&server, 1).is_none());
+    }
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use metrics::{counter, describe_counter, describe_gauge, gauge, Counter, Gauge, Unit};
+
+use crate::{ClientId, ConnectionConfig, RenetClient};
+
+fn describe() {
+    describe_gauge!("renet2_server_clients", "Number of connections in the renet server.");
+    describe_gauge!("renet2_client_rtt", Unit::Seconds, "Round-trip time of a connection.");
+    describe_gauge!(
+        "renet2_client_packet_loss",
+        "Fraction of packets sent over a connection that were lost."
+    );
+    describe_gauge!("renet2_client_sent_bytes_per_second", "Bytes per second sent over a connection.");
+    describe_gauge!(
+        "renet2_client_received_bytes_per_second",
+        "Bytes per second received over a connection."
+    );
+    describe_counter!(
+        "renet2_client_resent_messages_total",
+        "Reliable messages and message slices that had to be resent over a connection."
+    );
+    describe_counter!(
+        "renet2_client_packets_recovered_total",
+        "Lost packets recovered from parity data over a connection."
+    );
+    describe_gauge!(
+        "renet2_client_channel_memory",
+        Unit::Bytes,
+        "Memory used by queued messages in a channel of a connection."
+    );
+}
+
+/// Time between updates of the metrics of each connection.
+const CLIENT_METRICS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Metric handles of a connection, dropped when the connection is removed.
+struct ClientMetrics {
+    rtt: Gauge,
+    packet_loss: Gauge,
+    sent_bytes_per_second: Gauge,
+    received_bytes_per_second: Gauge,
+    resent_messages: Counter,
+    packets_recovered: Counter,
+    channel_memory: Vec<(u8, Gauge)>,
+}
+
+impl ClientMetrics {
+    fn new(client_id: ClientId, channel_ids: &[u8]) -> Self {
+        let client_id = client_id.to_string();
+        Self {
+            rtt: gauge!("renet2_client_rtt", "client_id" => client_id.clone()),
+            packet_loss: gauge!("renet2_client_packet_loss", "client_id" => client_id.clone()),
+            sent_bytes_per_second: gauge!("renet2_client_sent_bytes_per_second", "client_id" => client_id.clone()),
+            received_bytes_per_second: gauge!("renet2_client_received_bytes_per_second", "client_id" => client_id.clone()),
+            resent_messages: counter!("renet2_client_resent_messages_total", "client_id" => client_id.clone()),
+            packets_recovered: counter!("renet2_client_packets_recovered_total", "client_id" => client_id.clone()),
+            channel_memory: channel_ids
+                .iter()
+                .map(|&channel_id| {
+                    let gauge =
+                        gauge!("renet2_client_channel_memory", "client_id" => client_id.clone(), "channel_id" => channel_id.to_string());
+                    (channel_id, gauge)
+                })
+                .collect(),
+        }
+    }
+
+    fn record(&self, connection: &RenetClient) {
+        self.rtt.set(connection.rtt());
+        self.packet_loss.set(connection.packet_loss());
+        self.sent_bytes_per_second.set(connection.bytes_sent_per_sec());
+        self.received_bytes_per_second.set(connection.bytes_received_per_sec());
+        self.resent_messages.absolute(connection.resent_messages());
+        self.packets_recovered.absolute(connection.packets_recovered());
+        for (channel_id, gauge) in self.channel_memory.iter() {
+            gauge.set(connection.channel_memory_usage(*channel_id) as f64);
+        }
+    }
+
+    /// Zeroes the gauges of a removed connection, the `metrics` facade has no way to unregister them.
+    fn clear(&self) {
+        self.rtt.set(0.0);
+        self.packet_loss.set(0.0);
+        self.sent_bytes_per_second.set(0.0);
+        self.received_bytes_per_second.set(0.0);
+        for (_, gauge) in self.channel_memory.iter() {
+            gauge.set(0.0);
+        }
+    }
+}
+
+/// Publishes the state of server connections.
+///
+/// The handles of each connection are registered again every [`CLIENT_METRICS_INTERVAL`], so they follow
+/// a recorder installed after the server was made, and the labels are not allocated on every tick.
+pub(crate) struct ServerMetrics {
+    channel_ids: Vec<u8>,
+    connections: HashMap<ClientId, ClientMetrics>,
+    since_clients_recorded: Option<Duration>,
+}
+
+impl fmt::Debug for ServerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerMetrics")
+            .field("channel_ids", &self.channel_ids)
+            .field("connections", &self.connections.len())
+            .field("since_clients_recorded", &self.since_clients_recorded)
+            .finish()
+    }
+}
+
+impl ServerMetrics {
+    pub fn new(config: &ConnectionConfig) -> Self {
+        let mut channel_ids: Vec<u8> = config
+            .server_channels_config
+            .iter()
+            .chain(config.client_channels_config.iter())
+            .map(|config| config.channel_id)
+            .collect();
+        channel_ids.sort_unstable();
+        channel_ids.dedup();
+
+        Self {
+            channel_ids,
+            connections: HashMap::new(),
+            since_clients_recorded: None,
+        }
+    }
+
+    /// Publishes the current state of server connections.
+    pub fn record(&mut self, connections: &HashMap<ClientId, RenetClient>, duration: Duration) {
+        gauge!("renet2_server_clients").set(connections.len() as f64);
+
+        if let Some(since_clients_recorded) = &mut self.since_clients_recorded {
+            *since_clients_recorded += duration;
+            if *since_clients_recorded < CLIENT_METRICS_INTERVAL {
+                return;
+            }
+        }
+        self.since_clients_recorded = Some(Duration::ZERO);
+
+        describe();
+        for (&client_id, connection) in connections.iter() {
+            let metrics = ClientMetrics::new(client_id, &self.channel_ids);
+            metrics.record(connection);
+            self.connections.insert(client_id, metrics);
+        }
+    }
+
+    /// Zeroes and drops the metrics of a removed connection.
+    ///
+    /// The `metrics` facade can't unregister series, recorders that support it can remove them once they are dropped.
+    pub fn remove_connection(&mut self, client_id: ClientId) {
+        if let Some(metrics) = self.connections.remove(&client_id) {
+            metrics.clear();
+        }
+    }
+}
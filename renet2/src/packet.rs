@@ -40,6 +40,42 @@ pub enum Packet {
         channel_id: u8,
         slice: Slice,
     },
+    // Parity data of a group of packets in an unreliable channel with forward error correction.
+    // Contains the sequence and serialized length of each packet in the group,
+    // and the XOR of all their serialized bytes, enough to recover one lost packet of the group.
+    UnreliableParity {
+        sequence: u64,
+        channel_id: u8,
+        protected: Vec<(u64, usize)>,
+        parity: Bytes,
+    },
+    // Ids of cancelled messages in a reliable unordered channel, the receiver skips them
+    ReliableCancel {
+        sequence: u64,
+        channel_id: u8,
+        message_ids: Vec<u64>,
+    },
+    // Hash of the channel configuration of the sender, sent until the receiver confirms that it matches.
+    // Verified is set when the sender has already received a matching hash from the receiver.
+    ChannelConfig {
+        sequence: u64,
+        hash: u64,
+        verified: bool,
+    },
+    // Sent by idle connections, so the receiver sends an ack back
+    Heartbeat {
+        sequence: u64,
+    },
+    // Requests a pong with the same id
+    Ping {
+        sequence: u64,
+        ping_id: u64,
+    },
+    // Answer to a ping
+    Pong {
+        sequence: u64,
+        ping_id: u64,
+    },
     // Contains the packets that were acked
     // Acks are saved in multiples ranges, all values in the ranges are considered acked.
     Ack {
@@ -56,6 +92,7 @@ pub enum SerializationError {
     EmptySlice,
     InvalidAckRange,
     InvalidPacketType,
+    InvalidParityGroup,
 }
 
 impl std::error::Error for SerializationError {}
@@ -69,6 +106,7 @@ impl fmt::Display for SerializationError {
             InvalidNumSlices => write!(fmt, "invalid number of slices"),
             InvalidAckRange => write!(fmt, "invalid ack range"),
             InvalidPacketType => write!(fmt, "invalid packet type"),
+            InvalidParityGroup => write!(fmt, "invalid parity group"),
             SliceSizeAboveLimit => write!(fmt, "invalid slice size, it's above the limit of {} bytes", SLICE_SIZE),
             EmptySlice => write!(fmt, "invalid slice, slices cannot be empty"),
         }
@@ -88,6 +126,12 @@ impl Packet {
             | Packet::SmallUnreliable { sequence, .. }
             | Packet::UnreliableSlice { sequence, .. }
             | Packet::ReliableSlice { sequence, .. }
+            | Packet::UnreliableParity { sequence, .. }
+            | Packet::ReliableCancel { sequence, .. }
+            | Packet::ChannelConfig { sequence, .. }
+            | Packet::Heartbeat { sequence }
+            | Packet::Ping { sequence, .. }
+            | Packet::Pong { sequence, .. }
             | Packet::Ack { sequence, .. } => *sequence,
         }
     }
@@ -153,6 +197,61 @@ impl Packet {
                 b.put_varint(slice.payload.len() as u64)?;
                 b.put_bytes(&slice.payload)?;
             }
+            Packet::UnreliableParity {
+                sequence,
+                channel_id,
+                protected,
+                parity,
+            } => {
+                b.put_u8(5)?;
+                b.put_varint(*sequence)?;
+                b.put_u8(*channel_id)?;
+                b.put_u8(protected.len() as u8)?;
+
+                // Sequences are increasing, so after the first one only the gap to the previous one is written
+                let mut previous_sequence = None;
+                for (protected_sequence, _) in protected {
+                    match previous_sequence {
+                        None => b.put_varint(*protected_sequence)?,
+                        Some(previous) => b.put_varint(protected_sequence - previous)?,
+                    };
+                    previous_sequence = Some(*protected_sequence);
+                }
+                for (_, len) in protected {
+                    b.put_varint(*len as u64)?;
+                }
+                b.put_varint(parity.len() as u64)?;
+                b.put_bytes(parity)?;
+            }
+            Packet::ReliableCancel {
+                sequence,
+                channel_id,
+                message_ids,
+            } => {
+                b.put_u8(7)?;
+                b.put_varint(*sequence)?;
+                b.put_u8(*channel_id)?;
+                b.put_u16(message_ids.len() as u16)?;
+                for message_id in message_ids {
+                    b.put_varint(*message_id)?;
+                }
+            }
+            Packet::ChannelConfig { sequence, hash, verified } => {
+                b.put_u8(6)?;
+                b.put_varint(*sequence)?;
+                b.put_u64(*hash)?;
+                b.put_u8(*verified as u8)?;
+            }
+            Packet::Heartbeat { sequence } => {
+                b.put_u8(8)?;
+                b.put_varint(*sequence)?;
+            }
+            Packet::Ping { sequence, ping_id } | Packet::Pong { sequence, ping_id } => {
+                let packet_type = if matches!(self, Packet::Ping { .. }) { 9 } else { 10 };
+                b.put_u8(packet_type)?;
+                b.put_varint(*sequence)?;
+                b.put_varint(*ping_id)?;
+            }
             Packet::Ack { sequence, ack_ranges } => {
                 b.put_u8(4)?;
                 b.put_varint(*sequence)?;
@@ -346,6 +445,83 @@ impl Packet {
 
                 Ok(Packet::Ack { sequence, ack_ranges })
             }
+            5 => {
+                // UnreliableParity
+                let sequence = b.get_varint()?;
+                let channel_id = b.get_u8()?;
+                let num_protected = b.get_u8()? as usize;
+                if num_protected < 2 {
+                    return Err(SerializationError::InvalidParityGroup);
+                }
+
+                let mut protected: Vec<(u64, usize)> = Vec::with_capacity(num_protected);
+                let mut previous_sequence = b.get_varint()?;
+                protected.push((previous_sequence, 0));
+                for _ in 1..num_protected {
+                    let gap = b.get_varint()?;
+                    if gap == 0 {
+                        return Err(SerializationError::InvalidParityGroup);
+                    }
+                    previous_sequence = previous_sequence.checked_add(gap).ok_or(SerializationError::InvalidParityGroup)?;
+                    protected.push((previous_sequence, 0));
+                }
+                for (_, len) in protected.iter_mut() {
+                    *len = b.get_varint()? as usize;
+                }
+
+                let parity = b.get_bytes_with_varint_length()?;
+                if protected.iter().any(|(_, len)| *len == 0 || *len > parity.len()) {
+                    return Err(SerializationError::InvalidParityGroup);
+                }
+
+                Ok(Packet::UnreliableParity {
+                    sequence,
+                    channel_id,
+                    protected,
+                    parity: parity.to_vec().into(),
+                })
+            }
+            6 => {
+                // ChannelConfig
+                let sequence = b.get_varint()?;
+                let hash = b.get_u64()?;
+                let verified = b.get_u8()? != 0;
+
+                Ok(Packet::ChannelConfig { sequence, hash, verified })
+            }
+            7 => {
+                // ReliableCancel
+                let sequence = b.get_varint()?;
+                let channel_id = b.get_u8()?;
+                let message_ids_len = b.get_u16()?;
+                let mut message_ids: Vec<u64> = Vec::with_capacity(message_ids_len.min(256) as usize);
+                for _ in 0..message_ids_len {
+                    message_ids.push(b.get_varint()?);
+                }
+
+                Ok(Packet::ReliableCancel {
+                    sequence,
+                    channel_id,
+                    message_ids,
+                })
+            }
+            8 => {
+                // Heartbeat
+                let sequence = b.get_varint()?;
+
+                Ok(Packet::Heartbeat { sequence })
+            }
+            9 | 10 => {
+                // Ping or Pong
+                let sequence = b.get_varint()?;
+                let ping_id = b.get_varint()?;
+
+                if packet_type == 9 {
+                    Ok(Packet::Ping { sequence, ping_id })
+                } else {
+                    Ok(Packet::Pong { sequence, ping_id })
+                }
+            }
             _ => Err(SerializationError::InvalidPacketType),
         }
     }
@@ -435,6 +611,92 @@ mod tests {
         assert_eq!(packet, recv_packet);
     }
 
+    #[test]
+    fn serialize_unreliable_parity_packet() {
+        let mut buffer = [0u8; 1300];
+
+        let packet = Packet::UnreliableParity {
+            sequence: 10,
+            channel_id: 0,
+            protected: vec![(3, 20), (5, 1000), (9, 1000)],
+            parity: vec![5; 1000].into(),
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        let recv_packet = Packet::from_bytes(&mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+    }
+
+    #[test]
+    fn serialize_reliable_cancel_packet() {
+        let mut buffer = [0u8; 1300];
+
+        let packet = Packet::ReliableCancel {
+            sequence: 7,
+            channel_id: 1,
+            message_ids: vec![0, 300, 70000],
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        let recv_packet = Packet::from_bytes(&mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+    }
+
+    #[test]
+    fn serialize_channel_config_packet() {
+        let mut buffer = [0u8; 1300];
+
+        let packet = Packet::ChannelConfig {
+            sequence: 3,
+            hash: 0xcbf29ce484222325,
+            verified: true,
+        };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        let recv_packet = Packet::from_bytes(&mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+    }
+
+    #[test]
+    fn serialize_heartbeat_packet() {
+        let mut buffer = [0u8; 1300];
+
+        let packet = Packet::Heartbeat { sequence: 300 };
+
+        let mut b = octets::OctetsMut::with_slice(&mut buffer);
+        packet.to_bytes(&mut b).unwrap();
+
+        let mut b = octets::Octets::with_slice(&buffer);
+        let recv_packet = Packet::from_bytes(&mut b).unwrap();
+        assert_eq!(packet, recv_packet);
+    }
+
+    #[test]
+    fn serialize_ping_packets() {
+        let mut buffer = [0u8; 1300];
+
+        let ping = Packet::Ping { sequence: 4, ping_id: 2 };
+        let pong = Packet::Pong { sequence: 9, ping_id: 2 };
+
+        for packet in [ping, pong] {
+            let mut b = octets::OctetsMut::with_slice(&mut buffer);
+            packet.to_bytes(&mut b).unwrap();
+
+            let mut b = octets::Octets::with_slice(&buffer);
+            let recv_packet = Packet::from_bytes(&mut b).unwrap();
+            assert_eq!(packet, recv_packet);
+        }
+    }
+
     #[test]
     fn serialize_ack_packet() {
         let mut buffer = [0u8; 1300];
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+/// Action taken by the server when a client exceeds the rate limit of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// Drop the packets with excess messages. Reliable messages are not acked, so the client will resend them later.
+    Drop,
+    /// Drop the packets with excess messages and emit [`ServerEvent::ClientRateLimited`](crate::ServerEvent::ClientRateLimited).
+    Warn,
+    /// Disconnect the client with [`DisconnectReason::RateLimited`](crate::DisconnectReason::RateLimited).
+    Disconnect,
+}
+
+/// Limits how fast each client can send messages to the server over a channel.
+///
+/// Both limits are token buckets that refill continuously and hold up to one second of tokens,
+/// so short bursts are allowed as long as the average rate stays below the limit.
+/// Packets are accepted whole while the buckets are not empty, and may leave them in debt
+/// that has to be refilled before the next packet of the channel is accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Maximum number of messages per second. Sliced messages count as one message, and so does each cancelled message.
+    pub messages_per_second: u64,
+    /// Maximum number of message bytes per second.
+    pub bytes_per_second: u64,
+    /// Action taken when the limit is exceeded.
+    pub policy: RateLimitPolicy,
+}
+
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    message_tokens: f64,
+    byte_tokens: f64,
+    last_update: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, current_time: Duration) -> Self {
+        Self {
+            limit,
+            message_tokens: limit.messages_per_second as f64,
+            byte_tokens: limit.bytes_per_second as f64,
+            last_update: current_time,
+        }
+    }
+
+    pub fn policy(&self) -> RateLimitPolicy {
+        self.limit.policy
+    }
+
+    /// Consumes tokens for the messages and bytes, possibly leaving the buckets in debt.
+    /// Returns false and consumes nothing if a bucket is in debt.
+    pub fn try_consume(&mut self, messages: u64, bytes: u64, current_time: Duration) -> bool {
+        let elapsed = current_time.saturating_sub(self.last_update).as_secs_f64();
+        self.last_update = current_time;
+        self.message_tokens =
+            (self.message_tokens + elapsed * self.limit.messages_per_second as f64).min(self.limit.messages_per_second as f64);
+        self.byte_tokens = (self.byte_tokens + elapsed * self.limit.bytes_per_second as f64).min(self.limit.bytes_per_second as f64);
+
+        // Accepting packets while the buckets aren't in debt allows packets bigger than the buckets,
+        // such as reliable messages accumulated while the channel was limited
+        if self.message_tokens < 0.0 || self.byte_tokens < 0.0 {
+            return false;
+        }
+
+        self.message_tokens -= messages as f64;
+        self.byte_tokens -= bytes as f64;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let limit = RateLimit {
+            messages_per_second: 10,
+            bytes_per_second: 1000,
+            policy: RateLimitPolicy::Drop,
+        };
+        let mut limiter = RateLimiter::new(limit, Duration::ZERO);
+
+        // Burst of one second of tokens
+        assert!(limiter.try_consume(10, 100, Duration::ZERO));
+        assert!(limiter.try_consume(1, 10, Duration::ZERO));
+        assert!(!limiter.try_consume(1, 10, Duration::ZERO));
+
+        // Refills over time until out of debt
+        assert!(!limiter.try_consume(1, 10, Duration::from_millis(50)));
+        assert!(limiter.try_consume(1, 10, Duration::from_millis(100)));
+        assert!(!limiter.try_consume(1, 10, Duration::from_millis(100)));
+
+        // Byte limit
+        assert!(limiter.try_consume(1, 3000, Duration::from_secs(5)));
+        assert!(!limiter.try_consume(1, 10, Duration::from_secs(6)));
+        assert!(limiter.try_consume(1, 10, Duration::from_secs(7)));
+    }
+}
@@ -1,18 +1,21 @@
+use crate::channel::fec::MAX_FEC_GROUP_SIZE;
 use crate::channel::reliable::{ReceiveChannelReliable, SendChannelReliable};
 use crate::channel::unreliable::{ReceiveChannelUnreliable, SendChannelUnreliable};
-use crate::channel::{ChannelConfig, DefaultChannel, SendType};
+use crate::channel::{ChannelConfig, DefaultChannel, MessageHandle, PendingMessage, SendType};
 use crate::connection_stats::ConnectionStats;
-use crate::error::DisconnectReason;
+use crate::error::{ChannelDirection, ConfigError, DisconnectReason};
 use crate::packet::{Packet, Payload};
+use crate::rate_limit::{RateLimit, RateLimitPolicy, RateLimiter};
 use bytes::Bytes;
 use octets::OctetsMut;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 use std::ops::Range;
 use std::time::Duration;
 
 /// Configuration for a renet connection and its channels.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnectionConfig {
     /// The number of bytes that is available per update tick to send messages.
     /// Default: 60_000, at 60hz this is becomes 28.8 Mbps
@@ -27,8 +30,23 @@ pub struct ConnectionConfig {
     /// Each tick, the first channel can consume up to `available_bytes_per_tick`,
     /// used bytes are removed from it and passed to the next channel
     pub client_channels_config: Vec<ChannelConfig>,
+    /// Idle connections send a heartbeat packet after not sending any packet for this long, or never if `None`.
+    ///
+    /// Heartbeats are acked like any other packet, so the round-trip time stays up to date on idle connections,
+    /// including connections over reliable sockets, and connections behind NATs keep sending traffic.
+    /// Peers with older versions of renet2 reject heartbeat packets, so set this to `None` to connect to them.
+    /// Default: 1 second
+    #[cfg_attr(feature = "serde", serde(default = "default_heartbeat_interval", with = "humantime_serde"))]
+    pub heartbeat_interval: Option<Duration>,
+}
+
+#[cfg(feature = "serde")]
+fn default_heartbeat_interval() -> Option<Duration> {
+    Some(DEFAULT_HEARTBEAT_INTERVAL)
 }
 
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
 impl ConnectionConfig {
     /// Makes a new config with default `available_bytes_per_tick`.
     pub fn from_channels(server: Vec<ChannelConfig>, client: Vec<ChannelConfig>) -> Self {
@@ -37,6 +55,7 @@ impl ConnectionConfig {
             available_bytes_per_tick: 60_000,
             server_channels_config: server,
             client_channels_config: client,
+            heartbeat_interval: Some(DEFAULT_HEARTBEAT_INTERVAL),
         }
     }
 
@@ -45,13 +64,122 @@ impl ConnectionConfig {
         Self::from_channels(channels.clone(), channels)
     }
 
+    /// Makes a new config with default `available_bytes_per_tick`, returns an error if the channels are invalid.
+    ///
+    /// See [`Self::validate`].
+    pub fn try_from_channels(server: Vec<ChannelConfig>, client: Vec<ChannelConfig>) -> Result<Self, ConfigError> {
+        let config = Self::from_channels(server, client);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Makes a new config with default `available_bytes_per_tick` and the same server and client channels,
+    /// returns an error if the channels are invalid.
+    ///
+    /// See [`Self::validate`].
+    pub fn try_from_shared_channels(channels: Vec<ChannelConfig>) -> Result<Self, ConfigError> {
+        Self::try_from_channels(channels.clone(), channels)
+    }
+
+    /// Checks that the config can be used to create connections.
+    ///
+    /// Invalid configs cause a panic when creating a [`RenetClient`] or adding a connection to a
+    /// [`RenetServer`](crate::RenetServer), this is useful to check configs loaded at runtime.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.available_bytes_per_tick == 0 {
+            return Err(ConfigError::ZeroAvailableBytesPerTick);
+        }
+        if self.heartbeat_interval == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroHeartbeatInterval);
+        }
+
+        for (direction, channels_config) in [
+            (ChannelDirection::ServerToClient, &self.server_channels_config),
+            (ChannelDirection::ClientToServer, &self.client_channels_config),
+        ] {
+            for (i, config) in channels_config.iter().enumerate() {
+                let channel_id = config.channel_id;
+                if channels_config[..i].iter().any(|other| other.channel_id == channel_id) {
+                    return Err(ConfigError::DuplicateChannelId { direction, channel_id });
+                }
+                if config.max_memory_usage_bytes == 0 {
+                    return Err(ConfigError::ZeroMaxMemoryUsage { direction, channel_id });
+                }
+                if config.max_message_size == 0 {
+                    return Err(ConfigError::ZeroMaxMessageSize { direction, channel_id });
+                }
+                if config.max_partial_messages == 0 {
+                    return Err(ConfigError::ZeroMaxPartialMessages { direction, channel_id });
+                }
+
+                match config.send_type {
+                    SendType::UnreliableFec { group_size } if !(2..=MAX_FEC_GROUP_SIZE).contains(&group_size) => {
+                        return Err(ConfigError::InvalidFecGroupSize {
+                            direction,
+                            channel_id,
+                            group_size,
+                        });
+                    }
+                    SendType::ReliableOrdered { resend_time } | SendType::ReliableUnordered { resend_time } if resend_time.is_zero() => {
+                        return Err(ConfigError::ZeroResendTime { direction, channel_id });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Makes a new config for testing purposes.
     pub fn test() -> Self {
         Self::from_shared_channels(DefaultChannel::config())
     }
 
+    /// Returns a hash of the channel layout: the id and send type of the server and client channels.
+    ///
+    /// Connections exchange this hash when they start, and are disconnected with
+    /// [`DisconnectReason::ChannelConfigMismatch`] if they don't match.
+    /// The hash is stable across builds and platforms, and doesn't depend on the priority order of the channels,
+    /// nor on their memory and message limits.
+    pub fn channels_hash(&self) -> u64 {
+        // 64-bit FNV-1a
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+
+        for channels_config in [&self.server_channels_config, &self.client_channels_config] {
+            let mut channels: Vec<(u8, u8)> = channels_config
+                .iter()
+                .map(|config| {
+                    let send_type = match config.send_type {
+                        SendType::Unreliable => 0,
+                        SendType::UnreliableFec { .. } => 1,
+                        SendType::ReliableOrdered { .. } => 2,
+                        SendType::ReliableUnordered { .. } => 3,
+                    };
+                    (config.channel_id, send_type)
+                })
+                .collect();
+            channels.sort_unstable();
+
+            write(&(channels.len() as u16).to_le_bytes());
+            for (channel_id, send_type) in channels {
+                write(&[channel_id, send_type]);
+            }
+        }
+
+        hash
+    }
+
     /// Downgrades all channels to [`SendType::Unreliable`].
     ///
+    /// Forward error correction is also disabled, since packets can't be lost.
+    ///
     /// Used when setting up a client that has a socket with built-in reliability (such as WebSockets).
     pub fn downgrade_to_unreliable(&mut self) {
         self.server_channels_config.iter_mut().for_each(|c| {
@@ -109,6 +237,19 @@ enum ReceiveChannel {
     Reliable(ReceiveChannelReliable),
 }
 
+// Maximum number of pings waiting for an answer, and of pings received from the peer waiting to be answered
+const MAX_PENDING_PINGS: usize = 64;
+// Pings without an answer after this long are considered lost
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Round-trip time measured by a ping sent with [`RenetClient::ping`] or [`RenetServer::ping`](crate::RenetServer::ping).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingResult {
+    /// Id returned when sending the ping.
+    pub ping_id: u64,
+    pub rtt: Duration,
+}
+
 /// Describes the stats of a connection.
 pub struct NetworkInfo {
     /// Round-trip Time
@@ -116,6 +257,8 @@ pub struct NetworkInfo {
     pub packet_loss: f64,
     pub bytes_sent_per_second: f64,
     pub bytes_received_per_second: f64,
+    /// Number of lost packets that were recovered from parity data in channels with forward error correction.
+    pub packets_recovered: u64,
 }
 
 /// The connection status of a [`RenetClient`].
@@ -141,6 +284,23 @@ pub struct RenetClient {
     available_bytes_per_tick: u64,
     connection_status: RenetConnectionStatus,
     rtt: f64,
+    rate_limiters: BTreeMap<u8, RateLimiter>,
+    rate_limited_channels: Vec<u8>,
+    bandwidth_budget: Option<u64>,
+    channels_hash: u64,
+    heartbeat_interval: Option<Duration>,
+    last_packet_sent_at: Duration,
+    // Whether the peer sent a matching channels hash
+    channels_verified: bool,
+    // Whether the peer needs our channels hash
+    send_channels_hash: bool,
+    next_ping_id: u64,
+    // Send time of the pings waiting for an answer
+    pending_pings: BTreeMap<u64, Duration>,
+    // Ids of pings to send, and of pings received from the peer to answer
+    pings_to_send: Vec<u64>,
+    pongs_to_send: Vec<u64>,
+    ping_results: VecDeque<PingResult>,
 }
 
 impl RenetClient {
@@ -150,27 +310,41 @@ impl RenetClient {
     ///
     /// See `ClientSocket::is_reliable` in `renet2_netcode`.
     pub fn new(mut config: ConnectionConfig, has_reliable_socket: bool) -> Self {
+        let channels_hash = config.channels_hash();
         if has_reliable_socket {
             config.downgrade_to_unreliable();
         }
 
         Self::from_channels(
             has_reliable_socket,
+            channels_hash,
+            config.heartbeat_interval,
             config.available_bytes_per_tick,
             config.client_channels_config,
             config.server_channels_config,
         )
     }
 
+    /// Same as [`Self::new`], but returns an error instead of panicking if the config is invalid.
+    ///
+    /// See [`ConnectionConfig::validate`].
+    pub fn try_new(config: ConnectionConfig, has_reliable_socket: bool) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self::new(config, has_reliable_socket))
+    }
+
     // When creating a client from the server, the server_channels_config are used as send channels,
     // and the client_channels_config is used as recv channels.
     pub(crate) fn new_from_server(mut config: ConnectionConfig, has_reliable_socket: bool) -> Self {
+        let channels_hash = config.channels_hash();
         if has_reliable_socket {
             config.downgrade_to_unreliable();
         }
 
         Self::from_channels(
             has_reliable_socket,
+            channels_hash,
+            config.heartbeat_interval,
             config.available_bytes_per_tick,
             config.server_channels_config,
             config.client_channels_config,
@@ -179,6 +353,8 @@ impl RenetClient {
 
     fn from_channels(
         has_reliable_socket: bool,
+        channels_hash: u64,
+        heartbeat_interval: Option<Duration>,
         available_bytes_per_tick: u64,
         send_channels_config: Vec<ChannelConfig>,
         receive_channels_config: Vec<ChannelConfig>,
@@ -200,12 +376,33 @@ impl RenetClient {
             match channel_config.send_type {
                 SendType::Unreliable => {
                     channel_send_order.push(ChannelOrder::Unreliable(channel_config.channel_id));
-                    let channel = SendChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes);
+                    let channel = SendChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes)
+                        .with_max_message_size(channel_config.max_message_size);
                     *send_channel = SendChannel::Unreliable(channel);
                 }
-                SendType::ReliableOrdered { resend_time } | SendType::ReliableUnordered { resend_time } => {
+                SendType::UnreliableFec { group_size } => {
+                    assert!(
+                        (2..=MAX_FEC_GROUP_SIZE).contains(&group_size),
+                        "invalid fec group size {group_size} for send channel {}",
+                        channel_config.channel_id
+                    );
+                    channel_send_order.push(ChannelOrder::Unreliable(channel_config.channel_id));
+                    let channel = SendChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes)
+                        .with_max_message_size(channel_config.max_message_size)
+                        .with_fec(group_size);
+                    *send_channel = SendChannel::Unreliable(channel);
+                }
+                SendType::ReliableOrdered { resend_time } => {
+                    channel_send_order.push(ChannelOrder::Reliable(channel_config.channel_id));
+                    let channel = SendChannelReliable::new(channel_config.channel_id, resend_time, channel_config.max_memory_usage_bytes)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages);
+                    *send_channel = SendChannel::Reliable(channel);
+                }
+                SendType::ReliableUnordered { resend_time } => {
                     channel_send_order.push(ChannelOrder::Reliable(channel_config.channel_id));
-                    let channel = SendChannelReliable::new(channel_config.channel_id, resend_time, channel_config.max_memory_usage_bytes);
+                    let channel = SendChannelReliable::new(channel_config.channel_id, resend_time, channel_config.max_memory_usage_bytes)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages)
+                        .with_cancellation();
                     *send_channel = SendChannel::Reliable(channel);
                 }
             }
@@ -223,15 +420,24 @@ impl RenetClient {
 
             match channel_config.send_type {
                 SendType::Unreliable => {
-                    let channel = ReceiveChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes);
+                    let channel = ReceiveChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages);
+                    *receive_channel = ReceiveChannel::Unreliable(channel);
+                }
+                SendType::UnreliableFec { .. } => {
+                    let channel = ReceiveChannelUnreliable::new(channel_config.channel_id, channel_config.max_memory_usage_bytes)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages)
+                        .with_fec();
                     *receive_channel = ReceiveChannel::Unreliable(channel);
                 }
                 SendType::ReliableOrdered { .. } => {
-                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, true);
+                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, true)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages);
                     *receive_channel = ReceiveChannel::Reliable(channel);
                 }
                 SendType::ReliableUnordered { .. } => {
-                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, false);
+                    let channel = ReceiveChannelReliable::new(channel_config.max_memory_usage_bytes, false)
+                        .with_message_limits(channel_config.max_message_size, channel_config.max_partial_messages);
                     *receive_channel = ReceiveChannel::Reliable(channel);
                 }
             }
@@ -250,6 +456,19 @@ impl RenetClient {
             rtt: 0.0,
             available_bytes_per_tick,
             connection_status: RenetConnectionStatus::Connecting,
+            rate_limiters: BTreeMap::new(),
+            rate_limited_channels: Vec::new(),
+            bandwidth_budget: None,
+            channels_hash,
+            heartbeat_interval,
+            last_packet_sent_at: Duration::ZERO,
+            channels_verified: false,
+            send_channels_hash: true,
+            next_ping_id: 0,
+            pending_pings: BTreeMap::new(),
+            pings_to_send: Vec::new(),
+            pongs_to_send: Vec::new(),
+            ping_results: VecDeque::new(),
         }
     }
 
@@ -285,9 +504,23 @@ impl RenetClient {
             packet_loss: self.stats.packet_loss(),
             bytes_sent_per_second: self.stats.bytes_sent_per_second(self.current_time),
             bytes_received_per_second: self.stats.bytes_received_per_second(self.current_time),
+            packets_recovered: self.packets_recovered(),
         }
     }
 
+    /// Returns the number of lost packets that were recovered from parity data.
+    ///
+    /// Only channels with [`SendType::UnreliableFec`] can recover lost packets.
+    pub fn packets_recovered(&self) -> u64 {
+        self.receive_channels
+            .iter()
+            .map(|channel| match channel {
+                ReceiveChannel::Unreliable(unreliable_channel) => unreliable_channel.packets_recovered(),
+                _ => 0,
+            })
+            .sum()
+    }
+
     /// Returns whether the client is connected.
     #[inline]
     pub fn is_connected(&self) -> bool {
@@ -370,6 +603,34 @@ impl RenetClient {
         }
     }
 
+    /// Returns the memory in bytes used by queued messages in the send and receive sides of the given channel.
+    pub fn channel_memory_usage<I: Into<u8>>(&self, channel_id: I) -> usize {
+        let channel_id = channel_id.into() as usize;
+        let send = match self.send_channels.get(channel_id) {
+            None | Some(SendChannel::Empty) => 0,
+            Some(SendChannel::Reliable(reliable_channel)) => reliable_channel.memory_usage(),
+            Some(SendChannel::Unreliable(unreliable_channel)) => unreliable_channel.memory_usage(),
+        };
+        let receive = match self.receive_channels.get(channel_id) {
+            None | Some(ReceiveChannel::Empty) => 0,
+            Some(ReceiveChannel::Reliable(reliable_channel)) => reliable_channel.memory_usage(),
+            Some(ReceiveChannel::Unreliable(unreliable_channel)) => unreliable_channel.memory_usage(),
+        };
+
+        send + receive
+    }
+
+    /// Returns the number of reliable messages and message slices that had to be resent.
+    pub fn resent_messages(&self) -> u64 {
+        self.send_channels
+            .iter()
+            .map(|channel| match channel {
+                SendChannel::Reliable(reliable_channel) => reliable_channel.num_resends(),
+                _ => 0,
+            })
+            .sum()
+    }
+
     /// Checks if the channel can send a message with the given size in bytes.
     pub fn can_send_message<I: Into<u8>>(&self, channel_id: I, size_bytes: usize) -> bool {
         let channel_id = channel_id.into();
@@ -384,23 +645,58 @@ impl RenetClient {
 
     /// Send a message to the server over a channel.
     pub fn send_message<I: Into<u8>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) {
+        self.send_message_with_handle(channel_id, message);
+    }
+
+    /// Same as [`Self::send_message`], but returns a handle to the message that can be used to cancel it,
+    /// or None if the message was dropped.
+    pub fn send_message_with_handle<I: Into<u8>, B: Into<Bytes>>(&mut self, channel_id: I, message: B) -> Option<MessageHandle> {
         if self.is_disconnected() {
-            return;
+            return None;
         }
 
         let channel_id = channel_id.into();
-        match self.send_channels.get_mut(channel_id as usize) {
+        let message_id = match self.send_channels.get_mut(channel_id as usize) {
             None | Some(SendChannel::Empty) => {
                 panic!("Called 'send_message' with invalid channel {channel_id}");
             }
-            Some(SendChannel::Reliable(reliable_channel)) => {
-                if let Err(error) = reliable_channel.send_message(message.into()) {
+            Some(SendChannel::Reliable(reliable_channel)) => match reliable_channel.send_message(message.into()) {
+                Ok(message_id) => message_id,
+                Err(error) => {
                     self.disconnect_with_reason(DisconnectReason::SendChannelError { channel_id, error });
+                    return None;
                 }
+            },
+            Some(SendChannel::Unreliable(unreliable_channel)) => unreliable_channel.send_message(message.into())?,
+        };
+
+        Some(MessageHandle::new(channel_id, message_id))
+    }
+
+    /// Returns the messages waiting to be sent in a channel, or waiting for an ack in reliable channels.
+    pub fn pending_messages<I: Into<u8>>(&self, channel_id: I) -> Vec<PendingMessage> {
+        let channel_id = channel_id.into();
+        match self.send_channels.get(channel_id as usize) {
+            None | Some(SendChannel::Empty) => {
+                panic!("Called 'pending_messages' with invalid channel {channel_id}");
             }
-            Some(SendChannel::Unreliable(unreliable_channel)) => {
-                unreliable_channel.send_message(message.into());
-            }
+            Some(SendChannel::Reliable(reliable_channel)) => reliable_channel.pending_messages(),
+            Some(SendChannel::Unreliable(unreliable_channel)) => unreliable_channel.pending_messages(),
+        }
+    }
+
+    /// Cancels a pending message, releasing its memory in the channel.
+    ///
+    /// Unreliable messages can be cancelled until they are sent. Messages in [`SendType::ReliableUnordered`] channels
+    /// can be cancelled until they are acked, the receiver will skip them if they weren't fully received.
+    /// Messages in [`SendType::ReliableOrdered`] channels can't be cancelled.
+    ///
+    /// Returns false if the message was not cancelled.
+    pub fn cancel_message(&mut self, handle: MessageHandle) -> bool {
+        match self.send_channels.get_mut(handle.channel_id() as usize) {
+            None | Some(SendChannel::Empty) => false,
+            Some(SendChannel::Reliable(reliable_channel)) => reliable_channel.cancel_message(handle.message_id()),
+            Some(SendChannel::Unreliable(unreliable_channel)) => unreliable_channel.cancel_message(handle.message_id()),
         }
     }
 
@@ -426,11 +722,15 @@ impl RenetClient {
         self.current_time += duration;
         self.stats.update(self.current_time);
 
+        let current_time = self.current_time;
+        self.pending_pings.retain(|_, sent_at| current_time - *sent_at < PING_TIMEOUT);
+
         for unreliable_channel in self.receive_channels.iter_mut() {
             let ReceiveChannel::Unreliable(unreliable_channel) = unreliable_channel else {
                 continue;
             };
             unreliable_channel.discard_incomplete_old_slices(self.current_time);
+            unreliable_channel.discard_old_fec_data(self.current_time);
         }
 
         // Discard lost packets
@@ -470,12 +770,17 @@ impl RenetClient {
             Ok(packet) => packet,
         };
 
+        if !self.rate_limiters.is_empty() && !self.check_rate_limit(&packet) {
+            // Packet is dropped without being acked
+            return;
+        }
+
         self.add_pending_ack(packet.sequence());
 
         match packet {
             Packet::SmallReliable { channel_id, messages, .. } => {
                 let Some(ReceiveChannel::Reliable(channel)) = self.receive_channels.get_mut(channel_id as usize) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
+                    self.disconnect_invalid_channel(channel_id);
                     return;
                 };
 
@@ -486,36 +791,75 @@ impl RenetClient {
                     }
                 }
             }
-            Packet::SmallUnreliable { channel_id, messages, .. } => {
-                let Some(ReceiveChannel::Unreliable(channel)) = self.receive_channels.get_mut(channel_id as usize) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
+            Packet::ReliableSlice { channel_id, slice, .. } => {
+                let Some(ReceiveChannel::Reliable(channel)) = self.receive_channels.get_mut(channel_id as usize) else {
+                    self.disconnect_invalid_channel(channel_id);
                     return;
                 };
 
-                for message in messages {
-                    channel.process_message(message);
+                if let Err(error) = channel.process_slice(slice) {
+                    self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
                 }
             }
-            Packet::ReliableSlice { channel_id, slice, .. } => {
+            Packet::ReliableCancel {
+                channel_id, message_ids, ..
+            } => {
                 let Some(ReceiveChannel::Reliable(channel)) = self.receive_channels.get_mut(channel_id as usize) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
+                    self.disconnect_invalid_channel(channel_id);
                     return;
                 };
 
-                if let Err(error) = channel.process_slice(slice) {
-                    self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                for message_id in message_ids {
+                    if let Err(error) = channel.process_cancel(message_id) {
+                        self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
+                        return;
+                    }
                 }
             }
-            Packet::UnreliableSlice { channel_id, slice, .. } => {
+            Packet::SmallUnreliable { channel_id, .. }
+            | Packet::UnreliableSlice { channel_id, .. }
+            | Packet::UnreliableParity { channel_id, .. } => {
                 let Some(ReceiveChannel::Unreliable(channel)) = self.receive_channels.get_mut(channel_id as usize) else {
-                    self.disconnect_with_reason(DisconnectReason::ReceivedInvalidChannelId(channel_id));
+                    self.disconnect_invalid_channel(channel_id);
                     return;
                 };
 
-                if let Err(error) = channel.process_slice(slice, self.current_time) {
+                if let Err(error) = channel.process_packet(packet, self.current_time) {
                     self.disconnect_with_reason(DisconnectReason::ReceiveChannelError { channel_id, error });
                 }
             }
+            Packet::ChannelConfig { hash, verified, .. } => {
+                if hash != self.channels_hash {
+                    self.disconnect_with_reason(DisconnectReason::ChannelConfigMismatch);
+                    return;
+                }
+
+                self.channels_verified = true;
+                if !verified {
+                    // Our previous hash was lost, or the peer sent its hash before receiving ours
+                    self.send_channels_hash = true;
+                }
+            }
+            Packet::Heartbeat { .. } => {
+                // Only acked
+            }
+            Packet::Ping { ping_id, .. } => {
+                if self.pongs_to_send.len() < MAX_PENDING_PINGS {
+                    self.pongs_to_send.push(ping_id);
+                }
+            }
+            Packet::Pong { ping_id, .. } => {
+                let Some(sent_at) = self.pending_pings.remove(&ping_id) else {
+                    log::debug!("discarded pong {ping_id} of an unknown ping");
+                    return;
+                };
+                if self.ping_results.len() < MAX_PENDING_PINGS {
+                    self.ping_results.push_back(PingResult {
+                        ping_id,
+                        rtt: self.current_time - sent_at,
+                    });
+                }
+            }
             Packet::Ack { ack_ranges, .. } => {
                 // Create list with just new acks
                 // This prevents DoS from huge ack ranges
@@ -577,7 +921,36 @@ impl RenetClient {
             return vec![];
         }
 
-        let mut available_bytes = self.available_bytes_per_tick;
+        if self.send_channels_hash || !self.channels_verified {
+            packets.push(Packet::ChannelConfig {
+                sequence: self.packet_sequence,
+                hash: self.channels_hash,
+                verified: self.channels_verified,
+            });
+            self.packet_sequence += 1;
+            self.send_channels_hash = false;
+        }
+
+        for ping_id in self.pings_to_send.drain(..) {
+            packets.push(Packet::Ping {
+                sequence: self.packet_sequence,
+                ping_id,
+            });
+            self.packet_sequence += 1;
+        }
+        for ping_id in self.pongs_to_send.drain(..) {
+            packets.push(Packet::Pong {
+                sequence: self.packet_sequence,
+                ping_id,
+            });
+            self.packet_sequence += 1;
+        }
+
+        let max_bytes = match self.bandwidth_budget {
+            Some(budget) => budget.min(self.available_bytes_per_tick),
+            None => self.available_bytes_per_tick,
+        };
+        let mut available_bytes = max_bytes;
         for order in self.channel_send_order.iter() {
             match order {
                 ChannelOrder::Reliable(channel_id) => {
@@ -595,6 +968,19 @@ impl RenetClient {
             }
         }
 
+        if let Some(budget) = &mut self.bandwidth_budget {
+            *budget -= max_bytes - available_bytes;
+        }
+
+        if let Some(heartbeat_interval) = self.heartbeat_interval {
+            if packets.is_empty() && self.pending_acks.is_empty() && self.current_time - self.last_packet_sent_at >= heartbeat_interval {
+                packets.push(Packet::Heartbeat {
+                    sequence: self.packet_sequence,
+                });
+                self.packet_sequence += 1;
+            }
+        }
+
         if !self.pending_acks.is_empty() {
             let ack_packet = Packet::Ack {
                 sequence: self.packet_sequence,
@@ -605,6 +991,9 @@ impl RenetClient {
         }
 
         let sent_at = self.current_time;
+        if !packets.is_empty() {
+            self.last_packet_sent_at = sent_at;
+        }
         for packet in packets.iter() {
             match packet {
                 Packet::SmallReliable {
@@ -623,6 +1012,22 @@ impl RenetClient {
                         },
                     );
                 }
+                Packet::ReliableCancel {
+                    sequence,
+                    channel_id,
+                    message_ids,
+                } => {
+                    self.sent_packets.insert(
+                        *sequence,
+                        PacketSent {
+                            sent_at,
+                            info: PacketSentInfo::ReliableMessages {
+                                channel_id: *channel_id,
+                                message_ids: message_ids.clone(),
+                            },
+                        },
+                    );
+                }
                 Packet::ReliableSlice {
                     sequence,
                     channel_id,
@@ -649,7 +1054,12 @@ impl RenetClient {
                         },
                     );
                 }
-                Packet::UnreliableSlice { sequence, .. } => {
+                Packet::UnreliableSlice { sequence, .. }
+                | Packet::UnreliableParity { sequence, .. }
+                | Packet::ChannelConfig { sequence, .. }
+                | Packet::Heartbeat { sequence }
+                | Packet::Ping { sequence, .. }
+                | Packet::Pong { sequence, .. } => {
                     self.sent_packets.insert(
                         *sequence,
                         PacketSent {
@@ -694,6 +1104,113 @@ impl RenetClient {
         serialized_packets
     }
 
+    /// Sends a ping with the next packets, and returns its id.
+    /// Returns None if 64 pings are already waiting for an answer.
+    ///
+    /// The peer answers pings right away, independently of the channels, and the measured round-trip time
+    /// is returned by [`Self::receive_ping_result`]. Pings are not resent, so lost pings have no result,
+    /// and pings without an answer after 10 seconds stop counting as waiting.
+    pub fn ping(&mut self) -> Option<u64> {
+        if self.pending_pings.len() >= MAX_PENDING_PINGS {
+            return None;
+        }
+
+        let ping_id = self.next_ping_id;
+        self.next_ping_id += 1;
+        self.pending_pings.insert(ping_id, self.current_time);
+        self.pings_to_send.push(ping_id);
+
+        Some(ping_id)
+    }
+
+    /// Returns the result of a ping sent with [`Self::ping`] if available.
+    pub fn receive_ping_result(&mut self) -> Option<PingResult> {
+        self.ping_results.pop_front()
+    }
+
+    /// Returns the number of message bytes waiting to be sent, limited by `available_bytes_per_tick`.
+    pub(crate) fn bytes_to_send(&self) -> u64 {
+        let bytes: u64 = self
+            .send_channels
+            .iter()
+            .map(|channel| match channel {
+                SendChannel::Empty => 0,
+                SendChannel::Unreliable(unreliable_channel) => unreliable_channel.memory_usage() as u64,
+                SendChannel::Reliable(reliable_channel) => reliable_channel.bytes_to_send(self.current_time),
+            })
+            .sum();
+
+        bytes.min(self.available_bytes_per_tick)
+    }
+
+    /// Returns the bytes left in the budget set with [`Self::set_bandwidth_budget`].
+    pub(crate) fn bandwidth_budget(&self) -> Option<u64> {
+        self.bandwidth_budget
+    }
+
+    /// Limits the message bytes sent by [`Self::get_packets_to_send`] until the budget is changed, or removes the limit with `None`.
+    pub(crate) fn set_bandwidth_budget(&mut self, budget: Option<u64>) {
+        self.bandwidth_budget = budget;
+    }
+
+    /// Sets the rate limit of messages received in a channel, or removes it with `None`.
+    pub(crate) fn set_rate_limit(&mut self, channel_id: u8, limit: Option<RateLimit>) {
+        match limit {
+            Some(limit) => {
+                self.rate_limiters.insert(channel_id, RateLimiter::new(limit, self.current_time));
+            }
+            None => {
+                self.rate_limiters.remove(&channel_id);
+            }
+        }
+    }
+
+    /// Returns the channels that exceeded their rate limit with [`RateLimitPolicy::Warn`] since the last call.
+    pub(crate) fn take_rate_limited_channels(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.rate_limited_channels)
+    }
+
+    // Returns false if the packet exceeds the rate limit of its channel and should be dropped.
+    fn check_rate_limit(&mut self, packet: &Packet) -> bool {
+        let (channel_id, num_messages, num_bytes) = match packet {
+            Packet::SmallReliable { channel_id, messages, .. } => {
+                (*channel_id, messages.len(), messages.iter().map(|(_, message)| message.len()).sum())
+            }
+            Packet::SmallUnreliable { channel_id, messages, .. } => (*channel_id, messages.len(), messages.iter().map(Bytes::len).sum()),
+            Packet::ReliableSlice { channel_id, slice, .. } | Packet::UnreliableSlice { channel_id, slice, .. } => {
+                // Sliced messages are counted once, with their first slice
+                (*channel_id, usize::from(slice.slice_index == 0), slice.payload.len())
+            }
+            Packet::UnreliableParity { channel_id, parity, .. } => (*channel_id, 0, parity.len()),
+            // Each cancelled message is counted as a message
+            Packet::ReliableCancel {
+                channel_id, message_ids, ..
+            } => (*channel_id, message_ids.len(), 0),
+            Packet::ChannelConfig { .. } | Packet::Heartbeat { .. } | Packet::Ping { .. } | Packet::Pong { .. } | Packet::Ack { .. } => {
+                return true
+            }
+        };
+
+        let Some(rate_limiter) = self.rate_limiters.get_mut(&channel_id) else {
+            return true;
+        };
+        if rate_limiter.try_consume(num_messages as u64, num_bytes as u64, self.current_time) {
+            return true;
+        }
+
+        match rate_limiter.policy() {
+            RateLimitPolicy::Drop => {}
+            RateLimitPolicy::Warn => {
+                if !self.rate_limited_channels.contains(&channel_id) {
+                    self.rate_limited_channels.push(channel_id);
+                }
+            }
+            RateLimitPolicy::Disconnect => self.disconnect_with_reason(DisconnectReason::RateLimited { channel_id }),
+        }
+
+        false
+    }
+
     fn add_pending_ack(&mut self, sequence: u64) {
         if self.pending_acks.is_empty() {
             self.pending_acks.push(sequence..sequence + 1);
@@ -768,8 +1285,30 @@ impl RenetClient {
         }
     }
 
+    fn disconnect_invalid_channel(&mut self, channel_id: u8) {
+        // Packets can arrive before the channels hash of the peer,
+        // in that case an invalid channel is most likely caused by a different channel configuration
+        let reason = if self.channels_verified {
+            DisconnectReason::ReceivedInvalidChannelId(channel_id)
+        } else {
+            DisconnectReason::ChannelConfigMismatch
+        };
+        self.disconnect_with_reason(reason);
+    }
+
     pub(crate) fn disconnect_with_reason(&mut self, reason: DisconnectReason) {
         if !self.is_disconnected() {
+            #[cfg(feature = "tracing")]
+            match reason {
+                DisconnectReason::SendChannelError { channel_id, error } | DisconnectReason::ReceiveChannelError { channel_id, error } => {
+                    if error == crate::ChannelError::InvalidSliceMessage {
+                        tracing::warn!(channel_id, %error, "slice reassembly failed");
+                    } else {
+                        tracing::warn!(channel_id, %error, "channel error");
+                    }
+                }
+                _ => tracing::debug!(%reason, "connection disconnected"),
+            }
             self.connection_status = RenetConnectionStatus::Disconnected { reason };
         }
     }
@@ -839,13 +1378,151 @@ mod tests {
         let message: Bytes = vec![5; 5].into();
         connection.send_message(0, message);
 
+        // The message and the channels hash
         connection.get_packets_to_send();
-        assert_eq!(connection.sent_packets.len(), 1);
+        assert_eq!(connection.sent_packets.len(), 2);
 
         connection.update(Duration::from_secs(1));
-        assert_eq!(connection.sent_packets.len(), 1);
+        assert_eq!(connection.sent_packets.len(), 2);
 
         connection.update(Duration::from_secs(4));
         assert_eq!(connection.sent_packets.len(), 0);
     }
+
+    #[test]
+    fn heartbeat() {
+        let mut connection = RenetClient::new(ConnectionConfig::test(), true);
+        let mut peer = RenetClient::new_from_server(ConnectionConfig::test(), true);
+        connection.channels_verified = true;
+        connection.send_channels_hash = false;
+        assert!(connection.get_packets_to_send().is_empty());
+
+        connection.update(Duration::from_millis(999));
+        assert!(connection.get_packets_to_send().is_empty());
+
+        // The heartbeat is acked by the peer, measuring the round-trip time
+        connection.update(Duration::from_millis(1));
+        let packets = connection.get_packets_to_send();
+        assert_eq!(packets.len(), 1);
+        assert!(connection.get_packets_to_send().is_empty());
+        for packet in packets {
+            peer.process_packet(&packet);
+        }
+
+        connection.update(Duration::from_millis(50));
+        for packet in peer.get_packets_to_send() {
+            connection.process_packet(&packet);
+        }
+        assert!(connection.sent_packets.is_empty());
+        assert!((connection.rtt() - 0.05).abs() < 1e-9);
+
+        // No heartbeats when disabled
+        let mut config = ConnectionConfig::test();
+        config.heartbeat_interval = None;
+        let mut connection = RenetClient::new(config, true);
+        connection.channels_verified = true;
+        connection.send_channels_hash = false;
+        connection.update(Duration::from_secs(10));
+        assert!(connection.get_packets_to_send().is_empty());
+    }
+
+    #[test]
+    fn unknown_pong() {
+        let mut connection = RenetClient::new(ConnectionConfig::test(), false);
+        let ping_id = connection.ping().unwrap();
+
+        let mut buffer = [0u8; 32];
+        for ping_id in [ping_id + 1, ping_id, ping_id] {
+            let mut b = octets::OctetsMut::with_slice(&mut buffer);
+            Packet::Pong { sequence: 0, ping_id }.to_bytes(&mut b).unwrap();
+            let len = b.off();
+            connection.process_packet(&buffer[..len]);
+        }
+
+        // Only the first answer of a sent ping has a result
+        assert_eq!(
+            connection.receive_ping_result(),
+            Some(PingResult {
+                ping_id,
+                rtt: Duration::ZERO
+            })
+        );
+        assert_eq!(connection.receive_ping_result(), None);
+        assert!(!connection.is_disconnected());
+    }
+
+    #[test]
+    fn validate_config() {
+        assert_eq!(ConnectionConfig::test().validate(), Ok(()));
+
+        let mut channels = DefaultChannel::config();
+        channels[2].channel_id = 0;
+        assert_eq!(
+            ConnectionConfig::try_from_channels(DefaultChannel::config(), channels).unwrap_err(),
+            ConfigError::DuplicateChannelId {
+                direction: ChannelDirection::ClientToServer,
+                channel_id: 0
+            }
+        );
+
+        let mut channels = DefaultChannel::config();
+        channels[1].max_partial_messages = 0;
+        assert_eq!(
+            ConnectionConfig::try_from_shared_channels(channels).unwrap_err(),
+            ConfigError::ZeroMaxPartialMessages {
+                direction: ChannelDirection::ServerToClient,
+                channel_id: 1
+            }
+        );
+
+        let mut channels = DefaultChannel::config();
+        channels[1].send_type = SendType::ReliableUnordered {
+            resend_time: Duration::ZERO,
+        };
+        assert_eq!(
+            ConnectionConfig::try_from_shared_channels(channels).unwrap_err(),
+            ConfigError::ZeroResendTime {
+                direction: ChannelDirection::ServerToClient,
+                channel_id: 1
+            }
+        );
+
+        let mut channels = DefaultChannel::config();
+        channels[0].send_type = SendType::UnreliableFec { group_size: 9 };
+        assert!(matches!(
+            RenetClient::try_new(ConnectionConfig::from_shared_channels(channels), false),
+            Err(ConfigError::InvalidFecGroupSize { group_size: 9, .. })
+        ));
+
+        let mut config = ConnectionConfig::test();
+        config.client_channels_config[2].max_memory_usage_bytes = 0;
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::ZeroMaxMemoryUsage {
+                direction: ChannelDirection::ClientToServer,
+                channel_id: 2
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_config() {
+        let config = ConnectionConfig::test();
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""ReliableOrdered":{"resend_time":"300ms"}"#));
+
+        let deserialized: ConnectionConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.channels_hash(), config.channels_hash());
+        assert_eq!(deserialized.available_bytes_per_tick, config.available_bytes_per_tick);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_channel_config_defaults() {
+        let json = r#"{"channel_id":0,"max_memory_usage_bytes":1024,"send_type":"Unreliable"}"#;
+        let config: ChannelConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.max_message_size, 5 * 1024 * 1024);
+        assert_eq!(config.max_partial_messages, 64);
+    }
 }
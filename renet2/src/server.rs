@@ -1,8 +1,10 @@
-use crate::error::{ClientNotFound, DisconnectReason};
+use crate::channel::{MessageHandle, PendingMessage};
+use crate::error::{ClientNotFound, ConfigError, DisconnectReason, GroupError};
 use crate::packet::Payload;
-use crate::remote_connection::{ConnectionConfig, NetworkInfo, RenetClient};
-use crate::ClientId;
-use std::collections::{HashMap, VecDeque};
+use crate::rate_limit::RateLimit;
+use crate::remote_connection::{ConnectionConfig, NetworkInfo, PingResult, RenetClient};
+use crate::{ClientId, GroupId};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -11,8 +13,26 @@ use bytes::Bytes;
 #[derive(Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
 pub enum ServerEvent {
-    ClientConnected { client_id: ClientId },
-    ClientDisconnected { client_id: ClientId, reason: DisconnectReason },
+    ClientConnected {
+        client_id: ClientId,
+    },
+    ClientDisconnected {
+        client_id: ClientId,
+        reason: DisconnectReason,
+    },
+    /// The client exceeded the rate limit of a channel with [`RateLimitPolicy::Warn`](crate::RateLimitPolicy::Warn).
+    ///
+    /// Emitted at most once per channel on each server update.
+    ClientRateLimited {
+        client_id: ClientId,
+        channel_id: u8,
+    },
+    /// A ping sent with [`RenetServer::ping`] was answered by the client.
+    PingCompleted {
+        client_id: ClientId,
+        ping_id: u64,
+        rtt: Duration,
+    },
 }
 
 #[derive(Debug)]
@@ -20,15 +40,93 @@ pub enum ServerEvent {
 pub struct RenetServer {
     connections: HashMap<ClientId, RenetClient>,
     connection_config: ConnectionConfig,
+    rate_limits: Vec<(u8, RateLimit)>,
+    groups: HashMap<GroupId, HashSet<ClientId>>,
+    client_groups: HashMap<ClientId, HashSet<GroupId>>,
+    next_group_id: GroupId,
+    max_bytes_per_tick: Option<u64>,
+    bandwidth_priorities: HashMap<ClientId, u32>,
+    bandwidth_allocated: bool,
+    remaining_tick_bytes: u64,
     events: VecDeque<ServerEvent>,
+    #[cfg(feature = "metrics")]
+    metrics: crate::metrics::ServerMetrics,
 }
 
 impl RenetServer {
     pub fn new(connection_config: ConnectionConfig) -> Self {
         Self {
             connections: HashMap::new(),
-            connection_config,
+            rate_limits: Vec::new(),
+            groups: HashMap::new(),
+            client_groups: HashMap::new(),
+            next_group_id: 0,
+            max_bytes_per_tick: None,
+            bandwidth_priorities: HashMap::new(),
+            bandwidth_allocated: false,
+            remaining_tick_bytes: 0,
             events: VecDeque::new(),
+            #[cfg(feature = "metrics")]
+            metrics: crate::metrics::ServerMetrics::new(&connection_config),
+            connection_config,
+        }
+    }
+
+    /// Same as [`Self::new`], but returns an error if the config is invalid
+    /// instead of panicking when adding connections.
+    ///
+    /// See [`ConnectionConfig::validate`].
+    pub fn try_new(connection_config: ConnectionConfig) -> Result<Self, ConfigError> {
+        connection_config.validate()?;
+        Ok(Self::new(connection_config))
+    }
+
+    /// Sets the number of message bytes that the server can send per tick across all clients, or removes the limit with `None`.
+    ///
+    /// The bytes are shared fairly between clients when packets are generated. Clients that need less than
+    /// their share keep the difference available to the others, and the rest is split in proportion to
+    /// the priority of each client. Each client is still limited by its `available_bytes_per_tick`.
+    ///
+    /// Unreliable messages that don't fit are dropped, and reliable messages are sent on later ticks.
+    /// Clients with pending reliable messages keep the bytes they couldn't use for the next ticks, so a client
+    /// whose share is smaller than a message slice still sends it after a few ticks. The saved bytes can only be
+    /// sent while the tick has bytes left, so the limit is never exceeded.
+    ///
+    /// Clients added after the bytes of a tick were shared can only send on the next tick.
+    pub fn set_max_bytes_per_tick(&mut self, max_bytes_per_tick: Option<u64>) {
+        self.max_bytes_per_tick = max_bytes_per_tick;
+        self.bandwidth_allocated = false;
+        if max_bytes_per_tick.is_none() {
+            for connection in self.connections.values_mut() {
+                connection.set_bandwidth_budget(None);
+            }
+        }
+    }
+
+    /// Sets the priority of a client when sharing the bytes set with [`Self::set_max_bytes_per_tick`].
+    ///
+    /// Clients have a priority of 1 by default. A client with priority 2 gets twice the share of a client with priority 1.
+    pub fn set_bandwidth_priority(&mut self, client_id: ClientId, priority: u32) -> Result<(), ClientNotFound> {
+        if !self.connections.contains_key(&client_id) {
+            return Err(ClientNotFound);
+        }
+
+        self.bandwidth_priorities.insert(client_id, priority.max(1));
+        Ok(())
+    }
+
+    /// Sets the rate limit for messages that each client sends over a channel, or removes it with `None`.
+    ///
+    /// Applies to current and future connections.
+    pub fn set_rate_limit<I: Into<u8>>(&mut self, channel_id: I, limit: Option<RateLimit>) {
+        let channel_id = channel_id.into();
+        self.rate_limits.retain(|(id, _)| *id != channel_id);
+        if let Some(limit) = limit {
+            self.rate_limits.push((channel_id, limit));
+        }
+
+        for connection in self.connections.values_mut() {
+            connection.set_rate_limit(channel_id, limit);
         }
     }
 
@@ -52,6 +150,13 @@ impl RenetServer {
         let mut client = RenetClient::new_from_server(connection_config, socket_is_reliable);
         // Consider newly added connections as connected
         client.set_connected();
+        for (channel_id, limit) in self.rate_limits.iter() {
+            client.set_rate_limit(*channel_id, Some(*limit));
+        }
+        if self.max_bytes_per_tick.is_some() {
+            // The bytes of this tick may already be shared, the client gets its share on the next tick
+            client.set_bandwidth_budget(Some(0));
+        }
         self.connections.insert(client_id, client);
         self.events.push_back(ServerEvent::ClientConnected { client_id })
     }
@@ -70,6 +175,12 @@ impl RenetServer {
     ///         ServerEvent::ClientDisconnected { client_id, reason } => {
     ///             println!("Client {client_id} disconnected: {reason}");
     ///         }
+    ///         ServerEvent::ClientRateLimited { client_id, channel_id } => {
+    ///             println!("Client {client_id} exceeded the rate limit of channel {channel_id}");
+    ///         }
+    ///         ServerEvent::PingCompleted { client_id, rtt, .. } => {
+    ///             println!("Client {client_id} answered a ping in {rtt:?}");
+    ///         }
     ///     }
     /// }
     /// ```
@@ -138,6 +249,10 @@ impl RenetServer {
     /// </p>
     pub fn remove_connection(&mut self, client_id: ClientId) {
         if let Some(connection) = self.connections.remove(&client_id) {
+            self.remove_from_all_groups(client_id);
+            self.bandwidth_priorities.remove(&client_id);
+            #[cfg(feature = "metrics")]
+            self.metrics.remove_connection(client_id);
             let reason = connection.disconnect_reason().unwrap_or(DisconnectReason::Transport);
             self.events.push_back(ServerEvent::ClientDisconnected { client_id, reason });
         }
@@ -179,6 +294,174 @@ impl RenetServer {
         }
     }
 
+    /// Creates a new empty group of clients.
+    pub fn create_group(&mut self) -> GroupId {
+        let group_id = self.next_group_id;
+        self.next_group_id += 1;
+        self.groups.insert(group_id, HashSet::new());
+        group_id
+    }
+
+    /// Destroys a group, removing all its clients from it.
+    pub fn destroy_group(&mut self, group_id: GroupId) -> Result<(), GroupError> {
+        let clients = self.groups.remove(&group_id).ok_or(GroupError::GroupNotFound)?;
+        for client_id in clients {
+            if let Some(groups) = self.client_groups.get_mut(&client_id) {
+                groups.remove(&group_id);
+                if groups.is_empty() {
+                    self.client_groups.remove(&client_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a client to a group, it does nothing if the client is already in the group.
+    ///
+    /// Clients are removed from all their groups when they are disconnected.
+    pub fn add_to_group(&mut self, group_id: GroupId, client_id: ClientId) -> Result<(), GroupError> {
+        let clients = self.groups.get_mut(&group_id).ok_or(GroupError::GroupNotFound)?;
+        if !self.connections.contains_key(&client_id) {
+            return Err(GroupError::ClientNotFound);
+        }
+
+        clients.insert(client_id);
+        self.client_groups.entry(client_id).or_default().insert(group_id);
+        Ok(())
+    }
+
+    /// Removes a client from a group, it does nothing if the client is not in the group.
+    pub fn remove_from_group(&mut self, group_id: GroupId, client_id: ClientId) -> Result<(), GroupError> {
+        let clients = self.groups.get_mut(&group_id).ok_or(GroupError::GroupNotFound)?;
+        clients.remove(&client_id);
+        if let Some(groups) = self.client_groups.get_mut(&client_id) {
+            groups.remove(&group_id);
+            if groups.is_empty() {
+                self.client_groups.remove(&client_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the group exists.
+    pub fn has_group(&self, group_id: GroupId) -> bool {
+        self.groups.contains_key(&group_id)
+    }
+
+    /// Returns whether the client is in the group.
+    pub fn is_in_group(&self, group_id: GroupId, client_id: ClientId) -> bool {
+        self.groups.get(&group_id).is_some_and(|clients| clients.contains(&client_id))
+    }
+
+    /// Return ids for all clients in a group (iterator), it is empty if the group does not exist.
+    pub fn group_clients_iter(&self, group_id: GroupId) -> impl Iterator<Item = ClientId> + '_ {
+        self.groups.get(&group_id).into_iter().flatten().copied()
+    }
+
+    /// Return ids for all clients in a group, it is empty if the group does not exist.
+    pub fn group_clients(&self, group_id: GroupId) -> Vec<ClientId> {
+        self.group_clients_iter(group_id).collect()
+    }
+
+    /// Return ids for all groups of a client.
+    pub fn client_groups(&self, client_id: ClientId) -> Vec<GroupId> {
+        match self.client_groups.get(&client_id) {
+            Some(groups) => groups.iter().copied().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Send a message to all clients in a group over a channel.
+    pub fn broadcast_to_group<I: Into<u8>, B: Into<Bytes>>(
+        &mut self,
+        group_id: GroupId,
+        channel_id: I,
+        message: B,
+    ) -> Result<(), GroupError> {
+        let clients = self.groups.get(&group_id).ok_or(GroupError::GroupNotFound)?;
+        let channel_id = channel_id.into();
+        let message = message.into();
+        for client_id in clients {
+            if let Some(connection) = self.connections.get_mut(client_id) {
+                connection.send_message(channel_id, message.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a message to all clients in a group, except the specified one, over a channel.
+    pub fn broadcast_to_group_except<I: Into<u8>, B: Into<Bytes>>(
+        &mut self,
+        group_id: GroupId,
+        except_id: ClientId,
+        channel_id: I,
+        message: B,
+    ) -> Result<(), GroupError> {
+        let clients = self.groups.get(&group_id).ok_or(GroupError::GroupNotFound)?;
+        let channel_id = channel_id.into();
+        let message = message.into();
+        for client_id in clients {
+            if except_id == *client_id {
+                continue;
+            }
+
+            if let Some(connection) = self.connections.get_mut(client_id) {
+                connection.send_message(channel_id, message.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // Splits the bytes between clients with weighted max-min fairness.
+    //
+    // Clients that still have bytes to send keep the budget they couldn't use, like in deficit round robin.
+    // Slices and reliable messages are only sent whole, so this lets clients with a small share
+    // save up for them over a few ticks instead of never sending them.
+    fn allocate_bandwidth(&mut self, max_bytes_per_tick: u64) {
+        let mut demands: Vec<(ClientId, u64, u64, u64)> = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| !connection.is_disconnected())
+            .map(|(client_id, connection)| {
+                let priority = self.bandwidth_priorities.get(client_id).copied().unwrap_or(1);
+                let bytes = connection.bytes_to_send();
+                let deficit = if bytes > 0 { connection.bandwidth_budget().unwrap_or(0) } else { 0 };
+                (*client_id, bytes, priority as u64, deficit)
+            })
+            .collect();
+
+        // Clients that need the least bytes relative to their priority are served first,
+        // so the bytes they don't need are shared by the remaining clients.
+        demands.sort_unstable_by(|(a_id, a_bytes, a_priority, _), (b_id, b_bytes, b_priority, _)| {
+            (*a_bytes as u128 * *b_priority as u128)
+                .cmp(&(*b_bytes as u128 * *a_priority as u128))
+                .then(a_id.cmp(b_id))
+        });
+
+        let mut remaining_bytes = max_bytes_per_tick;
+        let mut remaining_priority: u64 = demands.iter().map(|(_, _, priority, _)| priority).sum();
+        for (client_id, bytes, priority, deficit) in demands {
+            let share = (remaining_bytes as u128 * priority as u128 / remaining_priority as u128) as u64;
+            let granted = bytes.min(share);
+            remaining_bytes -= granted;
+            remaining_priority -= priority;
+
+            let budget = bytes.min(granted + deficit);
+            self.connections.get_mut(&client_id).unwrap().set_bandwidth_budget(Some(budget));
+        }
+    }
+
+    fn remove_from_all_groups(&mut self, client_id: ClientId) {
+        let Some(groups) = self.client_groups.remove(&client_id) else {
+            return;
+        };
+        for group_id in groups {
+            if let Some(clients) = self.groups.get_mut(&group_id) {
+                clients.remove(&client_id);
+            }
+        }
+    }
+
     /// Returns the available memory in bytes of a channel for the given client.
     /// Returns 0 if the client is not found.
     pub fn channel_available_memory<I: Into<u8>>(&self, client_id: ClientId, channel_id: I) -> usize {
@@ -205,6 +488,57 @@ impl RenetServer {
         }
     }
 
+    /// Same as [`Self::send_message`], but returns a handle to the message that can be used to cancel it,
+    /// or None if the message was dropped.
+    ///
+    /// See [`RenetClient::send_message_with_handle`].
+    pub fn send_message_with_handle<I: Into<u8>, B: Into<Bytes>>(
+        &mut self,
+        client_id: ClientId,
+        channel_id: I,
+        message: B,
+    ) -> Option<MessageHandle> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.send_message_with_handle(channel_id, message),
+            None => {
+                log::error!("Tried to send a message to invalid client {:?}", client_id);
+                None
+            }
+        }
+    }
+
+    /// Returns the messages waiting to be sent to a client in a channel, or waiting for an ack in reliable channels.
+    ///
+    /// See [`RenetClient::pending_messages`].
+    pub fn pending_messages<I: Into<u8>>(&self, client_id: ClientId, channel_id: I) -> Vec<PendingMessage> {
+        match self.connections.get(&client_id) {
+            Some(connection) => connection.pending_messages(channel_id),
+            None => Vec::new(),
+        }
+    }
+
+    /// Cancels a pending message sent to a client, returns false if the message was not cancelled.
+    ///
+    /// See [`RenetClient::cancel_message`].
+    pub fn cancel_message(&mut self, client_id: ClientId, handle: MessageHandle) -> bool {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => connection.cancel_message(handle),
+            None => false,
+        }
+    }
+
+    /// Sends a ping to a client, and returns its id.
+    /// Returns None if 64 pings to the client are already waiting for an answer.
+    ///
+    /// The round-trip time is reported by [`ServerEvent::PingCompleted`] after the server update following the answer.
+    /// See [`RenetClient::ping`].
+    pub fn ping(&mut self, client_id: ClientId) -> Result<Option<u64>, ClientNotFound> {
+        match self.connections.get_mut(&client_id) {
+            Some(connection) => Ok(connection.ping()),
+            None => Err(ClientNotFound),
+        }
+    }
+
     /// Receive a message from a client over a channel.
     pub fn receive_message<I: Into<u8>>(&mut self, client_id: ClientId, channel_id: I) -> Option<Bytes> {
         if let Some(connection) = self.connections.get_mut(&client_id) {
@@ -249,9 +583,19 @@ impl RenetServer {
     /// Advances the server by the duration.
     /// Should be called every tick
     pub fn update(&mut self, duration: Duration) {
-        for connection in self.connections.values_mut() {
+        self.bandwidth_allocated = false;
+        for (&client_id, connection) in self.connections.iter_mut() {
             connection.update(duration);
+            for channel_id in connection.take_rate_limited_channels() {
+                self.events.push_back(ServerEvent::ClientRateLimited { client_id, channel_id });
+            }
+            while let Some(PingResult { ping_id, rtt }) = connection.receive_ping_result() {
+                self.events.push_back(ServerEvent::PingCompleted { client_id, ping_id, rtt });
+            }
         }
+
+        #[cfg(feature = "metrics")]
+        self.metrics.record(&self.connections, duration);
     }
 
     /// Returns a list of packets to be sent to the client.
@@ -259,10 +603,30 @@ impl RenetServer {
     /// <strong>Note:</strong> This should only be called by the transport layer.
     /// </p>
     pub fn get_packets_to_send(&mut self, client_id: ClientId) -> Result<Vec<Payload>, ClientNotFound> {
-        match self.connections.get_mut(&client_id) {
-            Some(connection) => Ok(connection.get_packets_to_send()),
-            None => Err(ClientNotFound),
+        if let Some(max_bytes_per_tick) = self.max_bytes_per_tick {
+            if !self.bandwidth_allocated {
+                self.allocate_bandwidth(max_bytes_per_tick);
+                self.remaining_tick_bytes = max_bytes_per_tick;
+                self.bandwidth_allocated = true;
+            }
         }
+
+        let Some(connection) = self.connections.get_mut(&client_id) else {
+            return Err(ClientNotFound);
+        };
+        let Some(budget) = connection.bandwidth_budget() else {
+            return Ok(connection.get_packets_to_send());
+        };
+
+        // The budget can include bytes saved from previous ticks, only the bytes left in this tick can be used
+        let tick_budget = budget.min(self.remaining_tick_bytes);
+        connection.set_bandwidth_budget(Some(tick_budget));
+        let packets = connection.get_packets_to_send();
+        let used_bytes = tick_budget - connection.bandwidth_budget().unwrap_or(0);
+        connection.set_bandwidth_budget(Some(budget - used_bytes));
+        self.remaining_tick_bytes -= used_bytes;
+
+        Ok(packets)
     }
 
     /// Process a packet received from the client.
@@ -295,6 +659,10 @@ impl RenetServer {
         }
         client.disconnect();
         if self.connections.remove(&client_id).is_some() {
+            self.remove_from_all_groups(client_id);
+            self.bandwidth_priorities.remove(&client_id);
+            #[cfg(feature = "metrics")]
+            self.metrics.remove_connection(client_id);
             self.events.push_back(ServerEvent::ClientDisconnected {
                 client_id,
                 reason: DisconnectReason::DisconnectedByClient,
@@ -1,5 +1,10 @@
 use bytes::Bytes;
-use renet2::{ConnectionConfig, DefaultChannel, DisconnectReason, RenetClient, RenetServer, ServerEvent};
+use std::time::Duration;
+
+use renet2::{
+    ChannelConfig, ConnectionConfig, DefaultChannel, DisconnectReason, GroupError, PingResult, RateLimit, RateLimitPolicy, RenetClient,
+    RenetServer, SendType, ServerEvent,
+};
 
 pub fn init_log() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -100,3 +105,442 @@ fn test_local_client() {
             }
     );
 }
+
+#[test]
+fn test_unreliable_fec_channel() {
+    init_log();
+    let channels = vec![ChannelConfig {
+        channel_id: 0,
+        max_memory_usage_bytes: 5 * 1024 * 1024,
+        max_message_size: 5 * 1024 * 1024,
+        max_partial_messages: 64,
+        send_type: SendType::UnreliableFec { group_size: 4 },
+    }];
+    let mut server = RenetServer::new(ConnectionConfig::from_shared_channels(channels.clone()));
+    let mut client = RenetClient::new(ConnectionConfig::from_shared_channels(channels), false);
+
+    let client_id = 0;
+    server.add_connection(client_id, false);
+
+    // Exchange the channels hash before losing packets
+    for packet in client.get_packets_to_send() {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+
+    // One message per packet, lose one packet of each group
+    let mut sent = 0;
+    for tick in 0..16u8 {
+        server.send_message(client_id, 0, vec![tick; 20]);
+        for (i, packet) in server.get_packets_to_send(client_id).unwrap().into_iter().enumerate() {
+            assert!(packet.len() < 1300);
+            sent += 1;
+            if i == 0 && tick % 4 == 1 {
+                continue;
+            }
+            client.process_packet(&packet);
+        }
+    }
+
+    // 16 data packets, 4 parity packets and 16 acks of the client packet
+    assert_eq!(sent, 36);
+    assert_eq!(client.disconnect_reason(), None);
+
+    let mut received = Vec::new();
+    while let Some(message) = client.receive_message(0) {
+        received.push(message[0]);
+    }
+    received.sort();
+    assert_eq!(received, (0..16).collect::<Vec<u8>>());
+    assert_eq!(client.packets_recovered(), 4);
+    assert_eq!(client.network_info().packets_recovered, 4);
+}
+
+#[test]
+fn test_rate_limit() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut client = RenetClient::new(ConnectionConfig::test(), false);
+    let client_id = 0;
+    server.add_connection(client_id, false);
+    server.get_event().unwrap();
+
+    let limit = RateLimit {
+        messages_per_second: 10,
+        bytes_per_second: 10_000,
+        policy: RateLimitPolicy::Warn,
+    };
+    server.set_rate_limit(DefaultChannel::Unreliable, Some(limit));
+    server.set_rate_limit(DefaultChannel::ReliableOrdered, Some(limit));
+
+    // Each tick sends one packet with 4 messages per channel
+    let mut unreliable_received = 0;
+    let mut reliable_received = 0;
+    for tick in 0..20 {
+        for _ in 0..4 {
+            client.send_message(DefaultChannel::Unreliable, Bytes::from("test"));
+            client.send_message(DefaultChannel::ReliableOrdered, Bytes::from("test"));
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+
+        let duration = Duration::from_millis(100);
+        client.update(duration);
+        server.update(duration);
+        if tick == 0 {
+            assert!(server.get_event().is_none());
+        }
+
+        while server.receive_message(client_id, DefaultChannel::Unreliable).is_some() {
+            unreliable_received += 1;
+        }
+        while server.receive_message(client_id, DefaultChannel::ReliableOrdered).is_some() {
+            reliable_received += 1;
+        }
+    }
+
+    // Burst of 10 messages, then 1 message every 100ms, plus the packet that went into debt
+    assert!(unreliable_received <= 10 + 20 + 4);
+    assert!(unreliable_received < 80);
+    assert_eq!(
+        server.get_event(),
+        Some(ServerEvent::ClientRateLimited {
+            client_id,
+            channel_id: DefaultChannel::Unreliable.into()
+        })
+    );
+    assert!(server.is_connected(client_id));
+
+    // Reliable messages are resent after being dropped, so they are delayed instead of lost
+    for _ in 0..100 {
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+        for packet in server.get_packets_to_send(client_id).unwrap() {
+            client.process_packet(&packet);
+        }
+
+        let duration = Duration::from_millis(100);
+        client.update(duration);
+        server.update(duration);
+        while server.receive_message(client_id, DefaultChannel::ReliableOrdered).is_some() {
+            reliable_received += 1;
+        }
+    }
+    assert_eq!(reliable_received, 80);
+
+    // Disconnect policy
+    server.set_rate_limit(
+        DefaultChannel::Unreliable,
+        Some(RateLimit {
+            policy: RateLimitPolicy::Disconnect,
+            ..limit
+        }),
+    );
+    for _ in 0..2 {
+        for _ in 0..20 {
+            client.send_message(DefaultChannel::Unreliable, Bytes::from("test"));
+        }
+        for packet in client.get_packets_to_send() {
+            server.process_packet_from(&packet, client_id).unwrap();
+        }
+    }
+    assert_eq!(
+        server.disconnect_reason(client_id),
+        Some(DisconnectReason::RateLimited {
+            channel_id: DefaultChannel::Unreliable.into()
+        })
+    );
+}
+
+#[test]
+fn test_client_groups() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut clients: Vec<RenetClient> = (0..3).map(|client_id| server.new_local_client(client_id)).collect();
+
+    let group = server.create_group();
+    let other_group = server.create_group();
+    assert_ne!(group, other_group);
+    assert_eq!(server.add_to_group(group, 3), Err(GroupError::ClientNotFound));
+    server.add_to_group(group, 0).unwrap();
+    server.add_to_group(group, 1).unwrap();
+    server.add_to_group(other_group, 1).unwrap();
+    server.add_to_group(other_group, 2).unwrap();
+
+    let mut members = server.group_clients(group);
+    members.sort();
+    assert_eq!(members, vec![0, 1]);
+    assert!(server.is_in_group(other_group, 2));
+    let mut groups = server.client_groups(1);
+    groups.sort();
+    assert_eq!(groups, vec![group, other_group]);
+
+    server.broadcast_to_group(group, DefaultChannel::ReliableOrdered, "group").unwrap();
+    server
+        .broadcast_to_group_except(other_group, 2, DefaultChannel::ReliableOrdered, "other group")
+        .unwrap();
+    for (client_id, client) in clients.iter_mut().enumerate() {
+        server.process_local_client(client_id as u64, client).unwrap();
+    }
+
+    let received = |client: &mut RenetClient| {
+        let mut messages = Vec::new();
+        while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
+            messages.push(message);
+        }
+        messages
+    };
+    assert_eq!(received(&mut clients[0]), vec!["group"]);
+    assert_eq!(received(&mut clients[1]), vec!["group", "other group"]);
+    assert!(received(&mut clients[2]).is_empty());
+
+    // Disconnected clients are removed from their groups
+    server.disconnect_local_client(1, &mut clients[1]);
+    assert_eq!(server.group_clients(group), vec![0]);
+    assert_eq!(server.group_clients(other_group), vec![2]);
+    assert!(server.client_groups(1).is_empty());
+
+    server.remove_from_group(group, 0).unwrap();
+    assert!(server.group_clients(group).is_empty());
+    server.destroy_group(other_group).unwrap();
+    assert!(!server.has_group(other_group));
+    assert!(server.client_groups(2).is_empty());
+    assert_eq!(
+        server.broadcast_to_group(other_group, DefaultChannel::ReliableOrdered, "destroyed"),
+        Err(GroupError::GroupNotFound)
+    );
+}
+
+#[test]
+fn test_server_bandwidth_budget() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut clients: Vec<RenetClient> = (0..3).map(|client_id| server.new_local_client(client_id)).collect();
+
+    server.set_max_bytes_per_tick(Some(6000));
+    server.set_bandwidth_priority(2, 2).unwrap();
+    assert!(server.set_bandwidth_priority(3, 2).is_err());
+
+    let message = Bytes::from(vec![7u8; 1000]);
+    server.send_message(0, DefaultChannel::ReliableUnordered, message.clone());
+    for _ in 0..10 {
+        server.send_message(1, DefaultChannel::ReliableUnordered, message.clone());
+        server.send_message(2, DefaultChannel::ReliableUnordered, message.clone());
+    }
+
+    let tick = |server: &mut RenetServer, clients: &mut Vec<RenetClient>| -> Vec<usize> {
+        server.update(Duration::from_millis(16));
+        clients
+            .iter_mut()
+            .enumerate()
+            .map(|(client_id, client)| {
+                client.update(Duration::from_millis(16));
+                server.process_local_client(client_id as u64, client).unwrap();
+                let mut received = 0;
+                while client.receive_message(DefaultChannel::ReliableUnordered).is_some() {
+                    received += 1;
+                }
+                received
+            })
+            .collect()
+    };
+
+    // The first client needs less than its share, the rest is split by priority
+    assert_eq!(tick(&mut server, &mut clients), vec![1, 1, 3]);
+    assert_eq!(tick(&mut server, &mut clients), vec![0, 2, 4]);
+
+    server.set_max_bytes_per_tick(None);
+    assert_eq!(tick(&mut server, &mut clients), vec![0, 7, 3]);
+}
+
+#[test]
+fn test_server_bandwidth_budget_small_shares() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut clients: Vec<RenetClient> = (0..20).map(|client_id| server.new_local_client(client_id)).collect();
+
+    // Each client gets a share of 300 bytes, less than a slice
+    server.set_max_bytes_per_tick(Some(6000));
+    let message = Bytes::from(vec![7u8; 3000]);
+    server.broadcast_message(DefaultChannel::ReliableUnordered, message.clone());
+
+    let mut received = vec![false; clients.len()];
+    for _ in 0..30 {
+        server.update(Duration::from_millis(16));
+        let mut sent_slices = 0;
+        for (client_id, client) in clients.iter_mut().enumerate() {
+            client.update(Duration::from_millis(16));
+            let packets = server.get_packets_to_send(client_id as u64).unwrap();
+            sent_slices += packets.iter().filter(|packet| packet.len() > 1000).count();
+            for packet in packets {
+                client.process_packet(&packet);
+            }
+            for packet in client.get_packets_to_send() {
+                server.process_packet_from(&packet, client_id as u64).unwrap();
+            }
+            if let Some(received_message) = client.receive_message(DefaultChannel::ReliableUnordered) {
+                assert_eq!(received_message, message);
+                received[client_id] = true;
+            }
+        }
+        // The saved bytes don't let a tick exceed the limit of 5 slices
+        assert!(sent_slices <= 5);
+    }
+
+    assert!(received.iter().all(|received| *received));
+}
+
+#[test]
+fn test_server_bandwidth_budget_new_connection() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut client = server.new_local_client(0);
+    server.set_max_bytes_per_tick(Some(6000));
+    server.update(Duration::from_millis(16));
+    server.process_local_client(0, &mut client).unwrap();
+
+    // Added after the bytes of this tick were shared
+    let mut new_client = server.new_local_client(1);
+    server.send_message(1, DefaultChannel::ReliableOrdered, vec![7u8; 1000]);
+    server.process_local_client(1, &mut new_client).unwrap();
+    assert!(new_client.receive_message(DefaultChannel::ReliableOrdered).is_none());
+
+    server.update(Duration::from_millis(16));
+    server.process_local_client(1, &mut new_client).unwrap();
+    assert!(new_client.receive_message(DefaultChannel::ReliableOrdered).is_some());
+}
+
+#[test]
+fn test_channel_config_mismatch() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut client_channels = DefaultChannel::config();
+    client_channels.pop();
+    let mut client = RenetClient::new(ConnectionConfig::from_shared_channels(client_channels), false);
+
+    let client_id = 0;
+    server.add_connection(client_id, false);
+    client.set_connected();
+
+    let client_packets = client.get_packets_to_send();
+    for packet in server.get_packets_to_send(client_id).unwrap() {
+        client.process_packet(&packet);
+    }
+    for packet in client_packets {
+        server.process_packet_from(&packet, client_id).unwrap();
+    }
+
+    assert_eq!(client.disconnect_reason(), Some(DisconnectReason::ChannelConfigMismatch));
+    assert_eq!(server.disconnections_id(), vec![client_id]);
+    server.remove_connection(client_id);
+    assert_eq!(server.get_event(), Some(ServerEvent::ClientConnected { client_id }));
+    assert_eq!(
+        server.get_event(),
+        Some(ServerEvent::ClientDisconnected {
+            client_id,
+            reason: DisconnectReason::ChannelConfigMismatch
+        })
+    );
+
+    // Same channels in a different priority order are compatible
+    let mut reordered = DefaultChannel::config();
+    reordered.reverse();
+    assert_eq!(
+        ConnectionConfig::from_shared_channels(reordered).channels_hash(),
+        ConnectionConfig::test().channels_hash()
+    );
+}
+
+#[test]
+fn test_cancel_message() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut client = server.new_local_client(0);
+
+    let first = server
+        .send_message_with_handle(0, DefaultChannel::ReliableUnordered, vec![1; 3000])
+        .unwrap();
+    let second = server
+        .send_message_with_handle(0, DefaultChannel::ReliableUnordered, vec![2; 10])
+        .unwrap();
+    let unreliable = server.send_message_with_handle(0, DefaultChannel::Unreliable, vec![3; 10]).unwrap();
+    let ordered = server
+        .send_message_with_handle(0, DefaultChannel::ReliableOrdered, vec![4; 10])
+        .unwrap();
+    assert_eq!(first.channel_id(), u8::from(DefaultChannel::ReliableUnordered));
+    assert_eq!(server.pending_messages(0, DefaultChannel::ReliableUnordered).len(), 2);
+
+    assert!(server.cancel_message(0, first));
+    assert!(server.cancel_message(0, unreliable));
+    assert!(!server.cancel_message(0, ordered));
+    let pending = server.pending_messages(0, DefaultChannel::ReliableUnordered);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].handle, second);
+    assert_eq!(pending[0].size_bytes, 10);
+    assert!(!pending[0].sent);
+
+    server.process_local_client(0, &mut client).unwrap();
+    assert_eq!(client.receive_message(DefaultChannel::ReliableUnordered).unwrap(), vec![2; 10]);
+    assert!(client.receive_message(DefaultChannel::ReliableUnordered).is_none());
+    assert!(client.receive_message(DefaultChannel::Unreliable).is_none());
+    assert!(client.is_connected());
+
+    // Acked messages are no longer pending
+    server.process_local_client(0, &mut client).unwrap();
+    assert!(server.pending_messages(0, DefaultChannel::ReliableUnordered).is_empty());
+    assert!(!server.cancel_message(0, second));
+}
+
+#[test]
+fn test_ping() {
+    init_log();
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut client = server.new_local_client(0);
+
+    let client_ping = client.ping().unwrap();
+    let server_ping = server.ping(0).unwrap().unwrap();
+    assert!(server.ping(1).is_err());
+
+    // Pings are answered on the next packets of the peer
+    server.process_local_client(0, &mut client).unwrap();
+    client.update(Duration::from_millis(20));
+    server.update(Duration::from_millis(20));
+    server.process_local_client(0, &mut client).unwrap();
+
+    assert_eq!(
+        client.receive_ping_result(),
+        Some(PingResult {
+            ping_id: client_ping,
+            rtt: Duration::from_millis(20)
+        })
+    );
+    assert_eq!(client.receive_ping_result(), None);
+
+    // The local client answered the server ping in the same exchange
+    assert_eq!(server.get_event(), Some(ServerEvent::ClientConnected { client_id: 0 }));
+    assert_eq!(
+        server.get_event(),
+        Some(ServerEvent::PingCompleted {
+            client_id: 0,
+            ping_id: server_ping,
+            rtt: Duration::ZERO
+        })
+    );
+    assert_eq!(server.get_event(), None);
+
+    // Unanswered pings are limited until they time out
+    let mut client = RenetClient::new(ConnectionConfig::test(), false);
+    for _ in 0..64 {
+        assert!(client.ping().is_some());
+    }
+    assert_eq!(client.ping(), None);
+    client.update(Duration::from_secs(10));
+    assert!(client.ping().is_some());
+}
@@ -115,6 +115,8 @@ fn server(public_addr: SocketAddr) {
                         );
                     }
                 }
+                ServerEvent::ClientRateLimited { .. } => {}
+                ServerEvent::PingCompleted { .. } => {}
             }
         }
 
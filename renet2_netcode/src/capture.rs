@@ -0,0 +1,512 @@
+//! Packet capture and replay.
+//!
+//! A [`PacketCapture`] can be attached to a [`NetcodeServerTransport`](crate::NetcodeServerTransport) or
+//! [`NetcodeClientTransport`](crate::NetcodeClientTransport) to record every datagram that passes through the transport.
+//! The capture can be read back with a [`PacketCaptureReader`], and fed into a [`RenetClient`] or [`RenetServer`]
+//! with a [`CaptureReplay`].
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use renet2::{ClientId, RenetClient, RenetServer};
+
+const CAPTURE_MAGIC: &[u8; 8] = b"RNT2PCAP";
+const CAPTURE_VERSION: u16 = 1;
+
+const FLAG_CLIENT_ID: u8 = 1 << 0;
+const FLAG_PAYLOAD: u8 = 1 << 1;
+
+/// Direction of a captured datagram, relative to the transport that captured it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// A single captured datagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// Netcode time of the transport when the datagram was captured.
+    pub timestamp: Duration,
+    pub direction: CaptureDirection,
+    /// Socket the datagram was sent or received on. Always `0` for clients.
+    pub socket_id: usize,
+    /// Address of the remote peer.
+    pub addr: SocketAddr,
+    /// Client the datagram belongs to, if known.
+    pub client_id: Option<ClientId>,
+    /// Raw datagram, as sent or received on the socket.
+    pub datagram: Vec<u8>,
+    /// Decrypted `renet2` payload, if the datagram is a netcode payload packet.
+    pub payload: Option<Vec<u8>>,
+}
+
+/// Writes captured datagrams to a sink.
+///
+/// # Format
+///
+/// Captures use a simple binary format. All integers are little-endian.
+///
+/// The file starts with a header:
+/// - 8 bytes: magic `RNT2PCAP`
+/// - `u16`: format version (currently `1`)
+///
+/// The header is followed by records until the end of the file:
+/// - `u64`: timestamp in nanoseconds, taken from the netcode clock of the transport
+/// - `u8`: direction, `0` for incoming and `1` for outgoing
+/// - `u8`: flags, bit `0` is set if a client id is present and bit `1` is set if a payload is present
+/// - `u16`: socket id
+/// - `u8`: address family, `4` or `6`, followed by 4 or 16 bytes of IP address and a `u16` port
+/// - `u64`: client id, if present
+/// - `u32` length followed by the raw datagram bytes, as they were sent or received on the socket
+/// - `u32` length followed by the decrypted `renet2` payload, if present
+///
+/// Payloads are only present for netcode payload packets, since those are the only packets that carry `renet2` data.
+pub struct PacketCapture {
+    writer: Box<dyn Write + Send + Sync>,
+    current_time: Duration,
+}
+
+impl fmt::Debug for PacketCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketCapture").field("current_time", &self.current_time).finish()
+    }
+}
+
+impl PacketCapture {
+    /// Makes a new capture that writes to `writer`. The capture header is written immediately.
+    pub fn new(mut writer: impl Write + Send + Sync + 'static) -> io::Result<Self> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_all(&CAPTURE_VERSION.to_le_bytes())?;
+
+        Ok(Self {
+            writer: Box::new(writer),
+            current_time: Duration::ZERO,
+        })
+    }
+
+    /// Makes a new capture that writes to a file, replacing it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Writes a record to the capture.
+    pub fn write_record(&mut self, record: &CaptureRecord) -> io::Result<()> {
+        let mut flags = 0;
+        if record.client_id.is_some() {
+            flags |= FLAG_CLIENT_ID;
+        }
+        if record.payload.is_some() {
+            flags |= FLAG_PAYLOAD;
+        }
+        let direction: u8 = match record.direction {
+            CaptureDirection::Incoming => 0,
+            CaptureDirection::Outgoing => 1,
+        };
+
+        let w = &mut self.writer;
+        w.write_all(&(record.timestamp.as_nanos() as u64).to_le_bytes())?;
+        w.write_all(&[direction, flags])?;
+        w.write_all(&(record.socket_id as u16).to_le_bytes())?;
+        match record.addr.ip() {
+            IpAddr::V4(ip) => {
+                w.write_all(&[4])?;
+                w.write_all(&ip.octets())?;
+            }
+            IpAddr::V6(ip) => {
+                w.write_all(&[6])?;
+                w.write_all(&ip.octets())?;
+            }
+        }
+        w.write_all(&record.addr.port().to_le_bytes())?;
+        if let Some(client_id) = record.client_id {
+            w.write_all(&client_id.to_le_bytes())?;
+        }
+        w.write_all(&(record.datagram.len() as u32).to_le_bytes())?;
+        w.write_all(&record.datagram)?;
+        if let Some(payload) = &record.payload {
+            w.write_all(&(payload.len() as u32).to_le_bytes())?;
+            w.write_all(payload)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes buffered records to the sink.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Sets the timestamp used for records captured by a transport.
+    pub(crate) fn set_current_time(&mut self, current_time: Duration) {
+        self.current_time = current_time;
+    }
+
+    /// Records a datagram on behalf of a transport. Errors are logged, since transports can't surface them.
+    pub(crate) fn record(
+        &mut self,
+        direction: CaptureDirection,
+        socket_id: usize,
+        addr: SocketAddr,
+        client_id: Option<ClientId>,
+        datagram: &[u8],
+        payload: Option<&[u8]>,
+    ) {
+        let record = CaptureRecord {
+            timestamp: self.current_time,
+            direction,
+            socket_id,
+            addr,
+            client_id,
+            datagram: datagram.to_vec(),
+            payload: payload.map(|p| p.to_vec()),
+        };
+        if let Err(e) = self.write_record(&record) {
+            log::error!("Failed to write packet capture record: {e}");
+        }
+    }
+}
+
+/// Reads records from a capture written by a [`PacketCapture`].
+#[derive(Debug)]
+pub struct PacketCaptureReader<R> {
+    reader: R,
+}
+
+impl PacketCaptureReader<BufReader<File>> {
+    /// Opens a capture file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PacketCaptureReader<R> {
+    /// Makes a new reader. Fails if the capture header is invalid.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a renet2 packet capture"));
+        }
+        let version = read_u16(&mut reader)?;
+        if version != CAPTURE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported packet capture version {version}"),
+            ));
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Reads the next record, or returns `None` at the end of the capture.
+    pub fn read_record(&mut self) -> io::Result<Option<CaptureRecord>> {
+        // The capture ends cleanly only between records
+        let mut timestamp = [0u8; 8];
+        let mut read = 0;
+        while read < timestamp.len() {
+            match self.reader.read(&mut timestamp[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let timestamp = Duration::from_nanos(u64::from_le_bytes(timestamp));
+
+        let r = &mut self.reader;
+        let direction = match read_u8(r)? {
+            0 => CaptureDirection::Incoming,
+            1 => CaptureDirection::Outgoing,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid capture direction")),
+        };
+        let flags = read_u8(r)?;
+        let socket_id = read_u16(r)? as usize;
+        let ip = match read_u8(r)? {
+            4 => {
+                let mut octets = [0u8; 4];
+                r.read_exact(&mut octets)?;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let mut octets = [0u8; 16];
+                r.read_exact(&mut octets)?;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid capture address family")),
+        };
+        let addr = SocketAddr::new(ip, read_u16(r)?);
+        let client_id = match flags & FLAG_CLIENT_ID != 0 {
+            true => Some(read_u64(r)?),
+            false => None,
+        };
+        let datagram = read_bytes(r)?;
+        let payload = match flags & FLAG_PAYLOAD != 0 {
+            true => Some(read_bytes(r)?),
+            false => None,
+        };
+
+        Ok(Some(CaptureRecord {
+            timestamp,
+            direction,
+            socket_id,
+            addr,
+            client_id,
+            datagram,
+            payload,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for PacketCaptureReader<R> {
+    type Item = io::Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Feeds a capture back into a [`RenetClient`] or [`RenetServer`].
+///
+/// Replays advance the connection's clock to each record's timestamp, and process the payloads of incoming records.
+/// Outgoing records are skipped, since the connection regenerates its own outgoing packets. Use the same
+/// [`ConnectionConfig`](renet2::ConnectionConfig) as the captured connection to reproduce its state.
+#[derive(Debug)]
+pub struct CaptureReplay {
+    records: Vec<CaptureRecord>,
+    next: usize,
+    current_time: Option<Duration>,
+}
+
+impl CaptureReplay {
+    /// Makes a new replay from captured records.
+    pub fn new(records: Vec<CaptureRecord>) -> Self {
+        Self {
+            records,
+            next: 0,
+            current_time: None,
+        }
+    }
+
+    /// Makes a new replay by reading all records from a capture.
+    pub fn from_reader<R: Read>(reader: PacketCaptureReader<R>) -> io::Result<Self> {
+        Ok(Self::new(reader.collect::<io::Result<Vec<_>>>()?))
+    }
+
+    /// Makes a new replay from a capture file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(PacketCaptureReader::open(path)?)
+    }
+
+    /// Returns the timestamp of the last replayed record, or `None` if nothing has been replayed yet.
+    pub fn current_time(&self) -> Option<Duration> {
+        self.current_time
+    }
+
+    /// Returns the timestamp of the next record to be replayed.
+    pub fn next_timestamp(&self) -> Option<Duration> {
+        self.records.get(self.next).map(|r| r.timestamp)
+    }
+
+    /// Returns `true` if all records have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.records.len()
+    }
+
+    /// Replays all records with a timestamp up to and including `until` into a client.
+    pub fn step_client(&mut self, until: Duration, client: &mut RenetClient) {
+        while let Some(record) = self.next_record(until) {
+            if let Some(duration) = record.elapsed {
+                client.update(duration);
+            }
+            let record = &self.records[record.index];
+            if let (CaptureDirection::Incoming, Some(payload)) = (record.direction, &record.payload) {
+                // Payloads are only captured once the netcode client is connected
+                if client.is_connecting() {
+                    client.set_connected();
+                }
+                client.process_packet(payload);
+            }
+        }
+    }
+
+    /// Replays all records with a timestamp up to and including `until` into a server.
+    ///
+    /// Clients are added to the server the first time one of their payloads is replayed.
+    pub fn step_server(&mut self, until: Duration, server: &mut RenetServer, socket_is_reliable: bool) {
+        while let Some(record) = self.next_record(until) {
+            if let Some(duration) = record.elapsed {
+                server.update(duration);
+            }
+            let record = &self.records[record.index];
+            let (CaptureDirection::Incoming, Some(client_id), Some(payload)) = (record.direction, record.client_id, &record.payload) else {
+                continue;
+            };
+            if !server.clients_id().contains(&client_id) {
+                server.add_connection(client_id, socket_is_reliable);
+            }
+            if let Err(e) = server.process_packet_from(payload, client_id) {
+                log::error!("Error while replaying payload for {client_id}: {e}");
+            }
+        }
+    }
+
+    /// Replays all remaining records into a client.
+    pub fn run_client(&mut self, client: &mut RenetClient) {
+        self.step_client(Duration::MAX, client);
+    }
+
+    /// Replays all remaining records into a server.
+    pub fn run_server(&mut self, server: &mut RenetServer, socket_is_reliable: bool) {
+        self.step_server(Duration::MAX, server, socket_is_reliable);
+    }
+
+    fn next_record(&mut self, until: Duration) -> Option<NextRecord> {
+        let record = self.records.get(self.next)?;
+        if record.timestamp > until {
+            return None;
+        }
+
+        let elapsed = match self.current_time {
+            Some(current_time) if record.timestamp > current_time => Some(record.timestamp - current_time),
+            _ => None,
+        };
+        self.current_time = Some(self.current_time.map_or(record.timestamp, |t| t.max(record.timestamp)));
+        let index = self.next;
+        self.next += 1;
+
+        Some(NextRecord { index, elapsed })
+    }
+}
+
+struct NextRecord {
+    index: usize,
+    elapsed: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_roundtrip() {
+        let records = vec![
+            CaptureRecord {
+                timestamp: Duration::from_millis(10),
+                direction: CaptureDirection::Incoming,
+                socket_id: 1,
+                addr: "127.0.0.1:5000".parse().unwrap(),
+                client_id: Some(7),
+                datagram: vec![1, 2, 3],
+                payload: Some(vec![4, 5]),
+            },
+            CaptureRecord {
+                timestamp: Duration::from_millis(20),
+                direction: CaptureDirection::Outgoing,
+                socket_id: 0,
+                addr: "[::1]:6000".parse().unwrap(),
+                client_id: None,
+                datagram: vec![9; 100],
+                payload: None,
+            },
+        ];
+
+        let shared = SharedBuffer::default();
+        let mut capture = PacketCapture::new(shared.clone()).unwrap();
+        for record in records.iter() {
+            capture.write_record(record).unwrap();
+        }
+        let buffer = shared.0.lock().unwrap().clone();
+        assert_eq!(&buffer[..8], CAPTURE_MAGIC);
+
+        let reader = PacketCaptureReader::new(buffer.as_slice()).unwrap();
+        let read: Vec<CaptureRecord> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(PacketCaptureReader::new(&b"NOTACAPTURE"[..]).is_err());
+    }
+
+    #[test]
+    fn truncated_record() {
+        let shared = SharedBuffer::default();
+        let mut capture = PacketCapture::new(shared.clone()).unwrap();
+        capture
+            .write_record(&CaptureRecord {
+                timestamp: Duration::ZERO,
+                direction: CaptureDirection::Incoming,
+                socket_id: 0,
+                addr: "127.0.0.1:5000".parse().unwrap(),
+                client_id: None,
+                datagram: vec![1, 2, 3],
+                payload: None,
+            })
+            .unwrap();
+        let mut buffer = shared.0.lock().unwrap().clone();
+        buffer.pop();
+
+        let mut reader = PacketCaptureReader::new(buffer.as_slice()).unwrap();
+        assert!(reader.read_record().is_err());
+
+        // A record cut inside its timestamp is not a clean end of the capture
+        let mut buffer = shared.0.lock().unwrap().clone();
+        buffer.extend_from_slice(&[0; 3]);
+        let mut reader = PacketCaptureReader::new(buffer.as_slice()).unwrap();
+        assert!(reader.read_record().unwrap().is_some());
+        assert_eq!(reader.read_record().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
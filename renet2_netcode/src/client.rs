@@ -1,10 +1,10 @@
 use std::{io, net::SocketAddr, time::Duration};
 
-use renetcode2::{ClientAuthentication, DisconnectReason, NetcodeClient, NetcodeError, NETCODE_MAX_PACKET_BYTES};
+use renetcode2::{ClientAuthentication, Clock, DisconnectReason, NetcodeClient, NetcodeError, NETCODE_MAX_PACKET_BYTES};
 
 use renet2::{ClientId, RenetClient};
 
-use super::{ClientSocket, NetcodeTransportError};
+use super::{CaptureDirection, ClientSocket, NetcodeTransportError, PacketCapture};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::resource::Resource))]
@@ -12,6 +12,7 @@ pub struct NetcodeClientTransport {
     socket: Box<dyn ClientSocket>,
     netcode_client: NetcodeClient,
     buffer: [u8; NETCODE_MAX_PACKET_BYTES],
+    capture: Option<PacketCapture>,
 }
 
 impl NetcodeClientTransport {
@@ -23,6 +24,7 @@ impl NetcodeClientTransport {
             socket: Box::new(socket),
             netcode_client,
             buffer: [0u8; NETCODE_MAX_PACKET_BYTES],
+            capture: None,
         })
     }
 
@@ -58,6 +60,21 @@ impl NetcodeClientTransport {
         self.netcode_client.is_disconnected()
     }
 
+    /// Sets extended user data to send to the server after connecting, see [`NetcodeClient::set_extended_user_data`].
+    pub fn set_extended_user_data(&mut self, sealed: Vec<u8>) -> Result<(), NetcodeError> {
+        self.netcode_client.set_extended_user_data(sealed)
+    }
+
+    /// Returns `true` if the server acknowledged the extended user data.
+    pub fn is_extended_user_data_acked(&self) -> bool {
+        self.netcode_client.is_extended_user_data_acked()
+    }
+
+    /// Sets the clock that the connect token expiry is checked with, see [`NetcodeClient::set_clock`].
+    pub fn set_clock(&mut self, clock: impl Clock) {
+        self.netcode_client.set_clock(clock);
+    }
+
     /// Returns the duration since the client last received a packet.
     ///
     /// Useful to detect timeouts.
@@ -65,6 +82,27 @@ impl NetcodeClientTransport {
         self.netcode_client.time_since_last_received_packet()
     }
 
+    /// Starts recording all datagrams sent and received by the transport, replacing any previous capture.
+    pub fn start_capture(&mut self, capture: PacketCapture) -> Option<PacketCapture> {
+        let previous = self.stop_capture();
+        self.capture = Some(capture);
+        previous
+    }
+
+    /// Stops recording datagrams, returning the flushed capture if there was one.
+    pub fn stop_capture(&mut self) -> Option<PacketCapture> {
+        let mut capture = self.capture.take()?;
+        if let Err(e) = capture.flush() {
+            log::error!("Failed to flush packet capture: {e}");
+        }
+        Some(capture)
+    }
+
+    /// Returns `true` if the transport is recording datagrams.
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
     /// Disconnects the client from the transport layer.
     ///
     /// This sends the disconnect packet instantly, use this when closing/exiting games,
@@ -74,8 +112,15 @@ impl NetcodeClientTransport {
             return;
         }
 
+        if let Some(capture) = self.capture.as_mut() {
+            capture.set_current_time(self.netcode_client.current_time());
+        }
+
         match self.netcode_client.disconnect() {
             Ok((addr, packet)) => {
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.record(CaptureDirection::Outgoing, 0, addr, None, packet, None);
+                }
                 if let Err(e) = self.socket.send(addr, packet) {
                     log::error!("Failed to send disconnect packet: {e}");
                 }
@@ -97,9 +142,16 @@ impl NetcodeClientTransport {
             return Err(NetcodeError::Disconnected(reason).into());
         }
 
+        if let Some(capture) = self.capture.as_mut() {
+            capture.set_current_time(self.netcode_client.current_time());
+        }
+
         let packets = connection.get_packets_to_send();
         for packet in packets {
             let (addr, payload) = self.netcode_client.generate_payload_packet(&packet)?;
+            if let Some(capture) = self.capture.as_mut() {
+                capture.record(CaptureDirection::Outgoing, 0, addr, None, payload, Some(&packet));
+            }
             self.socket.send(addr, payload)?;
         }
 
@@ -120,9 +172,16 @@ impl NetcodeClientTransport {
             client.disconnect_due_to_transport();
         }
 
+        if let Some(capture) = self.capture.as_mut() {
+            capture.set_current_time(self.netcode_client.current_time());
+        }
+
         if let Some(error) = client.disconnect_reason() {
             let (addr, disconnect_packet) = self.netcode_client.disconnect()?;
             if !self.socket.is_closed() {
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.record(CaptureDirection::Outgoing, 0, addr, None, disconnect_packet, None);
+                }
                 self.socket.send(addr, disconnect_packet)?;
                 self.socket.close();
             }
@@ -138,26 +197,38 @@ impl NetcodeClientTransport {
         self.socket.preupdate();
 
         loop {
-            let packet = match self.socket.try_recv(&mut self.buffer) {
+            let (packet, addr) = match self.socket.try_recv(&mut self.buffer) {
                 Ok((len, addr)) => {
                     if addr != self.netcode_client.server_addr() {
                         log::debug!("Discarded packet from unknown server {:?}", addr);
                         continue;
                     }
 
-                    &mut self.buffer[..len]
+                    (&mut self.buffer[..len], addr)
                 }
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
                 Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
                 Err(e) => return Err(NetcodeTransportError::IO(e)),
             };
 
-            if let Some(payload) = self.netcode_client.process_packet(packet) {
+            // Copy the datagram before it gets decrypted in place.
+            let datagram = self.capture.as_ref().map(|_| packet.to_vec());
+            let payload = self.netcode_client.process_packet(packet);
+            if let (Some(capture), Some(datagram)) = (self.capture.as_mut(), datagram) {
+                let client_id = payload.map(|_| self.netcode_client.client_id());
+                capture.record(CaptureDirection::Incoming, 0, addr, client_id, &datagram, payload);
+            }
+            if let Some(payload) = payload {
                 client.process_packet(payload);
             }
         }
 
+        let update_time = self.netcode_client.current_time() + duration;
         if let Some((packet, addr)) = self.netcode_client.update(duration) {
+            if let Some(capture) = self.capture.as_mut() {
+                capture.set_current_time(update_time);
+                capture.record(CaptureDirection::Outgoing, 0, addr, None, packet, None);
+            }
             self.socket.send(addr, packet)?;
         }
 
@@ -4,32 +4,59 @@
 
 use std::{error::Error, fmt};
 
+/// Logs through `tracing` when that feature is enabled, so the event is recorded in the current span, or through `log` otherwise.
+#[cfg(all(any(feature = "ws_server_transport", feature = "wt_server_transport"), not(target_family = "wasm")))]
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::$level!($($arg)+);
+    }};
+}
+
+mod capture;
 mod client;
 #[cfg(feature = "memory_transport")]
 mod memory_socket;
+#[cfg(feature = "metrics")]
+mod metrics_endpoint;
 #[cfg(all(feature = "native_transport", not(target_family = "wasm")))]
 mod native_socket;
 mod server;
 mod sockets;
+#[cfg(feature = "test_harness")]
+mod test_harness;
 mod websocket_socket;
 mod webtransport_socket;
 
+pub use capture::*;
 pub use client::*;
 pub use server::*;
 pub use sockets::*;
 
 #[cfg(feature = "memory_transport")]
 pub use memory_socket::*;
+#[cfg(feature = "metrics")]
+pub use metrics_endpoint::*;
 #[cfg(all(feature = "native_transport", not(target_family = "wasm")))]
 pub use native_socket::*;
+#[cfg(feature = "test_harness")]
+pub use test_harness::*;
 #[allow(unused_imports)]
 pub use websocket_socket::*;
 pub use webtransport_socket::*;
 
+#[cfg(feature = "serde")]
+pub use renetcode2::{decode_user_data, encode_user_data, UserDataError, NETCODE_MAX_ENCODED_USER_DATA_BYTES};
 pub use renetcode2::{
-    generate_random_bytes, ClientAuthentication, ConnectToken, DisconnectReason as NetcodeDisconnectReason, NetcodeError,
-    ServerAuthentication, ServerConfig, ServerSocketConfig, TokenGenerationError, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES,
+    generate_random_bytes, ClientAuthentication, Clock, ConnectToken, ConnectionDeniedReason, ConnectionRequestLimit,
+    DisconnectReason as NetcodeDisconnectReason, IpRange, IssuedToken, ManualClock, NetcodeError, NetcodeVersion, ServerAuthentication,
+    ServerConfig, ServerConfigError, ServerKey, ServerSocketConfig, TokenGenerationError, TokenIssuer, TokenIssuerConfig,
+    NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_MAX_EXTENDED_USER_DATA_BYTES, NETCODE_USER_DATA_BYTES,
 };
+#[cfg(not(target_family = "wasm"))]
+pub use renetcode2::{MonotonicClock, SystemClock};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
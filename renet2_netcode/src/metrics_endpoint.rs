@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use metrics::{Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SetRecorderError, SharedString, Unit};
+
+#[derive(Debug, Default)]
+struct HistogramSummary {
+    /// Number of samples and the sum of all samples.
+    state: Mutex<(u64, f64)>,
+}
+
+impl HistogramFn for HistogramSummary {
+    fn record(&self, value: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        state.1 += value;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+    gauges: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+    histograms: Mutex<HashMap<Key, Arc<HistogramSummary>>>,
+    descriptions: Mutex<HashMap<String, SharedString>>,
+}
+
+/// Minimal [`Recorder`] that stores metrics in memory so they can be scraped in the Prometheus text format.
+///
+/// This is intended for servers that don't already install a recorder. Histograms are exported as summaries
+/// with only a `_sum` and `_count`.
+///
+/// Series with a `client_id` label are removed once nothing holds their handles anymore,
+/// so the series of a connection are removed when the server removes the connection.
+///
+/// # Usage
+/// ```no_run
+/// # use renet2_netcode::MetricsRecorder;
+/// let handle = MetricsRecorder::new().install().unwrap();
+/// handle.serve("0.0.0.0:9000".parse().unwrap()).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+    registry: Arc<Registry>,
+}
+
+impl MetricsRecorder {
+    /// Makes a new recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a handle for rendering the recorded metrics.
+    pub fn handle(&self) -> MetricsHandle {
+        MetricsHandle {
+            registry: self.registry.clone(),
+        }
+    }
+
+    /// Installs this recorder as the global recorder of the `metrics` crate.
+    pub fn install(self) -> Result<MetricsHandle, SetRecorderError<Self>> {
+        let handle = self.handle();
+        metrics::set_global_recorder(self)?;
+        Ok(handle)
+    }
+
+    fn describe(&self, key: KeyName, description: SharedString) {
+        self.registry
+            .descriptions
+            .lock()
+            .unwrap()
+            .insert(key.as_str().to_owned(), description);
+    }
+}
+
+impl Recorder for MetricsRecorder {
+    fn describe_counter(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, _unit: Option<Unit>, description: SharedString) {
+        self.describe(key, description);
+    }
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        let mut counters = self.registry.counters.lock().unwrap();
+        Counter::from_arc(counters.entry(key.clone()).or_default().clone())
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        let mut gauges = self.registry.gauges.lock().unwrap();
+        Gauge::from_arc(gauges.entry(key.clone()).or_default().clone())
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        let mut histograms = self.registry.histograms.lock().unwrap();
+        Histogram::from_arc(histograms.entry(key.clone()).or_default().clone())
+    }
+}
+
+/// Handle for rendering metrics stored by a [`MetricsRecorder`].
+#[derive(Debug, Clone)]
+pub struct MetricsHandle {
+    registry: Arc<Registry>,
+}
+
+impl MetricsHandle {
+    /// Renders all recorded metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let descriptions = self.registry.descriptions.lock().unwrap();
+
+        // Group samples by metric name so each metric gets a single TYPE line.
+        let mut metrics: Vec<(String, &'static str, Vec<String>)> = Vec::new();
+        let mut push = |name: &str, kind: &'static str, sample: String| match metrics.iter_mut().find(|(n, _, _)| n == name) {
+            Some((_, _, samples)) => samples.push(sample),
+            None => metrics.push((name.to_owned(), kind, vec![sample])),
+        };
+
+        let mut counters = self.registry.counters.lock().unwrap();
+        counters.retain(is_alive);
+        for (key, value) in counters.iter() {
+            let value = value.load(Ordering::Relaxed);
+            push(key.name(), "counter", format!("{}{} {}", key.name(), render_labels(key), value));
+        }
+        let mut gauges = self.registry.gauges.lock().unwrap();
+        gauges.retain(is_alive);
+        for (key, value) in gauges.iter() {
+            let value = f64::from_bits(value.load(Ordering::Relaxed));
+            push(key.name(), "gauge", format!("{}{} {}", key.name(), render_labels(key), value));
+        }
+        for (key, summary) in self.registry.histograms.lock().unwrap().iter() {
+            let (count, sum) = *summary.state.lock().unwrap();
+            let labels = render_labels(key);
+            push(key.name(), "summary", format!("{}_sum{} {}", key.name(), labels, sum));
+            push(key.name(), "summary", format!("{}_count{} {}", key.name(), labels, count));
+        }
+
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut out = String::new();
+        for (name, kind, mut samples) in metrics {
+            if let Some(description) = descriptions.get(&name) {
+                let _ = writeln!(out, "# HELP {} {}", name, description.replace('\\', "\\\\").replace('\n', "\\n"));
+            }
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            samples.sort();
+            for sample in samples {
+                out.push_str(&sample);
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    /// Serves the rendered metrics over HTTP on a background thread.
+    ///
+    /// Every request to the address is answered with the output of [`Self::render`], regardless of the path.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn serve(&self, addr: std::net::SocketAddr) -> std::io::Result<std::thread::JoinHandle<()>> {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind(addr)?;
+        let handle = self.clone();
+        Ok(std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::debug!("Failed to accept metrics connection: {e}");
+                        continue;
+                    }
+                };
+
+                // The request is ignored, but it needs to be read so the client doesn't see a connection reset.
+                let mut request = [0u8; 1024];
+                let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(1)));
+                let _ = stream.read(&mut request);
+
+                let body = handle.render();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                if let Err(e) = stream.write_all(response.as_bytes()) {
+                    log::debug!("Failed to write metrics response: {e}");
+                }
+            }
+        }))
+    }
+}
+
+// Series of a connection are removed when only the registry holds them
+fn is_alive(key: &Key, value: &mut Arc<AtomicU64>) -> bool {
+    Arc::strong_count(value) > 1 || !key.labels().any(|label| label.key() == "client_id")
+}
+
+fn render_labels(key: &Key) -> String {
+    let mut labels = key.labels().peekable();
+    if labels.peek().is_none() {
+        return String::new();
+    }
+
+    let mut out = String::from("{");
+    for (i, label) in labels.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let value = label.value().replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        let _ = write!(out, "{}=\"{}\"", label.key(), value);
+    }
+    out.push('}');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_text_format() {
+        let recorder = MetricsRecorder::new();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::describe_counter!("test_total", "A test counter.");
+            metrics::counter!("test_total", "socket_id" => "0").increment(2);
+            metrics::counter!("test_total", "socket_id" => "1").increment(1);
+            metrics::gauge!("test_gauge").set(1.5);
+            metrics::histogram!("test_histogram").record(2.0);
+            metrics::histogram!("test_histogram").record(3.0);
+        });
+
+        let expected = "\
+# TYPE test_gauge gauge
+test_gauge 1.5
+# TYPE test_histogram summary
+test_histogram_count 2
+test_histogram_sum 5
+# HELP test_total A test counter.
+# TYPE test_total counter
+test_total{socket_id=\"0\"} 2
+test_total{socket_id=\"1\"} 1
+";
+        assert_eq!(handle.render(), expected);
+    }
+
+    #[test]
+    fn server_connection_metrics() {
+        let recorder = MetricsRecorder::new();
+        let handle = recorder.handle();
+
+        // The recorder can be installed after making the server
+        let mut server = renet2::RenetServer::new(renet2::ConnectionConfig::test());
+        metrics::with_local_recorder(&recorder, || {
+            let mut client = server.new_local_client(7);
+            server.send_message(7, 0, vec![0; 10]);
+            server.update(std::time::Duration::ZERO);
+            let rendered = handle.render();
+            assert!(rendered.contains("renet2_server_clients 1\n"));
+            assert!(!rendered.contains("renet2_client_channel_memory{client_id=\"7\",channel_id=\"0\"} 0\n"));
+
+            server.disconnect_local_client(7, &mut client);
+            server.update(std::time::Duration::ZERO);
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains("# HELP renet2_server_clients"));
+        assert!(rendered.contains("renet2_server_clients 0\n"));
+        assert!(!rendered.contains("client_id=\"7\""));
+    }
+}
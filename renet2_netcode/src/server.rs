@@ -1,11 +1,18 @@
-use std::{io, net::SocketAddr, time::Duration};
-
-use renetcode2::{NetcodeServer, ServerConfig, ServerResult, NETCODE_MAX_PACKET_BYTES, NETCODE_USER_DATA_BYTES};
-use renetcode2::{ServerAuthentication, ServerSocketConfig};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use renetcode2::{
+    Clock, ConnectionRequestLimit, IpRange, NetcodeServer, ServerConfig, ServerResult, NETCODE_MAC_BYTES, NETCODE_MAX_PACKET_BYTES,
+    NETCODE_USER_DATA_BYTES,
+};
+use renetcode2::{ServerAuthentication, ServerKey, ServerSocketConfig};
 
 use renet2::{ClientId, Payload, RenetServer};
 
-use super::{NetcodeTransportError, ServerSocket};
+use super::{CaptureDirection, NetcodeTransportError, PacketCapture, ServerSocket};
 
 /// Config for setting up a [`NetcodeServerTransport`].
 ///
@@ -42,6 +49,7 @@ pub struct NetcodeServerTransport {
     sockets: Vec<Box<dyn ServerSocket>>,
     netcode_server: NetcodeServer,
     buffer: [u8; NETCODE_MAX_PACKET_BYTES],
+    capture: Option<PacketCapture>,
 }
 
 impl NetcodeServerTransport {
@@ -74,13 +82,14 @@ impl NetcodeServerTransport {
         for (addrs, socket) in socket_addresses.drain(..).zip(sockets.iter()) {
             socket_configs.push(ServerSocketConfig {
                 needs_encryption: !socket.is_encrypted(),
-                public_addresses: addrs,
+                ..ServerSocketConfig::new(addrs)
             });
         }
 
         let server_config = ServerConfig {
             current_time: server_config.current_time,
             max_clients: server_config.max_clients,
+            max_pending_clients: (server_config.max_clients * 4).max(4096),
             protocol_id: server_config.protocol_id,
             sockets: socket_configs,
             authentication: server_config.authentication,
@@ -90,6 +99,7 @@ impl NetcodeServerTransport {
             sockets,
             netcode_server: NetcodeServer::new(server_config),
             buffer: [0; NETCODE_MAX_PACKET_BYTES],
+            capture: None,
         })
     }
 
@@ -120,32 +130,162 @@ impl NetcodeServerTransport {
         self.netcode_server.set_max_clients(max_clients);
     }
 
+    /// Returns the maximum number of clients that can be in the middle of connecting.
+    ///
+    /// Defaults to four times the `max_clients` the transport was created with, and at least 4096.
+    pub fn max_pending_clients(&self) -> usize {
+        self.netcode_server.max_pending_clients()
+    }
+
+    /// Update the maximum numbers of clients that can be in the middle of connecting.
+    pub fn set_max_pending_clients(&mut self, max_pending_clients: usize) {
+        self.netcode_server.set_max_pending_clients(max_pending_clients);
+    }
+
     /// Returns current number of clients connected.
     pub fn connected_clients(&self) -> usize {
         self.netcode_server.connected_clients()
     }
 
+    /// Returns current number of clients that are in the middle of connecting.
+    pub fn pending_clients(&self) -> usize {
+        self.netcode_server.pending_clients()
+    }
+
     /// Returns the user data for client if connected.
     pub fn user_data(&self, client_id: ClientId) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
         self.netcode_server.user_data(client_id)
     }
 
+    /// Returns the extended user data for client once received, see [`NetcodeServer::extended_user_data`].
+    pub fn extended_user_data(&self, client_id: ClientId) -> Option<&[u8]> {
+        self.netcode_server.extended_user_data(client_id)
+    }
+
+    /// Returns the user data for client if connected, decoded with [`decode_user_data`](renetcode2::decode_user_data).
+    ///
+    /// Call it when receiving [`ServerEvent::ClientConnected`](renet2::ServerEvent::ClientConnected) to get the
+    /// typed data encoded in the connect token with [`encode_user_data`](renetcode2::encode_user_data).
+    #[cfg(feature = "serde")]
+    pub fn decode_user_data<T: serde::de::DeserializeOwned>(&self, client_id: ClientId) -> Option<Result<T, renetcode2::UserDataError>> {
+        self.netcode_server
+            .user_data(client_id)
+            .map(|user_data| renetcode2::decode_user_data(&user_data))
+    }
+
     /// Returns the client socket id and address if connected.
     pub fn client_addr(&self, client_id: ClientId) -> Option<(usize, SocketAddr)> {
         self.netcode_server.client_addr(client_id)
     }
 
+    /// Denies the connection requests of a client until the server time `until`.
+    ///
+    /// Does not disconnect the client if it is already connected, see [`RenetServer::disconnect`].
+    pub fn ban_client(&mut self, client_id: ClientId, until: Duration) {
+        self.netcode_server.ban_client(client_id, until);
+    }
+
+    /// Lifts the ban of a client, returns `false` if it wasn't banned.
+    pub fn unban_client(&mut self, client_id: ClientId) -> bool {
+        self.netcode_server.unban_client(client_id)
+    }
+
+    /// Returns whether the connection requests of a client are denied.
+    pub fn is_client_banned(&self, client_id: ClientId) -> bool {
+        self.netcode_server.is_client_banned(client_id)
+    }
+
+    /// Denies the connection requests from a range of addresses until the server time `until`.
+    ///
+    /// Does not disconnect the clients already connected from these addresses.
+    pub fn ban_address(&mut self, range: impl Into<IpRange>, until: Duration) {
+        self.netcode_server.ban_address(range, until);
+    }
+
+    /// Lifts the ban of a range of addresses, returns `false` if it wasn't banned.
+    pub fn unban_address(&mut self, range: impl Into<IpRange>) -> bool {
+        self.netcode_server.unban_address(range)
+    }
+
+    /// Returns whether the connection requests from an address are denied.
+    pub fn is_address_banned(&self, addr: IpAddr) -> bool {
+        self.netcode_server.is_address_banned(addr)
+    }
+
+    /// Denies the connection requests that use a connect token until the server time `until`.
+    ///
+    /// The token is identified by its MAC, see [`ConnectToken::mac`](renetcode2::ConnectToken::mac).
+    pub fn revoke_token(&mut self, mac: [u8; NETCODE_MAC_BYTES], until: Duration) {
+        self.netcode_server.revoke_token(mac, until);
+    }
+
+    /// Adds a private key accepted when decrypting connect tokens, replacing the key with the same id.
+    ///
+    /// Useful to rotate keys without invalidating the connect tokens already issued.
+    pub fn add_private_key(&mut self, key: ServerKey) {
+        self.netcode_server.add_private_key(key);
+    }
+
+    /// Removes a private key, returns `false` if there was no key with the id.
+    pub fn remove_private_key(&mut self, id: u64) -> bool {
+        self.netcode_server.remove_private_key(id)
+    }
+
+    /// Returns the ids of the private keys, from the least to the most recently added.
+    pub fn private_key_ids(&self) -> Vec<u64> {
+        self.netcode_server.private_key_ids()
+    }
+
+    /// Sets the limit of connection request and response packets per source, or removes it with `None`.
+    ///
+    /// Packets above the limit are dropped before decryption, no limit is set by default.
+    pub fn set_connection_request_limit(&mut self, limit: Option<ConnectionRequestLimit>) {
+        self.netcode_server.set_connection_request_limit(limit);
+    }
+
+    /// Returns the number of connection request and response packets dropped by the connection request limit.
+    pub fn rate_limited_requests(&self) -> u64 {
+        self.netcode_server.rate_limited_requests()
+    }
+
+    /// Starts recording all datagrams sent and received by the transport, replacing any previous capture.
+    pub fn start_capture(&mut self, capture: PacketCapture) -> Option<PacketCapture> {
+        let previous = self.stop_capture();
+        self.capture = Some(capture);
+        previous
+    }
+
+    /// Stops recording datagrams, returning the flushed capture if there was one.
+    pub fn stop_capture(&mut self) -> Option<PacketCapture> {
+        let mut capture = self.capture.take()?;
+        if let Err(e) = capture.flush() {
+            log::error!("Failed to flush packet capture: {e}");
+        }
+        Some(capture)
+    }
+
+    /// Returns `true` if the transport is recording datagrams.
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
     /// Disconnects all connected clients.
     ///
     /// This sends the disconnect packet instantly, use this when closing/exiting games,
     /// should use [RenetServer::disconnect_all] otherwise.
     pub fn disconnect_all(&mut self, server: &mut RenetServer) {
+        self.sync_capture_time();
         for client_id in self.netcode_server.clients_id() {
             let server_result = self.netcode_server.disconnect(client_id);
-            handle_server_result(server_result, &mut self.sockets, server);
+            handle_server_result(server_result, &mut self.sockets, server, &mut self.capture);
         }
     }
 
+    /// Sets the clock that the server reads the token time from, see [`NetcodeServer::set_clock`].
+    pub fn set_clock(&mut self, clock: impl Clock) {
+        self.netcode_server.set_clock(clock);
+    }
+
     /// Returns the duration since the connected client last received a packet.
     ///
     /// Useful to detect users that are timing out.
@@ -156,6 +296,7 @@ impl NetcodeServerTransport {
     /// Advances the transport by the duration, and receive packets from the network.
     pub fn update(&mut self, duration: Duration, server: &mut RenetServer) -> Result<(), Vec<NetcodeTransportError>> {
         self.netcode_server.update(duration);
+        self.sync_capture_time();
 
         let mut transport_errors = Vec::default();
         for socket_id in 0..self.sockets.len() {
@@ -164,13 +305,26 @@ impl NetcodeServerTransport {
             loop {
                 match self.sockets[socket_id].try_recv(&mut self.buffer) {
                     Ok((len, addr)) => {
+                        #[cfg(feature = "tracing")]
+                        let _span = self.netcode_server.connection_span(socket_id, addr).map(|span| span.entered());
+                        // Copy the datagram before it gets decrypted in place.
+                        let datagram = self.capture.as_ref().map(|_| self.buffer[..len].to_vec());
                         let server_result = self.netcode_server.process_packet(socket_id, addr, &mut self.buffer[..len]);
-                        handle_server_result(server_result, &mut self.sockets, server);
+                        if let (Some(capture), Some(datagram)) = (self.capture.as_mut(), datagram) {
+                            let (client_id, payload) = match server_result {
+                                ServerResult::Payload { client_id, payload } => (Some(client_id), Some(payload)),
+                                _ => (None, None),
+                            };
+                            capture.record(CaptureDirection::Incoming, socket_id, addr, client_id, &datagram, payload);
+                        }
+                        handle_server_result(server_result, &mut self.sockets, server, &mut self.capture);
                     }
                     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
                     Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
                     Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
                     Err(e) => {
+                        #[cfg(feature = "metrics")]
+                        metrics::counter!("renet2_netcode_socket_errors_total", "socket_id" => socket_id.to_string()).increment(1);
                         transport_errors.push(e.into());
                     }
                 };
@@ -178,19 +332,29 @@ impl NetcodeServerTransport {
         }
 
         for client_id in self.netcode_server.clients_id() {
+            #[cfg(feature = "tracing")]
+            let _span = self.netcode_server.client_span(client_id).map(|span| span.entered());
             let server_result = self.netcode_server.update_client(client_id);
-            handle_server_result(server_result, &mut self.sockets, server);
+            handle_server_result(server_result, &mut self.sockets, server, &mut self.capture);
         }
 
         for disconnection_id in server.disconnections_id() {
+            #[cfg(feature = "tracing")]
+            let _span = self.netcode_server.client_span(disconnection_id).map(|span| span.entered());
             let server_result = self.netcode_server.disconnect(disconnection_id);
-            handle_server_result(server_result, &mut self.sockets, server);
+            handle_server_result(server_result, &mut self.sockets, server, &mut self.capture);
         }
 
         for socket in self.sockets.iter_mut() {
             socket.postupdate();
         }
 
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("renet2_netcode_connected_clients").set(self.connected_clients() as f64);
+            metrics::gauge!("renet2_netcode_pending_clients").set(self.pending_clients() as f64);
+        }
+
         if !transport_errors.is_empty() {
             return Err(transport_errors);
         }
@@ -200,17 +364,33 @@ impl NetcodeServerTransport {
 
     /// Sends packets to connected clients.
     pub fn send_packets(&mut self, server: &mut RenetServer) {
+        self.sync_capture_time();
         //TODO: it isn't necessary to allocate client ids here, just use one big vec of packets for all clients
         // - also, the vec can be cached in RenetServer for reuse, and likewise with the internal pieces of packets
         for client_id in server.clients_id() {
+            #[cfg(feature = "tracing")]
+            let _span = self.netcode_server.client_span(client_id).map(|span| span.entered());
             let packets = server.get_packets_to_send(client_id).unwrap();
             for packet in packets {
-                if !send_packet_to_client(&mut self.sockets, &mut self.netcode_server, server, &packet, client_id) {
+                if !send_packet_to_client(
+                    &mut self.sockets,
+                    &mut self.netcode_server,
+                    server,
+                    &mut self.capture,
+                    &packet,
+                    client_id,
+                ) {
                     break;
                 }
             }
         }
     }
+
+    fn sync_capture_time(&mut self) {
+        if let Some(capture) = self.capture.as_mut() {
+            capture.set_current_time(self.netcode_server.current_time());
+        }
+    }
 }
 
 /// Sends a packet to a client.
@@ -220,11 +400,17 @@ fn send_packet_to_client(
     sockets: &mut [Box<dyn ServerSocket>],
     netcode_server: &mut NetcodeServer,
     reliable_server: &mut RenetServer,
+    capture: &mut Option<PacketCapture>,
     packet: &Payload,
     client_id: ClientId,
 ) -> bool {
     let (send_result, socket_id, addr) = match netcode_server.generate_payload_packet(client_id, packet) {
-        Ok((socket_id, addr, payload)) => (sockets[socket_id].send(addr, payload), socket_id, addr),
+        Ok((socket_id, addr, payload)) => {
+            if let Some(capture) = capture.as_mut() {
+                capture.record(CaptureDirection::Outgoing, socket_id, addr, Some(client_id), payload, Some(packet));
+            }
+            (sockets[socket_id].send(addr, payload), socket_id, addr)
+        }
         Err(e) => {
             log::error!("Failed to encrypt payload packet for client {client_id}: {e}");
             return false;
@@ -248,8 +434,16 @@ fn send_packet_to_client(
     }
 }
 
-fn handle_server_result(server_result: ServerResult, sockets: &mut [Box<dyn ServerSocket>], reliable_server: &mut RenetServer) {
-    let send_packet = |sockets: &mut [Box<dyn ServerSocket>], packet: &[u8], socket_id: usize, addr: SocketAddr| {
+fn handle_server_result(
+    server_result: ServerResult,
+    sockets: &mut [Box<dyn ServerSocket>],
+    reliable_server: &mut RenetServer,
+    capture: &mut Option<PacketCapture>,
+) {
+    let mut send_packet = |sockets: &mut [Box<dyn ServerSocket>], packet: &[u8], socket_id: usize, addr: SocketAddr| {
+        if let Some(capture) = capture.as_mut() {
+            capture.record(CaptureDirection::Outgoing, socket_id, addr, None, packet, None);
+        }
         if let Err(err) = sockets[socket_id].send(addr, packet) {
             log::trace!("Failed to send packet to {socket_id}/{addr}: {err}");
         }
@@ -0,0 +1,441 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use renet2::{Bytes, ClientId, ConnectionConfig, RenetClient, RenetServer, ServerEvent};
+use renetcode2::{ClientAuthentication, ServerAuthentication};
+
+use crate::{
+    in_memory_server_addr, new_memory_sockets, ClientSocket, MemorySocketClient, MemorySocketServer, NetcodeClientTransport,
+    NetcodeServerTransport, NetcodeTransportError, ServerSetupConfig, ServerSocket,
+};
+
+/// Shared handle for controlling the packet loss of a [`LossySocket`].
+///
+/// The loss is a probability in the range `[0.0, 1.0]` that a sent packet is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct PacketLoss(Arc<AtomicU64>);
+
+impl PacketLoss {
+    /// Makes a new packet loss handle.
+    pub fn new(loss: f64) -> Self {
+        let handle = Self::default();
+        handle.set(loss);
+        handle
+    }
+
+    /// Sets the packet loss. The value is clamped to `[0.0, 1.0]`.
+    pub fn set(&self, loss: f64) {
+        self.0.store(loss.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Gets the packet loss.
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Socket wrapper that drops a fraction of sent packets.
+///
+/// Packets are dropped using a seeded pseudo-random sequence, so the same seed always drops the same packets.
+#[derive(Debug)]
+pub struct LossySocket<S> {
+    inner: S,
+    loss: PacketLoss,
+    rng_state: u64,
+}
+
+impl<S> LossySocket<S> {
+    /// Wraps a socket.
+    pub fn new(inner: S, loss: PacketLoss, seed: u64) -> Self {
+        Self {
+            inner,
+            loss,
+            rng_state: seed,
+        }
+    }
+
+    fn should_drop(&mut self) -> bool {
+        let loss = self.loss.get();
+        if loss <= 0.0 {
+            return false;
+        }
+
+        // splitmix64
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        let sample = (z >> 11) as f64 / (1u64 << 53) as f64;
+        sample < loss
+    }
+}
+
+impl<S: ServerSocket> ServerSocket for LossySocket<S> {
+    fn is_encrypted(&self) -> bool {
+        self.inner.is_encrypted()
+    }
+    fn is_reliable(&self) -> bool {
+        self.inner.is_reliable()
+    }
+
+    fn addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.addr()
+    }
+
+    fn is_closed(&mut self) -> bool {
+        self.inner.is_closed()
+    }
+
+    fn close(&mut self) {
+        self.inner.close()
+    }
+    fn connection_denied(&mut self, addr: SocketAddr) {
+        self.inner.connection_denied(addr)
+    }
+    fn connection_accepted(&mut self, client_id: u64, addr: SocketAddr) {
+        self.inner.connection_accepted(client_id, addr)
+    }
+    fn disconnect(&mut self, addr: SocketAddr) {
+        self.inner.disconnect(addr)
+    }
+    fn preupdate(&mut self) {
+        self.inner.preupdate()
+    }
+
+    fn try_recv(&mut self, buffer: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.inner.try_recv(buffer)
+    }
+
+    fn postupdate(&mut self) {
+        self.inner.postupdate()
+    }
+
+    fn send(&mut self, addr: SocketAddr, packet: &[u8]) -> Result<(), NetcodeTransportError> {
+        if self.should_drop() {
+            return Ok(());
+        }
+        self.inner.send(addr, packet)
+    }
+}
+
+impl<S: ClientSocket> ClientSocket for LossySocket<S> {
+    fn is_encrypted(&self) -> bool {
+        self.inner.is_encrypted()
+    }
+    fn is_reliable(&self) -> bool {
+        self.inner.is_reliable()
+    }
+
+    fn addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.addr()
+    }
+
+    fn is_closed(&mut self) -> bool {
+        self.inner.is_closed()
+    }
+
+    fn close(&mut self) {
+        self.inner.close()
+    }
+    fn preupdate(&mut self) {
+        self.inner.preupdate()
+    }
+
+    fn try_recv(&mut self, buffer: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.inner.try_recv(buffer)
+    }
+
+    fn postupdate(&mut self) {
+        self.inner.postupdate()
+    }
+
+    fn send(&mut self, addr: SocketAddr, packet: &[u8]) -> Result<(), NetcodeTransportError> {
+        if self.should_drop() {
+            return Ok(());
+        }
+        self.inner.send(addr, packet)
+    }
+}
+
+/// Config for setting up a [`TestHarness`].
+#[derive(Debug, Clone)]
+pub struct TestHarnessConfig {
+    /// Number of clients to create. Clients get the ids `1..=num_clients`.
+    pub num_clients: u16,
+    /// Connection config shared by the server and clients.
+    pub connection_config: ConnectionConfig,
+    /// Protocol id used by the server and clients.
+    pub protocol_id: u64,
+    /// Virtual time that passes in each [`TestHarness::step`].
+    pub tick: Duration,
+    /// Virtual time when the harness is created.
+    pub start_time: Duration,
+    /// See [`new_memory_sockets`].
+    pub encrypted: bool,
+    /// See [`new_memory_sockets`].
+    pub reliable: bool,
+    /// Seed used to decide which packets are dropped when packet loss is set.
+    pub seed: u64,
+}
+
+impl TestHarnessConfig {
+    /// Makes a new config with default settings.
+    pub fn new(num_clients: u16) -> Self {
+        Self {
+            num_clients,
+            connection_config: ConnectionConfig::test(),
+            protocol_id: 0,
+            tick: Duration::from_millis(16),
+            start_time: Duration::ZERO,
+            encrypted: false,
+            reliable: false,
+            seed: 0,
+        }
+    }
+}
+
+/// A client driven by a [`TestHarness`].
+#[derive(Debug)]
+pub struct TestClient {
+    pub client: RenetClient,
+    pub transport: NetcodeClientTransport,
+    loss: PacketLoss,
+}
+
+impl TestClient {
+    /// Returns the client's id.
+    pub fn client_id(&self) -> ClientId {
+        self.transport.client_id()
+    }
+}
+
+/// Deterministic harness for testing a [`RenetServer`] and many [`RenetClients`](RenetClient).
+///
+/// The server and clients are connected with in-memory sockets, and are driven by a virtual clock that only
+/// advances when the harness is stepped. Server events are collected by the harness every step, use
+/// [`Self::take_server_events`] or [`Self::expect_server_event`] to inspect them.
+///
+/// # Usage
+/// ```
+/// # use renet2::DefaultChannel;
+/// # use renet2_netcode::{TestHarness, TestHarnessConfig};
+/// # use std::time::Duration;
+/// let mut harness = TestHarness::new(TestHarnessConfig::new(2));
+/// assert!(harness.connect_all(Duration::from_secs(5)));
+///
+/// harness.server.send_message(1, DefaultChannel::ReliableOrdered, "hello");
+/// harness.step_for(Duration::from_millis(100));
+/// assert_eq!(harness.client_messages(1, DefaultChannel::ReliableOrdered), vec!["hello"]);
+/// ```
+#[derive(Debug)]
+pub struct TestHarness {
+    pub server: RenetServer,
+    pub server_transport: NetcodeServerTransport,
+    clients: Vec<TestClient>,
+    server_loss: PacketLoss,
+    server_events: Vec<ServerEvent>,
+    current_time: Duration,
+    tick: Duration,
+}
+
+impl TestHarness {
+    /// Makes a new harness. Clients start connecting on the first step.
+    pub fn new(config: TestHarnessConfig) -> Self {
+        let client_ids: Vec<u16> = (1..=config.num_clients).collect();
+        let (server_socket, client_sockets) = new_memory_sockets(client_ids, config.encrypted, config.reliable);
+
+        let server_loss = PacketLoss::default();
+        let server_socket: LossySocket<MemorySocketServer> = LossySocket::new(server_socket, server_loss.clone(), config.seed);
+        let server_config = ServerSetupConfig {
+            current_time: config.start_time,
+            max_clients: config.num_clients as usize,
+            protocol_id: config.protocol_id,
+            socket_addresses: vec![vec![in_memory_server_addr()]],
+            authentication: ServerAuthentication::Unsecure,
+        };
+        let server_transport = NetcodeServerTransport::new(server_config, server_socket).unwrap();
+        let server = RenetServer::new(config.connection_config.clone());
+
+        let clients = client_sockets
+            .into_iter()
+            .map(|socket: MemorySocketClient| {
+                let client_id = socket.id();
+                let loss = PacketLoss::default();
+                let socket = LossySocket::new(socket, loss.clone(), config.seed.wrapping_add(client_id));
+                let reliable = ClientSocket::is_reliable(&socket);
+                let authentication = ClientAuthentication::Unsecure {
+                    protocol_id: config.protocol_id,
+                    client_id,
+                    socket_id: 0,
+                    server_addr: in_memory_server_addr(),
+                    user_data: None,
+                };
+                let transport = NetcodeClientTransport::new(config.start_time, authentication, socket).unwrap();
+                let client = RenetClient::new(config.connection_config.clone(), reliable);
+
+                TestClient { client, transport, loss }
+            })
+            .collect();
+
+        Self {
+            server,
+            server_transport,
+            clients,
+            server_loss,
+            server_events: Vec::new(),
+            current_time: config.start_time,
+            tick: config.tick,
+        }
+    }
+
+    /// Returns the current virtual time.
+    pub fn current_time(&self) -> Duration {
+        self.current_time
+    }
+
+    /// Returns the ids of all clients in the harness, whether connected or not.
+    pub fn client_ids(&self) -> Vec<ClientId> {
+        self.clients.iter().map(|c| c.client_id()).collect()
+    }
+
+    /// Gets a client.
+    ///
+    /// Panics if there is no client with the given id.
+    pub fn client(&mut self, client_id: ClientId) -> &mut TestClient {
+        self.clients
+            .iter_mut()
+            .find(|c| c.client_id() == client_id)
+            .unwrap_or_else(|| panic!("test harness has no client {client_id}"))
+    }
+
+    /// Returns `true` if all clients are connected.
+    pub fn all_connected(&self) -> bool {
+        self.clients.iter().all(|c| c.client.is_connected())
+    }
+
+    /// Advances the virtual clock by one tick, updating the server and all clients and exchanging packets.
+    pub fn step(&mut self) {
+        let duration = self.tick;
+        self.current_time += duration;
+
+        for test_client in self.clients.iter_mut() {
+            test_client.client.update(duration);
+            if let Err(e) = test_client.transport.update(duration, &mut test_client.client) {
+                log::trace!("Test client {} transport error: {e}", test_client.client_id());
+            }
+        }
+
+        self.server.update(duration);
+        if let Err(errors) = self.server_transport.update(duration, &mut self.server) {
+            for e in errors {
+                log::trace!("Test server transport error: {e}");
+            }
+        }
+        while let Some(event) = self.server.get_event() {
+            self.server_events.push(event);
+        }
+
+        self.server_transport.send_packets(&mut self.server);
+        for test_client in self.clients.iter_mut() {
+            if let Err(e) = test_client.transport.send_packets(&mut test_client.client) {
+                log::trace!("Test client {} failed sending packets: {e}", test_client.client_id());
+            }
+        }
+    }
+
+    /// Steps the harness until at least `duration` of virtual time has passed.
+    pub fn step_for(&mut self, duration: Duration) {
+        let end = self.current_time + duration;
+        while self.current_time < end {
+            self.step();
+        }
+    }
+
+    /// Steps the harness until the condition returns `true`.
+    ///
+    /// The condition is checked before each step. Returns `false` if `timeout` of virtual time passed without
+    /// the condition being satisfied.
+    pub fn run_until(&mut self, timeout: Duration, mut condition: impl FnMut(&mut Self) -> bool) -> bool {
+        let end = self.current_time + timeout;
+        loop {
+            if condition(self) {
+                return true;
+            }
+            if self.current_time >= end {
+                return false;
+            }
+            self.step();
+        }
+    }
+
+    /// Steps the harness until all clients are connected.
+    ///
+    /// Returns `false` if `timeout` of virtual time passed before all clients connected.
+    pub fn connect_all(&mut self, timeout: Duration) -> bool {
+        self.run_until(timeout, |h| h.all_connected())
+    }
+
+    /// Sets the packet loss for all packets sent by the server and all clients.
+    pub fn set_packet_loss(&mut self, loss: f64) {
+        self.server_loss.set(loss);
+        for test_client in self.clients.iter() {
+            test_client.loss.set(loss);
+        }
+    }
+
+    /// Sets the packet loss for packets sent by the server.
+    pub fn set_server_packet_loss(&mut self, loss: f64) {
+        self.server_loss.set(loss);
+    }
+
+    /// Sets the packet loss for packets sent by a client.
+    pub fn set_client_packet_loss(&mut self, client_id: ClientId, loss: f64) {
+        self.client(client_id).loss.set(loss);
+    }
+
+    /// Takes all server events collected since the last call.
+    pub fn take_server_events(&mut self) -> Vec<ServerEvent> {
+        std::mem::take(&mut self.server_events)
+    }
+
+    /// Removes the first collected server event that equals `event`.
+    ///
+    /// Panics if no such event was collected.
+    pub fn expect_server_event(&mut self, event: &ServerEvent) {
+        match self.server_events.iter().position(|e| e == event) {
+            Some(index) => {
+                self.server_events.remove(index);
+            }
+            None => panic!("expected server event {event:?}, collected events: {:?}", self.server_events),
+        }
+    }
+
+    /// Receives all available messages sent by a client over a channel.
+    pub fn server_messages<I: Into<u8>>(&mut self, client_id: ClientId, channel_id: I) -> Vec<Bytes> {
+        let channel_id = channel_id.into();
+        let mut messages = Vec::new();
+        while let Some(message) = self.server.receive_message(client_id, channel_id) {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Receives all available messages sent to a client over a channel.
+    pub fn client_messages<I: Into<u8>>(&mut self, client_id: ClientId, channel_id: I) -> Vec<Bytes> {
+        let channel_id = channel_id.into();
+        let client = &mut self.client(client_id).client;
+        let mut messages = Vec::new();
+        while let Some(message) = client.receive_message(channel_id) {
+            messages.push(message);
+        }
+        messages
+    }
+}
@@ -91,12 +91,18 @@ impl WebSocketServerClient {
         // Setup reader.
         let (sender, incoming_receiver) = crossbeam::channel::bounded::<Bytes>(256);
         let (reader_aborter, abort_receiver) = mpsc::unbounded_channel::<()>();
-        let reader_handle = tokio::spawn(async move { WebSocketServer::reading_thread(stream, sender, abort_receiver).await });
+        let reader = WebSocketServer::reading_thread(stream, sender, abort_receiver);
+        #[cfg(feature = "tracing")]
+        let reader = tracing::Instrument::in_current_span(reader);
+        let reader_handle = tokio::spawn(reader);
 
         // Setup writer.
         // - Writer must be in a thread because sending is async.
         let (outgoing_sender, receiver) = mpsc::channel::<Bytes>(256);
-        let writer_handle = tokio::spawn(async move { WebSocketServer::writing_thread(sink, receiver, client_idx).await });
+        let writer = WebSocketServer::writing_thread(sink, receiver, client_idx);
+        #[cfg(feature = "tracing")]
+        let writer = tracing::Instrument::in_current_span(writer);
+        let writer_handle = tokio::spawn(writer);
 
         Self {
             client_id,
@@ -258,14 +264,22 @@ impl WebSocketServer {
         current_clients: Arc<AtomicUsize>,
         max_clients: usize,
     ) {
-        while let Ok((mut stream, _)) = socket.accept().await {
+        while let Ok(accepted) = socket.accept().await {
+            #[cfg(feature = "tracing")]
+            let span = tracing::info_span!(
+                "ws_connection",
+                peer_addr = %accepted.1,
+                client_idx = tracing::field::Empty,
+                client_id = tracing::field::Empty
+            );
+            let (mut stream, _) = accepted;
             let acceptor = acceptor.clone();
             let connection_sender = connection_sender.clone();
             let connection_req_sender = connection_req_sender.clone();
             let current_clients = current_clients.clone();
             let client_iterator = client_iterator.clone();
 
-            tokio::spawn(async move {
+            let task = async move {
                 let is_full = {
                     let current_clients = current_clients.load(Ordering::Relaxed);
                     // We allow 25% extra clients in case clients want to override their old sessions.
@@ -273,7 +287,7 @@ impl WebSocketServer {
                 };
                 if is_full {
                     stream.shutdown().await.ok();
-                    log::debug!("Server is full, rejecting connection");
+                    log_event!(debug, "Server is full, rejecting connection");
                     return;
                 }
 
@@ -286,10 +300,13 @@ impl WebSocketServer {
                         }
                     }
                     Err(err) => {
-                        log::debug!("Failed to handle connection: {:?}", err);
+                        log_event!(debug, "Failed to handle connection: {:?}", err);
                     }
                 }
-            });
+            };
+            #[cfg(feature = "tracing")]
+            let task = tracing::Instrument::instrument(task, span);
+            tokio::spawn(task);
         }
     }
 
@@ -341,6 +358,8 @@ impl WebSocketServer {
 
         // Assign an identifier to this client.
         let client_idx = client_iterator.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("client_idx", client_idx);
 
         // Send connection request packet to netcode for evaluation.
         let (result_sender, mut result_receiver) = mpsc::channel::<ConnectionRequestResult>(1);
@@ -359,10 +378,14 @@ impl WebSocketServer {
         // - The connection must be validated before we accept the session to avoid resources being
         //   consumed by fake clients.
         let Some(ConnectionRequestResult::Success { client_id }) = result_receiver.recv().await else {
+            log_event!(debug, "Connection request {} denied", client_idx);
             return Ok(None);
         };
 
         // Finalize the connection.
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("client_id", client_id);
+        log_event!(debug, "Connection request {} accepted as client {}", client_idx, client_id);
         let server_client = (make_server_client)(client_id, client_idx);
 
         Ok(Some(server_client))
@@ -421,7 +444,7 @@ impl WebSocketServer {
                     }
                 },
                 _ = &mut sleep => {
-                    log::trace!("WS client socket reader timed out, disconnecting.");
+                    log_event!(trace, "WS client socket reader timed out, disconnecting.");
                     break;
                 }
                 else => {
@@ -303,14 +303,26 @@ impl WebTransportServer {
                 (current_clients * 4) >= (max_clients * 5)
             };
             if is_full {
+                log_event!(
+                    debug,
+                    "Server is full, refusing connection from {}",
+                    incoming_connection.remote_address()
+                );
                 incoming_connection.refuse();
                 continue;
             }
 
+            #[cfg(feature = "tracing")]
+            let span = tracing::info_span!(
+                "wt_connection",
+                peer_addr = %incoming_connection.remote_address(),
+                client_idx = tracing::field::Empty,
+                client_id = tracing::field::Empty
+            );
             let sender = sender.clone();
             let client_iterator = client_iterator.clone();
             let connection_req_sender = connection_req_sender.clone();
-            tokio::spawn(async move {
+            let task = async move {
                 match incoming_connection.await {
                     Ok(session_request) => {
                         match Self::handle_session_request(client_iterator, connection_req_sender, session_request).await {
@@ -322,15 +334,18 @@ impl WebTransportServer {
                                 }
                             }
                             Err(err) => {
-                                debug!("Failed to handle connection: {err:?}");
+                                log_event!(debug, "Failed to handle connection: {err:?}");
                             }
                         }
                     }
                     Err(err) => {
-                        debug!("accepting connection failed: {err:?}");
+                        log_event!(debug, "accepting connection failed: {err:?}");
                     }
                 }
-            });
+            };
+            #[cfg(feature = "tracing")]
+            let task = tracing::Instrument::instrument(task, span);
+            tokio::spawn(task);
         }
     }
 
@@ -349,6 +364,8 @@ impl WebTransportServer {
 
         // Assign an identifier to this client.
         let client_idx = client_iterator.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("client_idx", client_idx);
 
         // Send connection request packet to netcode for evaluation.
         let (result_sender, mut result_receiver) = mpsc::channel::<ConnectionRequestResult>(1usize);
@@ -364,20 +381,26 @@ impl WebTransportServer {
         // - The connection must be validated before we accept the session to avoid resources being
         //   consumed by fake clients.
         let Some(ConnectionRequestResult::Success { client_id }) = result_receiver.recv().await else {
+            log_event!(debug, "Connection request {client_idx} denied");
             return Ok(None);
         };
 
         // Finalize the connection.
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("client_id", client_id);
         match session_request.accept().await {
-            Ok(session) => Ok(Some(ClientConnectionResult::Success {
-                client_idx,
-                client_id,
-                session,
-            })),
+            Ok(session) => {
+                log_event!(debug, "Connection request {client_idx} accepted as client {client_id}");
+                Ok(Some(ClientConnectionResult::Success {
+                    client_idx,
+                    client_id,
+                    session,
+                }))
+            }
             Err(err) => {
                 // We must return failure here because `ConnectionRequestResult::Success` means the server
                 // is tracking this connection. We need the server to clean up its pending client entry.
-                debug!("Failed to handle connection: {err:?}");
+                log_event!(debug, "Failed to accept session: {err:?}");
                 Ok(Some(ClientConnectionResult::Failure { client_idx }))
             }
         }
@@ -389,7 +412,7 @@ impl WebTransportServer {
         sender: crossbeam::channel::Sender<Bytes>,
         mut abort_signal: mpsc::UnboundedReceiver<()>,
     ) -> tokio::task::JoinHandle<()> {
-        handle.spawn(async move {
+        let task = async move {
             // We must have a keep-alive timer here to ensure pending clients cannot occupy client slots after
             // their connect token has expired and they have been removed from the netcode server.
             // - Requiring incoming messages to reset the timer means pending clients will eventually cause netcode
@@ -424,7 +447,7 @@ impl WebTransportServer {
                         }
                     },
                     _ = &mut sleep => {
-                        trace!("WT client socket reader timed out, disconnecting.");
+                        log_event!(trace, "WT client socket reader timed out, disconnecting.");
                         break;
                     }
                     else => {
@@ -434,7 +457,10 @@ impl WebTransportServer {
 
                 sleep.as_mut().reset(tokio::time::Instant::now() + timeout);
             }
-        })
+        };
+        #[cfg(feature = "tracing")]
+        let task = tracing::Instrument::in_current_span(task);
+        handle.spawn(task)
     }
 }
 
@@ -544,6 +570,8 @@ impl ServerSocket for WebTransportServer {
             // Set up datagram reading for the session.
             let (sender, receiver) = crossbeam::channel::bounded::<Bytes>(256);
             let (abort_sender, abort_receiver) = mpsc::unbounded_channel::<()>();
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("wt_connection", peer_addr = %session.remote_address(), client_idx, client_id).entered();
             let thread = Self::reading_thread(&self.handle, session.clone(), sender, abort_receiver);
             self.clients.insert(
                 client_idx,
@@ -0,0 +1,121 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use renet2::{ConnectionConfig, DefaultChannel, RenetClient, RenetServer};
+use renet2_netcode::{CaptureDirection, CaptureRecord, CaptureReplay, PacketCapture, PacketCaptureReader, TestHarness, TestHarnessConfig};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn records(&self) -> Vec<CaptureRecord> {
+        let bytes = self.0.lock().unwrap().clone();
+        PacketCaptureReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+}
+
+#[test]
+fn client_capture_replay() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(1));
+    let buffer = SharedBuffer::default();
+    harness
+        .client(1)
+        .transport
+        .start_capture(PacketCapture::new(buffer.clone()).unwrap());
+
+    assert!(harness.connect_all(Duration::from_secs(5)));
+    for i in 0..10u8 {
+        harness.server.send_message(1, DefaultChannel::ReliableOrdered, vec![i; 2000]);
+    }
+    harness.step_for(Duration::from_millis(200));
+    let received = harness.client_messages(1, DefaultChannel::ReliableOrdered);
+    assert_eq!(received.len(), 10);
+    assert!(harness.client(1).transport.stop_capture().is_some());
+
+    let records = buffer.records();
+    assert!(records
+        .iter()
+        .any(|r| r.direction == CaptureDirection::Outgoing && r.payload.is_none()));
+    assert!(records
+        .iter()
+        .any(|r| r.direction == CaptureDirection::Incoming && r.payload.is_some()));
+    assert!(records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    let mut client = RenetClient::new(ConnectionConfig::test(), false);
+    let mut replay = CaptureReplay::new(records);
+    replay.run_client(&mut client);
+    assert!(replay.is_finished());
+
+    let mut replayed = Vec::new();
+    while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
+        replayed.push(message);
+    }
+    assert_eq!(replayed, received);
+}
+
+#[test]
+fn server_capture_replay() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(2));
+    let buffer = SharedBuffer::default();
+    harness.server_transport.start_capture(PacketCapture::new(buffer.clone()).unwrap());
+
+    assert!(harness.connect_all(Duration::from_secs(5)));
+    harness.client(1).client.send_message(DefaultChannel::ReliableOrdered, "one");
+    harness.client(2).client.send_message(DefaultChannel::ReliableOrdered, "two");
+    harness.step_for(Duration::from_millis(100));
+    assert!(harness.server_transport.stop_capture().is_some());
+
+    let mut server = RenetServer::new(ConnectionConfig::test());
+    let mut replay = CaptureReplay::new(buffer.records());
+    replay.run_server(&mut server, false);
+
+    assert_eq!(server.receive_message(1, DefaultChannel::ReliableOrdered).unwrap(), "one");
+    assert_eq!(server.receive_message(2, DefaultChannel::ReliableOrdered).unwrap(), "two");
+}
+
+#[test]
+fn restart_capture() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(1));
+    let first = SharedBuffer::default();
+    let second = SharedBuffer::default();
+    assert!(harness
+        .server_transport
+        .start_capture(PacketCapture::new(first.clone()).unwrap())
+        .is_none());
+    assert!(harness.connect_all(Duration::from_secs(5)));
+
+    // Restarting returns the previous capture
+    assert!(harness
+        .server_transport
+        .start_capture(PacketCapture::new(second.clone()).unwrap())
+        .is_some());
+    let first_len = first.records().len();
+    assert!(first_len > 0);
+
+    harness.client(1).client.send_message(DefaultChannel::ReliableOrdered, "one");
+    harness.step_for(Duration::from_millis(100));
+    assert!(harness.server_transport.stop_capture().is_some());
+    assert!(harness.server_transport.stop_capture().is_none());
+
+    assert_eq!(first.records().len(), first_len);
+    assert!(second.records().iter().any(|r| r.payload.is_some()));
+}
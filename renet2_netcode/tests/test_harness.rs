@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use renet2::{DefaultChannel, DisconnectReason, ServerEvent};
+use renet2_netcode::{TestHarness, TestHarnessConfig};
+
+fn init_log() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn connect_and_exchange_messages() {
+    init_log();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(3));
+    assert!(harness.connect_all(Duration::from_secs(5)));
+
+    let mut events = harness.take_server_events();
+    events.sort_by_key(|e| match e {
+        ServerEvent::ClientConnected { client_id } => *client_id,
+        ServerEvent::ClientDisconnected { client_id, .. } => *client_id,
+        ServerEvent::ClientRateLimited { client_id, .. } => *client_id,
+        ServerEvent::PingCompleted { client_id, .. } => *client_id,
+    });
+    assert_eq!(
+        events,
+        (1..=3)
+            .map(|client_id| ServerEvent::ClientConnected { client_id })
+            .collect::<Vec<_>>()
+    );
+
+    harness.server.broadcast_message(DefaultChannel::ReliableOrdered, "hello");
+    harness.client(2).client.send_message(DefaultChannel::Unreliable, "from 2");
+    harness.step_for(Duration::from_millis(100));
+
+    for client_id in harness.client_ids() {
+        assert_eq!(harness.client_messages(client_id, DefaultChannel::ReliableOrdered), vec!["hello"]);
+    }
+    assert_eq!(harness.server_messages(2, DefaultChannel::Unreliable), vec!["from 2"]);
+    assert!(harness.server_messages(1, DefaultChannel::Unreliable).is_empty());
+}
+
+#[test]
+fn reliable_delivery_under_loss() {
+    init_log();
+    let mut config = TestHarnessConfig::new(1);
+    config.seed = 42;
+    let mut harness = TestHarness::new(config);
+    assert!(harness.connect_all(Duration::from_secs(5)));
+
+    harness.set_packet_loss(0.5);
+    for i in 0..50u32 {
+        harness
+            .server
+            .send_message(1, DefaultChannel::ReliableOrdered, i.to_le_bytes().to_vec());
+    }
+
+    let mut received = Vec::new();
+    let delivered = harness.run_until(Duration::from_secs(10), |h| {
+        received.extend(h.client_messages(1, DefaultChannel::ReliableOrdered));
+        received.len() == 50
+    });
+    assert!(delivered);
+    for (i, message) in received.iter().enumerate() {
+        assert_eq!(message[..], (i as u32).to_le_bytes());
+    }
+}
+
+#[test]
+fn deterministic_time() {
+    init_log();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(1));
+    assert_eq!(harness.current_time(), Duration::ZERO);
+    harness.step();
+    assert_eq!(harness.current_time(), Duration::from_millis(16));
+    harness.step_for(Duration::from_millis(100));
+    assert_eq!(harness.current_time(), Duration::from_millis(128));
+}
+
+#[test]
+fn client_disconnect_event() {
+    init_log();
+    let mut harness = TestHarness::new(TestHarnessConfig::new(2));
+    assert!(harness.connect_all(Duration::from_secs(5)));
+    harness.take_server_events();
+
+    harness.client(1).client.disconnect();
+    harness.step_for(Duration::from_millis(100));
+
+    harness.expect_server_event(&ServerEvent::ClientDisconnected {
+        client_id: 1,
+        reason: DisconnectReason::Transport,
+    });
+    assert!(harness.take_server_events().is_empty());
+    assert!(harness.client(2).client.is_connected());
+}
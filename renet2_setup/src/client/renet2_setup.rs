@@ -1,13 +1,11 @@
 #![allow(unused_imports)]
 
 use renet2::{ConnectionConfig, RenetClient};
-use renet2_netcode::{ClientAuthentication, ClientSocket, NetcodeClientTransport};
+use renet2_netcode::{ClientAuthentication, ClientSocket, Clock, NetcodeClientTransport};
 
+use crate::{ClientConnectPack, SetupClock};
 #[cfg(not(target_family = "wasm"))]
 use std::net::{SocketAddr, UdpSocket};
-use wasm_timer::SystemTime;
-
-use crate::ClientConnectPack;
 
 //-------------------------------------------------------------------------------------------------------------------
 
@@ -25,10 +23,7 @@ fn setup_native_renet_client(
     let client = RenetClient::new(connection_config, client_socket.is_reliable());
 
     // make transport
-    let current_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|err| format!("failed getting current time: {err:?}"))?;
-    let transport = NetcodeClientTransport::new(current_time, authentication, client_socket)
+    let transport = NetcodeClientTransport::new(SetupClock.now(), authentication, client_socket)
         .map_err(|err| format!("failed constructing netcode client transport: {err:?}"))?;
 
     Ok((client, transport))
@@ -48,10 +43,7 @@ fn setup_wasm_wt_renet_client(
     let client = RenetClient::new(connection_config, client_socket.is_reliable());
 
     // make transport
-    let current_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|err| format!("failed getting current time: {err:?}"))?;
-    let transport = NetcodeClientTransport::new(current_time, authentication, client_socket)
+    let transport = NetcodeClientTransport::new(SetupClock.now(), authentication, client_socket)
         .map_err(|err| format!("failed constructing netcode client transport: {err:?}"))?;
 
     Ok((client, transport))
@@ -72,10 +64,7 @@ fn setup_wasm_ws_renet_client(
     let client = RenetClient::new(connection_config, client_socket.is_reliable());
 
     // make transport
-    let current_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|err| format!("failed getting current time: {err:?}"))?;
-    let transport = NetcodeClientTransport::new(current_time, authentication, client_socket)
+    let transport = NetcodeClientTransport::new(SetupClock.now(), authentication, client_socket)
         .map_err(|err| format!("failed constructing netcode client transport: {err:?}"))?;
 
     Ok((client, transport))
@@ -94,10 +83,7 @@ fn setup_memory_renet_client(
     let client = RenetClient::new(connection_config, client_socket.is_reliable());
 
     // make transport
-    let current_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|err| format!("failed getting current time: {err:?}"))?;
-    let transport = NetcodeClientTransport::new(current_time, authentication, client_socket)
+    let transport = NetcodeClientTransport::new(SetupClock.now(), authentication, client_socket)
         .map_err(|err| format!("failed constructing netcode client transport: {err:?}"))?;
 
     Ok((client, transport))
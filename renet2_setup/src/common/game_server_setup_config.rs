@@ -61,6 +61,12 @@ pub struct GameServerSetupConfig {
     ///
     /// If this is true then [`Self::wss_certs`] should be `None`.
     pub has_wss_proxy: bool,
+    /// Compares connect token timestamps to the system time instead of the server uptime, with `SetupClock`.
+    ///
+    /// Enable it when connect tokens are issued by another process, so they don't look expired after the server
+    /// time drifts from the system time.
+    #[serde(default)]
+    pub use_system_clock: bool,
 }
 
 impl GameServerSetupConfig {
@@ -83,6 +89,7 @@ impl GameServerSetupConfig {
             wss_certs: None,
             ws_domain: None,
             has_wss_proxy: false,
+            use_system_clock: false,
         }
     }
 
@@ -5,6 +5,8 @@ mod connection_type;
 mod game_server_setup_config;
 #[cfg(feature = "netcode")]
 mod server_connect_token;
+#[cfg(any(feature = "client", feature = "server"))]
+mod setup_clock;
 
 pub use address_utils::*;
 #[cfg(feature = "netcode")]
@@ -13,3 +15,5 @@ pub use connection_type::*;
 pub use game_server_setup_config::*;
 #[cfg(feature = "netcode")]
 pub use server_connect_token::*;
+#[cfg(any(feature = "client", feature = "server"))]
+pub use setup_clock::*;
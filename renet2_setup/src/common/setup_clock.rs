@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use renet2_netcode::Clock;
+use wasm_timer::{SystemTime, UNIX_EPOCH};
+
+//-------------------------------------------------------------------------------------------------------------------
+
+/// [`Clock`] that reads the system time, also on WASM targets.
+///
+/// Set on server transports made by the setup functions if [`GameServerSetupConfig::use_system_clock`](crate::GameServerSetupConfig::use_system_clock) is enabled.
+/// Client transports don't have a clock by default, set it with `set_clock` to check connect tokens against the
+/// system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SetupClock;
+
+impl Clock for SetupClock {
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------------
@@ -1,9 +1,8 @@
-use crate::common::{ConnectMetaNative, ConnectMetaWasmWs, ConnectMetaWasmWt, ConnectMetas, GameServerSetupConfig};
+use crate::common::{ConnectMetaNative, ConnectMetaWasmWs, ConnectMetaWasmWt, ConnectMetas, GameServerSetupConfig, SetupClock};
 use renet2::{ConnectionConfig, RenetServer};
-use renet2_netcode::{BoxedSocket, NetcodeServerTransport, ServerAuthentication, ServerSetupConfig};
+use renet2_netcode::{BoxedSocket, Clock, NetcodeServerTransport, ServerAuthentication, ServerSetupConfig};
 
 use std::net::SocketAddr;
-use wasm_timer::{SystemTime, UNIX_EPOCH};
 
 use super::ClientCounts;
 
@@ -332,7 +331,7 @@ pub fn setup_combo_renet2_server_with_key(
 
     // save final addresses
     let server_config = ServerSetupConfig {
-        current_time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+        current_time: SetupClock.now(),
         max_clients,
         protocol_id: config.protocol_id,
         socket_addresses,
@@ -341,8 +340,11 @@ pub fn setup_combo_renet2_server_with_key(
 
     // construct server
     let server = RenetServer::new(connection_config);
-    let server_transport = NetcodeServerTransport::new_with_sockets(server_config, sockets)
+    let mut server_transport = NetcodeServerTransport::new_with_sockets(server_config, sockets)
         .map_err(|err| format!("failed constructing renet2 netcode server transport: {err:?}"))?;
+    if config.use_system_clock {
+        server_transport.set_clock(SetupClock);
+    }
 
     Ok((server, server_transport, connect_metas))
 }
@@ -371,11 +373,7 @@ pub fn setup_combo_renet2_server(
                 ));
             }
 
-            let time: [u8; 16] = wasm_timer::SystemTime::now()
-                .duration_since(wasm_timer::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_nanos()
-                .to_le_bytes();
+            let time: [u8; 16] = SetupClock.now().as_nanos().to_le_bytes();
             let mut key: [u8; 32] = Default::default();
             key[..16].clone_from_slice(&time);
             key
@@ -98,6 +98,8 @@ fn run_server(steam_client: Client<ClientManager>, single: SingleClient, with_lo
                 ServerEvent::ClientDisconnected { client_id, reason } => {
                     println!("Client {} disconnected: {}", client_id, reason);
                 }
+                ServerEvent::ClientRateLimited { .. } => {}
+                ServerEvent::PingCompleted { .. } => {}
             }
         }
 
@@ -130,6 +130,7 @@ fn server(addr: SocketAddr, private_key: [u8; NETCODE_KEY_BYTES]) {
     let config = ServerConfig {
         current_time,
         max_clients: 16,
+        max_pending_clients: 64,
         protocol_id: PROTOCOL_ID,
         sockets: vec![ServerSocketConfig::new(vec![addr])],
         authentication: ServerAuthentication::Secure { private_key },
@@ -0,0 +1,161 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+
+use crate::NETCODE_MAC_BYTES;
+
+/// A range of IP addresses in CIDR notation, such as `10.0.0.0/8`.
+///
+/// IPv4-mapped IPv6 addresses are treated as their IPv4 address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Makes a new range with the addresses that share the first `prefix_len` bits with `addr`.
+    ///
+    /// Panics if `prefix_len` is above 32 for IPv4 addresses or above 128 for IPv6 addresses.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Self {
+        let network = match addr.to_canonical() {
+            IpAddr::V4(addr) => {
+                assert!(prefix_len <= 32, "IPv4 prefix length must be at most 32, got {prefix_len}");
+                let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(addr) & mask))
+            }
+            IpAddr::V6(addr) => {
+                assert!(prefix_len <= 128, "IPv6 prefix length must be at most 128, got {prefix_len}");
+                let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(addr) & mask))
+            }
+        };
+
+        Self { network, prefix_len }
+    }
+
+    /// Returns the first address of the range.
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    /// Returns the number of leading bits shared by the addresses in the range.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns whether the address is in the range.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        addr.is_ipv4() == self.network.is_ipv4() && Self::new(addr, self.prefix_len) == *self
+    }
+}
+
+impl From<IpAddr> for IpRange {
+    /// Makes a range with a single address.
+    fn from(addr: IpAddr) -> Self {
+        let prefix_len = match addr.to_canonical() {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Banned client ids and addresses until a server time, and revoked connect tokens until a token time.
+#[derive(Debug, Default)]
+pub(crate) struct BanList {
+    clients: HashMap<u64, Duration>,
+    addresses: HashMap<IpRange, Duration>,
+    tokens: HashMap<[u8; NETCODE_MAC_BYTES], Duration>,
+}
+
+impl BanList {
+    pub fn ban_client(&mut self, client_id: u64, until: Duration) {
+        self.clients.insert(client_id, until);
+    }
+
+    pub fn unban_client(&mut self, client_id: u64) -> bool {
+        self.clients.remove(&client_id).is_some()
+    }
+
+    pub fn is_client_banned(&self, client_id: u64, current_time: Duration) -> bool {
+        self.clients.get(&client_id).is_some_and(|until| *until > current_time)
+    }
+
+    pub fn ban_address(&mut self, range: IpRange, until: Duration) {
+        self.addresses.insert(range, until);
+    }
+
+    pub fn unban_address(&mut self, range: IpRange) -> bool {
+        self.addresses.remove(&range).is_some()
+    }
+
+    pub fn is_address_banned(&self, addr: IpAddr, current_time: Duration) -> bool {
+        self.addresses
+            .iter()
+            .any(|(range, until)| *until > current_time && range.contains(addr))
+    }
+
+    pub fn revoke_token(&mut self, mac: [u8; NETCODE_MAC_BYTES], until: Duration) {
+        self.tokens.insert(mac, until);
+    }
+
+    pub fn is_token_revoked(&self, mac: &[u8; NETCODE_MAC_BYTES], token_time: Duration) -> bool {
+        self.tokens.get(mac).is_some_and(|until| *until > token_time)
+    }
+
+    /// Removes the bans and revocations that have ended.
+    pub fn remove_expired(&mut self, current_time: Duration, token_time: Duration) {
+        self.clients.retain(|_, until| *until > current_time);
+        self.addresses.retain(|_, until| *until > current_time);
+        self.tokens.retain(|_, until| *until > token_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_range() {
+        let range = IpRange::new("10.1.2.3".parse().unwrap(), 16);
+        assert_eq!(range.to_string(), "10.1.0.0/16");
+        assert!(range.contains("10.1.255.1".parse().unwrap()));
+        assert!(range.contains("::ffff:10.1.0.1".parse().unwrap()));
+        assert!(!range.contains("10.2.0.1".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+
+        let everything = IpRange::new("1.2.3.4".parse().unwrap(), 0);
+        assert!(everything.contains("255.255.255.255".parse().unwrap()));
+
+        let single = IpRange::from("2001:db8::1".parse::<IpAddr>().unwrap());
+        assert!(single.contains("2001:db8::1".parse().unwrap()));
+        assert!(!single.contains("2001:db8::2".parse().unwrap()));
+        assert!(!single.contains("10.1.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ban_expiry() {
+        let mut bans = BanList::default();
+        bans.ban_client(3, Duration::from_secs(10));
+        assert!(bans.is_client_banned(3, Duration::from_secs(9)));
+        assert!(!bans.is_client_banned(3, Duration::from_secs(10)));
+        assert!(!bans.is_client_banned(4, Duration::ZERO));
+
+        // Revoked tokens are removed with the token time
+        bans.revoke_token([1; NETCODE_MAC_BYTES], Duration::from_secs(10));
+        bans.remove_expired(Duration::from_secs(10), Duration::from_secs(9));
+        assert!(!bans.unban_client(3));
+        assert!(bans.is_token_revoked(&[1; NETCODE_MAC_BYTES], Duration::from_secs(9)));
+    }
+}
@@ -1,8 +1,13 @@
 use std::{error::Error, fmt, net::SocketAddr, time::Duration};
 
 use crate::{
-    packet::Packet, replay_protection::ReplayProtection, token::ConnectToken, NetcodeError, NETCODE_CHALLENGE_TOKEN_BYTES,
-    NETCODE_KEY_BYTES, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES, NETCODE_SEND_RATE, NETCODE_USER_DATA_BYTES,
+    clock::Clock,
+    extended_user_data::{ExtendedUserDataSender, MAX_SEALED_EXTENDED_USER_DATA_BYTES},
+    packet::{ConnectionDeniedReason, Packet},
+    replay_protection::ReplayProtection,
+    token::ConnectToken,
+    NetcodeError, NetcodeVersion, NETCODE_CHALLENGE_TOKEN_BYTES, NETCODE_KEY_BYTES, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES,
+    NETCODE_SEND_RATE, NETCODE_USER_DATA_BYTES,
 };
 
 /// The reason why a client is in error state
@@ -12,7 +17,7 @@ pub enum DisconnectReason {
     ConnectionTimedOut,
     ConnectionResponseTimedOut,
     ConnectionRequestTimedOut,
-    ConnectionDenied,
+    ConnectionDenied(ConnectionDeniedReason),
     DisconnectedByClient,
     DisconnectedByServer,
 }
@@ -57,6 +62,7 @@ pub struct NetcodeClient {
     last_packet_send_time: Option<Duration>,
     last_packet_received_time: Duration,
     current_time: Duration,
+    clock: Option<Box<dyn Clock>>,
     sequence: u64,
     server_addr: SocketAddr,
     server_addr_index: usize,
@@ -71,6 +77,8 @@ pub struct NetcodeClient {
     /// Extension to netcode to allow disabling netcode encryption if the underlying data stream is already
     /// encrypted.
     encryption_policy: bool,
+    version: NetcodeVersion,
+    extended_user_data: Option<ExtendedUserDataSender>,
 }
 
 impl fmt::Display for DisconnectReason {
@@ -82,7 +90,7 @@ impl fmt::Display for DisconnectReason {
             ConnectionTimedOut => write!(f, "connection timed out"),
             ConnectionResponseTimedOut => write!(f, "connection timed out during response step"),
             ConnectionRequestTimedOut => write!(f, "connection timed out during request step"),
-            ConnectionDenied => write!(f, "server denied connection"),
+            ConnectionDenied(reason) => write!(f, "server denied connection: {reason}"),
             DisconnectedByClient => write!(f, "connection terminated by client"),
             DisconnectedByServer => write!(f, "connection terminated by server"),
         }
@@ -115,6 +123,7 @@ impl NetcodeClient {
         };
 
         let server_addr = connect_token.server_addresses[0].expect("cannot create or deserialize a ConnectToken without a server address");
+        let version = connect_token.version().ok_or(NetcodeError::InvalidVersion)?;
 
         Ok(Self {
             sequence: 0,
@@ -127,6 +136,7 @@ impl NetcodeClient {
             last_packet_send_time: None,
             last_packet_received_time: current_time,
             current_time,
+            clock: None,
             max_clients: 0,
             client_index: 0,
             send_rate: NETCODE_SEND_RATE,
@@ -135,17 +145,46 @@ impl NetcodeClient {
             replay_protection: ReplayProtection::new(),
             out: [0u8; NETCODE_MAX_PACKET_BYTES],
             encryption_policy: true,
+            version,
+            extended_user_data: None,
         })
     }
 
     /// Sets the clients's encryption policy.
     ///
     /// Packets will not be encrypted nor decrypted if set to `false`.
+    /// Ignored with standard netcode connect tokens, that always need encryption.
     pub fn set_encryption_policy(mut self, policy: bool) -> Self {
-        self.encryption_policy = policy;
+        self.encryption_policy = policy || self.version == NetcodeVersion::Standard;
         self
     }
 
+    /// Sets extended user data to send to the server after connecting, sealed with
+    /// [`ConnectToken::seal_extended_user_data`]. It is sent again until the server acknowledges it.
+    ///
+    /// Extended user data is a `renet2` extension, so it fails with standard netcode connect tokens.
+    pub fn set_extended_user_data(&mut self, sealed: Vec<u8>) -> Result<(), NetcodeError> {
+        if self.version == NetcodeVersion::Standard {
+            return Err(NetcodeError::InvalidVersion);
+        }
+        if sealed.len() > MAX_SEALED_EXTENDED_USER_DATA_BYTES {
+            return Err(NetcodeError::ExtendedUserDataAboveLimit);
+        }
+
+        self.extended_user_data = Some(ExtendedUserDataSender::new(sealed));
+        Ok(())
+    }
+
+    /// Returns true if the server acknowledged the extended user data.
+    pub fn is_extended_user_data_acked(&self) -> bool {
+        self.extended_user_data.as_ref().is_some_and(|e| e.is_acked())
+    }
+
+    /// Returns the netcode version of the connect token, used to talk to the server.
+    pub fn version(&self) -> NetcodeVersion {
+        self.version
+    }
+
     pub fn is_connecting(&self) -> bool {
         matches!(
             self.state,
@@ -165,6 +204,14 @@ impl NetcodeClient {
         self.current_time
     }
 
+    /// Sets the clock that the expire timestamp of the connect token is compared to.
+    ///
+    /// Without a clock, the token expires after its expire duration since the client started connecting.
+    /// The current time is still the sum of the durations given to [`NetcodeClient::update`], used for timeouts.
+    pub fn set_clock(&mut self, clock: impl Clock) {
+        self.clock = Some(Box::new(clock));
+    }
+
     pub fn client_id(&self) -> u64 {
         self.client_id
     }
@@ -198,6 +245,7 @@ impl NetcodeClient {
             self.connect_token.protocol_id,
             Some((self.sequence, &self.connect_token.client_to_server_key)),
             self.encryption_policy,
+            self.version,
         )?;
 
         Ok((self.server_addr, &mut self.out[..len]))
@@ -213,6 +261,7 @@ impl NetcodeClient {
             Some(&self.connect_token.server_to_client_key),
             Some(&mut self.replay_protection),
             self.encryption_policy,
+            self.version,
         ) {
             Ok((_, packet)) => packet,
             Err(e) => {
@@ -223,8 +272,8 @@ impl NetcodeClient {
         log::trace!("Received packet from server: {:?}", packet.packet_type());
 
         match (packet, &self.state) {
-            (Packet::ConnectionDenied, ClientState::SendingConnectionRequest | ClientState::SendingConnectionResponse) => {
-                self.state = ClientState::Disconnected(DisconnectReason::ConnectionDenied);
+            (Packet::ConnectionDenied { reason }, ClientState::SendingConnectionRequest | ClientState::SendingConnectionResponse) => {
+                self.state = ClientState::Disconnected(DisconnectReason::ConnectionDenied(reason));
                 self.last_packet_received_time = self.current_time;
             }
             (
@@ -253,6 +302,12 @@ impl NetcodeClient {
                 self.last_packet_received_time = self.current_time;
                 return Some(p);
             }
+            (Packet::ExtendedUserDataAck, ClientState::Connected) => {
+                self.last_packet_received_time = self.current_time;
+                if let Some(extended_user_data) = &mut self.extended_user_data {
+                    extended_user_data.ack();
+                }
+            }
             (Packet::Disconnect, ClientState::Connected) => {
                 self.state = ClientState::Disconnected(DisconnectReason::DisconnectedByServer);
                 self.last_packet_received_time = self.current_time;
@@ -279,6 +334,7 @@ impl NetcodeClient {
             self.connect_token.protocol_id,
             Some((self.sequence, &self.connect_token.client_to_server_key)),
             self.encryption_policy,
+            self.version,
         )?;
         self.sequence += 1;
         self.last_packet_send_time = Some(self.current_time);
@@ -305,8 +361,13 @@ impl NetcodeClient {
 
         match self.state {
             ClientState::SendingConnectionRequest | ClientState::SendingConnectionResponse => {
-                let expire_seconds = self.connect_token.expire_timestamp - self.connect_token.create_timestamp;
-                let connection_expired = (self.current_time - self.connect_start_time).as_secs() >= expire_seconds;
+                let connection_expired = match &self.clock {
+                    Some(clock) => clock.now().as_secs() >= self.connect_token.expire_timestamp,
+                    None => {
+                        let expire_seconds = self.connect_token.expire_timestamp - self.connect_token.create_timestamp;
+                        (self.current_time - self.connect_start_time).as_secs() >= expire_seconds
+                    }
+                };
                 if connection_expired {
                     self.state = ClientState::Disconnected(DisconnectReason::ConnectTokenExpired);
                     return Err(NetcodeError::Expired);
@@ -352,8 +413,11 @@ impl NetcodeClient {
     }
 
     fn generate_packet(&mut self) -> Option<(&mut [u8], SocketAddr)> {
+        // The fragments of the extended user data are sent back to back, then again after the send rate until acknowledged
+        let sending_extended_user_data =
+            self.state == ClientState::Connected && self.extended_user_data.as_ref().is_some_and(|e| e.is_sending());
         if let Some(last_packet_send_time) = self.last_packet_send_time {
-            if self.current_time - last_packet_send_time < self.send_rate {
+            if self.current_time - last_packet_send_time < self.send_rate && !sending_extended_user_data {
                 return None;
             }
         }
@@ -370,9 +434,12 @@ impl NetcodeClient {
                 token_sequence: self.challenge_token_sequence,
                 token_data: self.challenge_token_data,
             },
-            ClientState::Connected => Packet::KeepAlive {
-                client_index: 0,
-                max_clients: 0,
+            ClientState::Connected => match &mut self.extended_user_data {
+                Some(extended_user_data) if !extended_user_data.is_acked() => extended_user_data.next_fragment(),
+                _ => Packet::KeepAlive {
+                    client_index: 0,
+                    max_clients: 0,
+                },
             },
             _ => return None,
         };
@@ -382,6 +449,7 @@ impl NetcodeClient {
             self.connect_token.protocol_id,
             Some((self.sequence, &self.connect_token.client_to_server_key)),
             self.encryption_policy,
+            self.version,
         );
         match result {
             Err(_) => None,
@@ -427,7 +495,7 @@ mod tests {
         let mut client = NetcodeClient::new(Duration::ZERO, authentication).unwrap();
         let (packet_buffer, _) = client.update(Duration::ZERO).unwrap();
 
-        let (r_sequence, packet) = Packet::decode(packet_buffer, protocol_id, None, None, true).unwrap();
+        let (r_sequence, packet) = Packet::decode(packet_buffer, protocol_id, None, None, true, NetcodeVersion::Renet2).unwrap();
         assert_eq!(0, r_sequence);
         assert!(matches!(packet, Packet::ConnectionRequest { .. }));
 
@@ -436,20 +504,20 @@ mod tests {
         let challenge_key = generate_random_bytes();
         let challenge_packet = Packet::generate_challenge(client_id, &user_data, challenge_sequence, &challenge_key).unwrap();
         let len = challenge_packet
-            .encode(&mut buffer, protocol_id, Some((0, &server_key)), true)
+            .encode(&mut buffer, protocol_id, Some((0, &server_key)), true, NetcodeVersion::Renet2)
             .unwrap();
         client.process_packet(&mut buffer[..len]);
         assert_eq!(ClientState::SendingConnectionResponse, client.state);
 
         let (packet_buffer, _) = client.update(Duration::ZERO).unwrap();
-        let (_, packet) = Packet::decode(packet_buffer, protocol_id, Some(&client_key), None, true).unwrap();
+        let (_, packet) = Packet::decode(packet_buffer, protocol_id, Some(&client_key), None, true, NetcodeVersion::Renet2).unwrap();
         assert!(matches!(packet, Packet::Response { .. }));
 
         let max_clients = 4;
         let client_index = 2;
         let keep_alive_packet = Packet::KeepAlive { max_clients, client_index };
         let len = keep_alive_packet
-            .encode(&mut buffer, protocol_id, Some((1, &server_key)), true)
+            .encode(&mut buffer, protocol_id, Some((1, &server_key)), true, NetcodeVersion::Renet2)
             .unwrap();
         client.process_packet(&mut buffer[..len]);
 
@@ -458,7 +526,7 @@ mod tests {
         let payload = vec![7u8; 500];
         let payload_packet = Packet::Payload(&payload[..]);
         let len = payload_packet
-            .encode(&mut buffer, protocol_id, Some((2, &server_key)), true)
+            .encode(&mut buffer, protocol_id, Some((2, &server_key)), true, NetcodeVersion::Renet2)
             .unwrap();
 
         let payload_client = client.process_packet(&mut buffer[..len]).unwrap();
@@ -466,10 +534,39 @@ mod tests {
 
         let to_send_payload = vec![5u8; 1000];
         let (_, packet) = client.generate_payload_packet(&to_send_payload).unwrap();
-        let (_, result) = Packet::decode(packet, protocol_id, Some(&client_key), None, true).unwrap();
+        let (_, result) = Packet::decode(packet, protocol_id, Some(&client_key), None, true, NetcodeVersion::Renet2).unwrap();
         match result {
             Packet::Payload(payload) => assert_eq!(to_send_payload, payload),
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn client_clock() {
+        let connect_token = ConnectToken::generate(
+            Duration::from_secs(1000),
+            2,
+            30,
+            4,
+            -1,
+            0,
+            vec!["127.0.0.1:8080".parse().unwrap()],
+            None,
+            b"an example very very secret key.",
+        )
+        .unwrap();
+        let clock = crate::ManualClock::new(Duration::from_secs(1000));
+        let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+        client.set_clock(clock.clone());
+
+        // The token expires at the clock time, while the current time is the sum of the updates
+        clock.advance(Duration::from_secs(29));
+        assert!(client.update(Duration::from_millis(16)).is_some());
+        assert_eq!(client.current_time(), Duration::from_millis(16));
+        assert!(!client.is_disconnected());
+
+        clock.advance(Duration::from_secs(1));
+        client.update(Duration::from_millis(16));
+        assert_eq!(client.disconnect_reason(), Some(DisconnectReason::ConnectTokenExpired));
+    }
 }
@@ -0,0 +1,98 @@
+#[cfg(not(target_family = "wasm"))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// A source of the time that a [`NetcodeServer`](crate::NetcodeServer) or a [`NetcodeClient`](crate::NetcodeClient)
+/// compares to the timestamps of connect tokens.
+///
+/// Like the timestamps, it should be the duration since the Unix epoch.
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Duration;
+}
+
+/// Reads the time from the system clock.
+///
+/// Follows adjustments to the system clock, such as NTP corrections.
+///
+/// Not available on wasm, where the std clocks panic. Implement [`Clock`] with a wasm time source instead.
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(not(target_family = "wasm"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+}
+
+/// Reads the time from a monotonic clock, started at the system time when it was made.
+///
+/// Unaffected by adjustments to the system clock, but can drift from it.
+///
+/// Not available on wasm, where the std clocks panic.
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    start_time: Duration,
+    start_instant: Instant,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            start_time: SystemClock.now(),
+            start_instant: Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.start_time + self.start_instant.elapsed()
+    }
+}
+
+/// A clock that is set manually, useful to simulate time in tests.
+///
+/// Clones share the same time.
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    time: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new(time: Duration) -> Self {
+        Self {
+            time: Arc::new(Mutex::new(time)),
+        }
+    }
+
+    pub fn set(&self, time: Duration) {
+        *self.time.lock().unwrap() = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.time.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.time.lock().unwrap()
+    }
+}
@@ -1,6 +1,6 @@
 use std::{error, fmt, io};
 
-use crate::{token::TokenGenerationError, DisconnectReason, NETCODE_MAX_PAYLOAD_BYTES};
+use crate::{token::TokenGenerationError, DisconnectReason, NETCODE_MAX_EXTENDED_USER_DATA_BYTES, NETCODE_MAX_PAYLOAD_BYTES};
 use chacha20poly1305::aead::Error as CryptoError;
 
 /// Errors from the renetcode crate.
@@ -20,6 +20,8 @@ pub enum NetcodeError {
     PacketTooSmall,
     /// Payload is above the maximum limit
     PayloadAboveLimit,
+    /// Sealed extended user data is above the maximum limit
+    ExtendedUserDataAboveLimit,
     /// The processed packet is duplicated
     DuplicatedSequence,
     /// No more host are available in the connect token..
@@ -54,6 +56,11 @@ impl fmt::Display for NetcodeError {
             InvalidVersion => write!(fmt, "invalid version info"),
             PacketTooSmall => write!(fmt, "packet is too small"),
             PayloadAboveLimit => write!(fmt, "payload is above the {} bytes limit", NETCODE_MAX_PAYLOAD_BYTES),
+            ExtendedUserDataAboveLimit => write!(
+                fmt,
+                "extended user data is above the {} bytes limit",
+                NETCODE_MAX_EXTENDED_USER_DATA_BYTES
+            ),
             Expired => write!(fmt, "connection expired"),
             DuplicatedSequence => write!(fmt, "sequence already received"),
             Disconnected(reason) => write!(fmt, "disconnected: {}", reason),
@@ -70,6 +77,29 @@ impl fmt::Display for NetcodeError {
 
 impl error::Error for NetcodeError {}
 
+/// Errors from an invalid [`ServerConfig`](crate::ServerConfig).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerConfigError {
+    /// The server has no sockets.
+    NoSockets,
+    /// The socket uses [`NetcodeVersion::Standard`](crate::NetcodeVersion::Standard) without encryption,
+    /// standard netcode packets are always encrypted.
+    UnencryptedStandardSocket { socket_id: usize },
+}
+
+impl fmt::Display for ServerConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use ServerConfigError::*;
+
+        match *self {
+            NoSockets => write!(fmt, "server has no sockets"),
+            UnencryptedStandardSocket { socket_id } => write!(fmt, "standard netcode socket {socket_id} must need encryption"),
+        }
+    }
+}
+
+impl error::Error for ServerConfigError {}
+
 impl From<io::Error> for NetcodeError {
     fn from(inner: io::Error) -> Self {
         NetcodeError::IoError(inner)
@@ -0,0 +1,233 @@
+use chacha20poly1305::aead::Error as CryptoError;
+
+use crate::{
+    crypto::{dencrypted_in_place_xnonce, encrypt_in_place_xnonce, generate_random_bytes},
+    packet::Packet,
+    TokenGenerationError, NETCODE_CONNECT_TOKEN_XNONCE_BYTES, NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_VERSION_INFO,
+};
+
+/// The maximum number of bytes of extended user data, delivered to the server after the handshake.
+pub const NETCODE_MAX_EXTENDED_USER_DATA_BYTES: usize = 4096;
+
+/// The number of bytes of sealed extended user data sent in each packet.
+const EXTENDED_USER_DATA_FRAGMENT_BYTES: usize = 1024;
+
+/// The maximum number of bytes of sealed extended user data: the xnonce, the encrypted data and its MAC.
+pub(crate) const MAX_SEALED_EXTENDED_USER_DATA_BYTES: usize =
+    NETCODE_CONNECT_TOKEN_XNONCE_BYTES + NETCODE_MAX_EXTENDED_USER_DATA_BYTES + NETCODE_MAC_BYTES;
+
+const EXTENDED_USER_DATA_ADDITIONAL_DATA_SIZE: usize = 13 + 8 + NETCODE_MAC_BYTES;
+
+/// Encrypts extended user data for the connect token with the MAC, so the server only accepts it from that token.
+pub(crate) fn seal_extended_user_data(
+    data: &[u8],
+    mac: &[u8; NETCODE_MAC_BYTES],
+    protocol_id: u64,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+) -> Result<Vec<u8>, TokenGenerationError> {
+    if data.len() > NETCODE_MAX_EXTENDED_USER_DATA_BYTES {
+        return Err(TokenGenerationError::ExtendedUserDataTooLarge);
+    }
+
+    let xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES] = generate_random_bytes();
+    let mut sealed = Vec::with_capacity(NETCODE_CONNECT_TOKEN_XNONCE_BYTES + data.len() + NETCODE_MAC_BYTES);
+    sealed.extend_from_slice(&xnonce);
+    sealed.extend_from_slice(data);
+    sealed.resize(sealed.capacity(), 0);
+
+    let aad = get_additional_data(mac, protocol_id);
+    encrypt_in_place_xnonce(&mut sealed[NETCODE_CONNECT_TOKEN_XNONCE_BYTES..], &xnonce, private_key, &aad)?;
+
+    Ok(sealed)
+}
+
+/// Decrypts extended user data sealed with [`seal_extended_user_data`].
+pub(crate) fn open_extended_user_data(
+    sealed: &[u8],
+    mac: &[u8; NETCODE_MAC_BYTES],
+    protocol_id: u64,
+    private_key: &[u8; NETCODE_KEY_BYTES],
+) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < NETCODE_CONNECT_TOKEN_XNONCE_BYTES + NETCODE_MAC_BYTES {
+        return Err(CryptoError);
+    }
+
+    let (xnonce, encrypted) = sealed.split_at(NETCODE_CONNECT_TOKEN_XNONCE_BYTES);
+    let xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES] = xnonce.try_into().unwrap();
+    let mut data = encrypted.to_vec();
+
+    let aad = get_additional_data(mac, protocol_id);
+    dencrypted_in_place_xnonce(&mut data, &xnonce, private_key, &aad)?;
+    data.truncate(data.len() - NETCODE_MAC_BYTES);
+
+    Ok(data)
+}
+
+fn get_additional_data(mac: &[u8; NETCODE_MAC_BYTES], protocol_id: u64) -> [u8; EXTENDED_USER_DATA_ADDITIONAL_DATA_SIZE] {
+    let mut buffer = [0; EXTENDED_USER_DATA_ADDITIONAL_DATA_SIZE];
+    buffer[..13].copy_from_slice(NETCODE_VERSION_INFO);
+    buffer[13..21].copy_from_slice(&protocol_id.to_le_bytes());
+    buffer[21..].copy_from_slice(mac);
+
+    buffer
+}
+
+/// Sends the fragments of sealed extended user data from the client until the server acknowledges them.
+#[derive(Debug)]
+pub(crate) struct ExtendedUserDataSender {
+    sealed: Vec<u8>,
+    next_offset: usize,
+    acked: bool,
+}
+
+impl ExtendedUserDataSender {
+    pub fn new(sealed: Vec<u8>) -> Self {
+        Self {
+            sealed,
+            next_offset: 0,
+            acked: false,
+        }
+    }
+
+    /// Returns true while sending the fragments of the current round, they are sent without waiting for the send rate.
+    pub fn is_sending(&self) -> bool {
+        !self.acked && self.next_offset < self.sealed.len()
+    }
+
+    pub fn is_acked(&self) -> bool {
+        self.acked
+    }
+
+    pub fn ack(&mut self) {
+        self.acked = true;
+    }
+
+    /// Returns the next fragment, starting a new round after the last one.
+    pub fn next_fragment(&mut self) -> Packet<'_> {
+        if self.next_offset >= self.sealed.len() {
+            self.next_offset = 0;
+        }
+
+        let offset = self.next_offset;
+        self.next_offset = (offset + EXTENDED_USER_DATA_FRAGMENT_BYTES).min(self.sealed.len());
+
+        Packet::ExtendedUserData {
+            total_len: self.sealed.len() as u16,
+            offset: offset as u16,
+            data: &self.sealed[offset..self.next_offset],
+        }
+    }
+}
+
+/// Reassembles the fragments of sealed extended user data in the server.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExtendedUserDataReceiver {
+    sealed: Vec<u8>,
+    received_fragments: u8,
+    data: Option<Vec<u8>>,
+}
+
+impl ExtendedUserDataReceiver {
+    /// Returns the opened extended user data, if received.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.sealed = Vec::new();
+        self.data = Some(data);
+    }
+
+    /// Adds a fragment of the sealed data, returns the sealed data once all of its fragments are received.
+    ///
+    /// Errors if the fragment doesn't match the previous fragments.
+    pub fn receive_fragment(&mut self, total_len: u16, offset: u16, fragment: &[u8]) -> Result<Option<Vec<u8>>, ()> {
+        if self.data.is_some() {
+            return Ok(None);
+        }
+
+        let (total_len, offset) = (total_len as usize, offset as usize);
+        if !(NETCODE_CONNECT_TOKEN_XNONCE_BYTES + NETCODE_MAC_BYTES..=MAX_SEALED_EXTENDED_USER_DATA_BYTES).contains(&total_len)
+            || offset % EXTENDED_USER_DATA_FRAGMENT_BYTES != 0
+            || offset + fragment.len() != (offset + EXTENDED_USER_DATA_FRAGMENT_BYTES).min(total_len)
+        {
+            return Err(());
+        }
+
+        if self.sealed.is_empty() {
+            self.sealed = vec![0; total_len];
+        } else if self.sealed.len() != total_len {
+            return Err(());
+        }
+
+        self.sealed[offset..offset + fragment.len()].copy_from_slice(fragment);
+        self.received_fragments |= 1 << (offset / EXTENDED_USER_DATA_FRAGMENT_BYTES);
+
+        let fragment_count = total_len.div_ceil(EXTENDED_USER_DATA_FRAGMENT_BYTES);
+        if self.received_fragments.count_ones() as usize == fragment_count {
+            return Ok(Some(std::mem::take(&mut self.sealed)));
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KEY: &[u8; NETCODE_KEY_BYTES] = b"an example very very secret key."; // 32-bytes
+
+    #[test]
+    fn seal_open() {
+        let mac = [3u8; NETCODE_MAC_BYTES];
+        let data: Vec<u8> = (0..3000).map(|i| i as u8).collect();
+        let sealed = seal_extended_user_data(&data, &mac, 7, TEST_KEY).unwrap();
+        assert_eq!(open_extended_user_data(&sealed, &mac, 7, TEST_KEY).unwrap(), data);
+
+        assert!(open_extended_user_data(&sealed, &[4u8; NETCODE_MAC_BYTES], 7, TEST_KEY).is_err());
+        assert!(open_extended_user_data(&sealed, &mac, 8, TEST_KEY).is_err());
+        assert!(matches!(
+            seal_extended_user_data(&[0; NETCODE_MAX_EXTENDED_USER_DATA_BYTES + 1], &mac, 7, TEST_KEY),
+            Err(TokenGenerationError::ExtendedUserDataTooLarge)
+        ));
+    }
+
+    #[test]
+    fn fragments() {
+        let sealed: Vec<u8> = (0..MAX_SEALED_EXTENDED_USER_DATA_BYTES).map(|i| i as u8).collect();
+        let mut sender = ExtendedUserDataSender::new(sealed.clone());
+        let mut receiver = ExtendedUserDataReceiver::default();
+
+        // Receive the fragments out of order, with a duplicate
+        let mut fragments = Vec::new();
+        while sender.is_sending() {
+            let Packet::ExtendedUserData { total_len, offset, data } = sender.next_fragment() else {
+                unreachable!()
+            };
+            fragments.push((total_len, offset, data.to_vec()));
+        }
+        assert_eq!(fragments.len(), 5);
+        fragments.swap(0, 4);
+        fragments.insert(2, fragments[0].clone());
+
+        let last = fragments.pop().unwrap();
+        for (total_len, offset, data) in fragments {
+            assert_eq!(receiver.receive_fragment(total_len, offset, &data), Ok(None));
+        }
+        assert_eq!(receiver.receive_fragment(last.0, last.1, &last.2), Ok(Some(sealed)));
+
+        // Fragments restart after a round until acknowledged
+        assert!(!sender.is_sending());
+        assert!(matches!(sender.next_fragment(), Packet::ExtendedUserData { offset: 0, .. }));
+        sender.ack();
+        assert!(!sender.is_sending());
+
+        let mut receiver = ExtendedUserDataReceiver::default();
+        assert_eq!(receiver.receive_fragment(2048, 0, &[0; 1000]), Err(()));
+        assert_eq!(receiver.receive_fragment(2048, 100, &[0; 1024]), Err(()));
+        assert_eq!(receiver.receive_fragment(5000, 0, &[0; 1024]), Err(()));
+        assert_eq!(receiver.receive_fragment(2048, 0, &[0; 1024]), Ok(None));
+        assert_eq!(receiver.receive_fragment(2000, 1024, &[0; 976]), Err(()));
+    }
+}
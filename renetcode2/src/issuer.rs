@@ -0,0 +1,254 @@
+use std::{net::SocketAddr, ops::Range, time::Duration};
+
+use crate::{
+    extended_user_data::seal_extended_user_data, ConnectToken, ServerKey, TokenGenerationError, NETCODE_KEY_BYTES, NETCODE_MAC_BYTES,
+    NETCODE_USER_DATA_BYTES,
+};
+
+/// Configuration of a [`TokenIssuer`].
+#[derive(Debug, Clone)]
+pub struct TokenIssuerConfig {
+    /// Protocol id of the server, see [`ServerConfig::protocol_id`](crate::ServerConfig::protocol_id).
+    pub protocol_id: u64,
+    /// Private key of the server, used to encrypt the connect tokens.
+    pub private_key: [u8; NETCODE_KEY_BYTES],
+    /// Id of the private key in [`ServerAuthentication::SecureKeys`](crate::ServerAuthentication::SecureKeys).
+    ///
+    /// When set, the tokens identify their key, so the server doesn't try to decrypt them with each key.
+    /// See [`ConnectToken::generate_for_key`].
+    pub key_id: Option<u64>,
+    /// Seconds before the connect tokens expire.
+    pub expire_seconds: u64,
+    /// Seconds without packets before a connection times out, negative values disable the timeout.
+    pub timeout_seconds: i32,
+    /// Public addresses of each server socket, indexed by socket id.
+    pub socket_addresses: Vec<Vec<SocketAddr>>,
+    /// Client ids allocated by the issuer, in order.
+    ///
+    /// Client ids given to [`TokenIssuer::issue_for_client`] must be outside of this range.
+    pub client_ids: Range<u64>,
+}
+
+/// A connect token made by a [`TokenIssuer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedToken {
+    pub client_id: u64,
+    /// Server time at which the token expires, in seconds.
+    pub expire_timestamp: u64,
+    /// Identifies the token to revoke it, see [`ConnectToken::mac`].
+    pub mac: [u8; NETCODE_MAC_BYTES],
+    /// The token written with [`ConnectToken::write`].
+    pub token: Vec<u8>,
+}
+
+/// Makes serialized connect tokens for a server, from a single configuration.
+///
+/// Useful for matchmaking services, that give connect tokens to clients before they connect to a server.
+///
+/// The next allocated client id is only kept in memory. An issuer made again with the same config,
+/// for example after a restart, allocates the same client ids again, so give it a new range instead.
+#[derive(Debug)]
+pub struct TokenIssuer {
+    config: TokenIssuerConfig,
+    next_client_id: u64,
+}
+
+impl TokenIssuer {
+    /// Makes a new issuer.
+    ///
+    /// Panics if the range of allocated client ids is empty.
+    pub fn new(config: TokenIssuerConfig) -> Self {
+        if config.client_ids.is_empty() {
+            panic!("Cannot make a token issuer with no client ids.");
+        }
+
+        Self {
+            next_client_id: config.client_ids.start,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &TokenIssuerConfig {
+        &self.config
+    }
+
+    /// Returns whether the client id is allocated by the issuer, instead of given to [`TokenIssuer::issue_for_client`].
+    pub fn is_allocated_client_id(&self, client_id: u64) -> bool {
+        self.config.client_ids.contains(&client_id)
+    }
+
+    /// Issues a token for a new client id, allocated from [`TokenIssuerConfig::client_ids`].
+    ///
+    /// The server gets the user data from [`NetcodeServer::user_data`](crate::NetcodeServer::user_data),
+    /// typed values can be written in it with `encode_user_data` (requires the `serde` feature).
+    ///
+    /// Fails with [`TokenGenerationError::ClientIdsExhausted`] once every client id of the range was allocated,
+    /// client ids are never reused.
+    pub fn issue(
+        &mut self,
+        current_time: Duration,
+        socket_id: u8,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+    ) -> Result<IssuedToken, TokenGenerationError> {
+        let client_id = self.next_client_id;
+        if client_id >= self.config.client_ids.end {
+            return Err(TokenGenerationError::ClientIdsExhausted);
+        }
+
+        let issued = self.generate(current_time, socket_id, client_id, user_data)?;
+        self.next_client_id += 1;
+
+        Ok(issued)
+    }
+
+    /// Issues a token for a client id chosen by the caller, such as an account id.
+    ///
+    /// Fails if the client id is in the range of allocated client ids.
+    pub fn issue_for_client(
+        &self,
+        current_time: Duration,
+        socket_id: u8,
+        client_id: u64,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+    ) -> Result<IssuedToken, TokenGenerationError> {
+        if self.is_allocated_client_id(client_id) {
+            return Err(TokenGenerationError::ReservedClientId);
+        }
+
+        self.generate(current_time, socket_id, client_id, user_data)
+    }
+
+    /// Encrypts extended user data for an issued token, see [`ConnectToken::seal_extended_user_data`].
+    pub fn seal_extended_user_data(&self, issued: &IssuedToken, data: &[u8]) -> Result<Vec<u8>, TokenGenerationError> {
+        seal_extended_user_data(data, &issued.mac, self.config.protocol_id, &self.config.private_key)
+    }
+
+    fn generate(
+        &self,
+        current_time: Duration,
+        socket_id: u8,
+        client_id: u64,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+    ) -> Result<IssuedToken, TokenGenerationError> {
+        let Some(server_addresses) = self.config.socket_addresses.get(socket_id as usize) else {
+            return Err(TokenGenerationError::InvalidSocketId);
+        };
+
+        let connect_token = match self.config.key_id {
+            Some(key_id) => ConnectToken::generate_for_key(
+                current_time,
+                self.config.protocol_id,
+                self.config.expire_seconds,
+                client_id,
+                self.config.timeout_seconds,
+                socket_id,
+                server_addresses.clone(),
+                user_data,
+                &ServerKey::new(key_id, self.config.private_key),
+            )?,
+            None => ConnectToken::generate(
+                current_time,
+                self.config.protocol_id,
+                self.config.expire_seconds,
+                client_id,
+                self.config.timeout_seconds,
+                socket_id,
+                server_addresses.clone(),
+                user_data,
+                &self.config.private_key,
+            )?,
+        };
+
+        let mut token = Vec::new();
+        connect_token.write(&mut token)?;
+
+        Ok(IssuedToken {
+            client_id,
+            expire_timestamp: connect_token.expire_timestamp,
+            mac: connect_token.mac(),
+            token,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NetcodeServer, ServerAuthentication, ServerConfig, ServerResult, ServerSocketConfig};
+
+    const TEST_KEY: &[u8; NETCODE_KEY_BYTES] = b"an example very very secret key."; // 32-bytes
+
+    fn new_issuer() -> TokenIssuer {
+        TokenIssuer::new(TokenIssuerConfig {
+            protocol_id: 7,
+            private_key: *TEST_KEY,
+            key_id: None,
+            expire_seconds: 30,
+            timeout_seconds: 5,
+            socket_addresses: vec![vec!["127.0.0.1:5000".parse().unwrap()]],
+            client_ids: 1000..1002,
+        })
+    }
+
+    #[test]
+    fn client_ids() {
+        let mut issuer = new_issuer();
+        assert_eq!(issuer.issue(Duration::ZERO, 0, None).unwrap().client_id, 1000);
+
+        assert!(matches!(
+            issuer.issue_for_client(Duration::ZERO, 0, 1001, None),
+            Err(TokenGenerationError::ReservedClientId)
+        ));
+        assert_eq!(issuer.issue_for_client(Duration::ZERO, 0, 5, None).unwrap().client_id, 5);
+        assert!(matches!(
+            issuer.issue(Duration::ZERO, 1, None),
+            Err(TokenGenerationError::InvalidSocketId)
+        ));
+
+        // Failed tokens don't use a client id, and client ids are not reused
+        assert_eq!(issuer.issue(Duration::ZERO, 0, None).unwrap().client_id, 1001);
+        assert!(matches!(
+            issuer.issue(Duration::ZERO, 0, None),
+            Err(TokenGenerationError::ClientIdsExhausted)
+        ));
+    }
+
+    #[test]
+    fn issued_token_connects() {
+        let mut issuer = new_issuer();
+        let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+        user_data[..10].copy_from_slice(b"player one");
+        let issued = issuer.issue(Duration::from_secs(10), 0, Some(&user_data)).unwrap();
+        assert_eq!(issued.expire_timestamp, 40);
+
+        let connect_token = ConnectToken::read(&mut issued.token.as_slice()).unwrap();
+        assert_eq!(connect_token.client_id, issued.client_id);
+        assert_eq!(connect_token.mac(), issued.mac);
+
+        let mut server = NetcodeServer::new(ServerConfig {
+            current_time: Duration::from_secs(10),
+            max_clients: 4,
+            max_pending_clients: 16,
+            protocol_id: 7,
+            sockets: vec![ServerSocketConfig::new(issuer.config().socket_addresses[0].clone())],
+            authentication: ServerAuthentication::Secure { private_key: *TEST_KEY },
+        });
+        let mut client = crate::NetcodeClient::new(Duration::from_secs(10), crate::ClientAuthentication::Secure { connect_token }).unwrap();
+        let client_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        match server.process_packet(0, client_addr, client_packet) {
+            ServerResult::ConnectionAccepted { payload, .. } => client.process_packet(payload),
+            _ => unreachable!(),
+        };
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        match server.process_packet(0, client_addr, client_packet) {
+            ServerResult::ClientConnected { client_id, user_data, .. } => {
+                assert_eq!(client_id, issued.client_id);
+                assert_eq!(&user_data[..10], b"player one");
+                assert!(user_data[10..].iter().all(|byte| *byte == 0));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 //! Renetcode is a simple connection based client/server protocol agnostic to the transport layer,
 //! was developed be used in games with UDP in mind. Implements the Netcode 1.a2 standard with `renet2`
-//! extensions. The standard is available [here][standard] and the original implementation in C++ is
-//! available in the [netcode][netcode] repository. The extensions are available in `NETCODE_EXTENSIONS.md`.
+//! extensions, and the standard Netcode 1.02 with [`NetcodeVersion::Standard`]. The standard is available
+//! [here][standard] and the original implementation in C++ is available in the [netcode][netcode] repository.
+//! The extensions are available in `NETCODE_EXTENSIONS.md`.
 //!
 //! Has the following feature:
 //! - Encrypted and signed packets
@@ -17,33 +18,52 @@
 //! [standard]: https://github.com/networkprotocol/netcode/blob/master/STANDARD.md
 //! [netcode]: https://github.com/networkprotocol/netcode
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
+mod ban;
 mod client;
+mod clock;
 mod crypto;
 mod error;
+mod extended_user_data;
+mod issuer;
 mod packet;
+mod rate_limit;
 mod replay_protection;
 mod serialize;
 mod server;
 mod token;
+#[cfg(feature = "serde")]
+mod user_data;
+mod version;
 
+pub use ban::IpRange;
 pub use client::{ClientAuthentication, DisconnectReason, NetcodeClient};
+pub use clock::{Clock, ManualClock};
+#[cfg(not(target_family = "wasm"))]
+pub use clock::{MonotonicClock, SystemClock};
 pub use crypto::generate_random_bytes;
-pub use error::NetcodeError;
-pub use packet::{Packet, PacketType};
-pub use server::{NetcodeServer, ServerAuthentication, ServerConfig, ServerResult, ServerSocketConfig};
+pub use error::{NetcodeError, ServerConfigError};
+pub use extended_user_data::NETCODE_MAX_EXTENDED_USER_DATA_BYTES;
+pub use issuer::{IssuedToken, TokenIssuer, TokenIssuerConfig};
+pub use packet::{ConnectionDeniedReason, Packet, PacketType};
+pub use rate_limit::ConnectionRequestLimit;
+pub use server::{NetcodeServer, ServerAuthentication, ServerConfig, ServerKey, ServerResult, ServerSocketConfig};
 pub use token::{ConnectToken, TokenGenerationError};
+#[cfg(feature = "serde")]
+pub use user_data::{decode_user_data, encode_user_data, UserDataError, NETCODE_MAX_ENCODED_USER_DATA_BYTES};
+pub use version::NetcodeVersion;
 
 use std::time::Duration;
 
 const NETCODE_VERSION_INFO: &[u8; 13] = b"NETCODE 1.a2\0"; //Netcode v1.02 with renet2 extensions (version 'a')
-const NETCODE_MAX_CLIENTS: usize = 1024;
-const NETCODE_MAX_PENDING_CLIENTS: usize = NETCODE_MAX_CLIENTS * 4;
+const NETCODE_STANDARD_VERSION_INFO: &[u8; 13] = b"NETCODE 1.02\0";
 
 const NETCODE_ADDRESS_NONE: u8 = 0;
 const NETCODE_ADDRESS_IPV4: u8 = 1;
 const NETCODE_ADDRESS_IPV6: u8 = 2;
 
 const NETCODE_CONNECT_TOKEN_PRIVATE_BYTES: usize = 1024;
+/// The number of bytes of a serialized standard netcode connect token.
+pub const NETCODE_CONNECT_TOKEN_BYTES: usize = 2048;
 /// The maximum number of bytes that a netcode packet can contain.
 pub const NETCODE_MAX_PACKET_BYTES: usize = 1400;
 /// The maximum number of bytes that a payload can have when generating a payload packet.
@@ -51,7 +71,8 @@ pub const NETCODE_MAX_PAYLOAD_BYTES: usize = 1300;
 
 /// The number of bytes in a private key;
 pub const NETCODE_KEY_BYTES: usize = 32;
-const NETCODE_MAC_BYTES: usize = 16;
+/// The number of bytes in a MAC, such as the one identifying a [`ConnectToken`].
+pub const NETCODE_MAC_BYTES: usize = 16;
 /// The number of bytes that an user data can contain in the ConnectToken.
 pub const NETCODE_USER_DATA_BYTES: usize = 256;
 const NETCODE_CHALLENGE_TOKEN_BYTES: usize = 300;
@@ -1,4 +1,7 @@
-use std::io::{self, Cursor, Write};
+use std::{
+    fmt,
+    io::{self, Cursor, Write},
+};
 
 use crate::crypto::{decode_and_check_buffer, dencrypted_in_place, encode_in_place, encrypt_in_place};
 use crate::replay_protection::ReplayProtection;
@@ -7,7 +10,7 @@ use crate::{
     serialize::*, NetcodeError, NETCODE_CHALLENGE_TOKEN_BYTES, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES,
     NETCODE_KEY_BYTES, NETCODE_MAC_BYTES,
 };
-use crate::{NETCODE_USER_DATA_BYTES, NETCODE_VERSION_INFO};
+use crate::{NetcodeVersion, NETCODE_USER_DATA_BYTES};
 
 #[derive(Debug, Eq, PartialEq)]
 #[repr(u8)]
@@ -19,6 +22,75 @@ pub enum PacketType {
     KeepAlive = 4,
     Payload = 5,
     Disconnect = 6,
+    ExtendedUserData = 7,
+    ExtendedUserDataAck = 8,
+}
+
+/// The reason sent by the server when denying a connection.
+///
+/// This is a `renet2` extension to netcode, see `NETCODE_EXTENSIONS.md`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionDeniedReason {
+    /// The server did not send a reason, or sent one that is unknown to this version.
+    Unspecified,
+    /// The server reached its maximum number of clients.
+    ServerFull,
+    /// The connect token is already in use by a different address.
+    ConnectTokenInUse,
+    /// The client id is banned from the server.
+    ClientBanned,
+    /// The connect token was revoked by the server.
+    ConnectTokenRevoked,
+    /// The client id is used by a client connected from a different address.
+    ClientIdInUse,
+    /// The server reached its maximum number of clients in the middle of connecting.
+    TooManyPendingClients,
+}
+
+impl ConnectionDeniedReason {
+    fn from_u8(value: u8) -> Self {
+        use ConnectionDeniedReason::*;
+
+        match value {
+            1 => ServerFull,
+            2 => ConnectTokenInUse,
+            3 => ClientBanned,
+            4 => ConnectTokenRevoked,
+            5 => ClientIdInUse,
+            6 => TooManyPendingClients,
+            _ => Unspecified,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        use ConnectionDeniedReason::*;
+
+        match self {
+            Unspecified => 0,
+            ServerFull => 1,
+            ConnectTokenInUse => 2,
+            ClientBanned => 3,
+            ConnectTokenRevoked => 4,
+            ClientIdInUse => 5,
+            TooManyPendingClients => 6,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionDeniedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ConnectionDeniedReason::*;
+
+        match *self {
+            Unspecified => write!(f, "unspecified"),
+            ServerFull => write!(f, "server is full"),
+            ConnectTokenInUse => write!(f, "connect token in use by another address"),
+            ClientBanned => write!(f, "client is banned"),
+            ConnectTokenRevoked => write!(f, "connect token was revoked"),
+            ClientIdInUse => write!(f, "client id in use by another address"),
+            TooManyPendingClients => write!(f, "too many clients are connecting"),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -31,7 +103,9 @@ pub enum Packet<'a> {
         xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES],
         data: [u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES],
     },
-    ConnectionDenied,
+    ConnectionDenied {
+        reason: ConnectionDeniedReason,
+    },
     Challenge {
         token_sequence: u64,
         token_data: [u8; NETCODE_CHALLENGE_TOKEN_BYTES], // encrypted ChallengeToken
@@ -46,6 +120,13 @@ pub enum Packet<'a> {
     },
     Payload(&'a [u8]),
     Disconnect,
+    /// A fragment of sealed extended user data, see `NETCODE_EXTENSIONS.md`.
+    ExtendedUserData {
+        total_len: u16,
+        offset: u16,
+        data: &'a [u8],
+    },
+    ExtendedUserDataAck,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -66,6 +147,8 @@ impl PacketType {
             4 => KeepAlive,
             5 => Payload,
             6 => Disconnect,
+            7 => ExtendedUserData,
+            8 => ExtendedUserDataAck,
             _ => return Err(NetcodeError::InvalidPacketType),
         };
         Ok(packet_type)
@@ -74,7 +157,7 @@ impl PacketType {
     fn apply_replay_protection(&self) -> bool {
         use PacketType::*;
 
-        matches!(self, KeepAlive | Payload | Disconnect)
+        matches!(self, KeepAlive | Payload | Disconnect | ExtendedUserData | ExtendedUserDataAck)
     }
 }
 
@@ -82,12 +165,14 @@ impl<'a> Packet<'a> {
     pub fn packet_type(&self) -> PacketType {
         match self {
             Packet::ConnectionRequest { .. } => PacketType::ConnectionRequest,
-            Packet::ConnectionDenied => PacketType::ConnectionDenied,
+            Packet::ConnectionDenied { .. } => PacketType::ConnectionDenied,
             Packet::Challenge { .. } => PacketType::Challenge,
             Packet::Response { .. } => PacketType::Response,
             Packet::KeepAlive { .. } => PacketType::KeepAlive,
             Packet::Payload { .. } => PacketType::Payload,
             Packet::Disconnect => PacketType::Disconnect,
+            Packet::ExtendedUserData { .. } => PacketType::ExtendedUserData,
+            Packet::ExtendedUserDataAck => PacketType::ExtendedUserDataAck,
         }
     }
 
@@ -98,7 +183,7 @@ impl<'a> Packet<'a> {
     pub fn connection_request_from_token(connect_token: &ConnectToken) -> Self {
         Packet::ConnectionRequest {
             xnonce: connect_token.xnonce,
-            version_info: *NETCODE_VERSION_INFO,
+            version_info: connect_token.version_info,
             protocol_id: connect_token.protocol_id,
             expire_timestamp: connect_token.expire_timestamp,
             data: connect_token.private_data,
@@ -122,7 +207,7 @@ impl<'a> Packet<'a> {
         })
     }
 
-    fn write(&self, writer: &mut impl io::Write) -> Result<(), io::Error> {
+    fn write(&self, writer: &mut impl io::Write, version: NetcodeVersion) -> Result<(), io::Error> {
         match self {
             Packet::ConnectionRequest {
                 version_info,
@@ -155,7 +240,18 @@ impl<'a> Packet<'a> {
             Packet::Payload(p) => {
                 writer.write_all(p)?;
             }
-            Packet::ConnectionDenied | Packet::Disconnect => {}
+            // Standard netcode clients drop denied packets with data
+            Packet::ConnectionDenied { reason } => {
+                if version == NetcodeVersion::Renet2 {
+                    writer.write_all(&[reason.to_u8()])?;
+                }
+            }
+            Packet::ExtendedUserData { total_len, offset, data } => {
+                writer.write_all(&total_len.to_le_bytes())?;
+                writer.write_all(&offset.to_le_bytes())?;
+                writer.write_all(data)?;
+            }
+            Packet::Disconnect | Packet::ExtendedUserDataAck => {}
         }
 
         Ok(())
@@ -166,6 +262,15 @@ impl<'a> Packet<'a> {
             return Ok(Packet::Payload(src));
         }
 
+        if matches!(packet_type, PacketType::ExtendedUserData) {
+            let mut header = Cursor::new(src);
+            let total_len = read_u16(&mut header)?;
+            let offset = read_u16(&mut header)?;
+            let data = &src[header.position() as usize..];
+
+            return Ok(Packet::ExtendedUserData { total_len, offset, data });
+        }
+
         let src = &mut Cursor::new(src);
 
         match packet_type {
@@ -208,9 +313,15 @@ impl<'a> Packet<'a> {
 
                 Ok(Packet::KeepAlive { client_index, max_clients })
             }
-            PacketType::ConnectionDenied => Ok(Packet::ConnectionDenied),
+            PacketType::ConnectionDenied => {
+                // Denied packets without a reason are sent by standard netcode servers
+                let reason = read_u8(src).map_or(ConnectionDeniedReason::Unspecified, ConnectionDeniedReason::from_u8);
+
+                Ok(Packet::ConnectionDenied { reason })
+            }
             PacketType::Disconnect => Ok(Packet::Disconnect),
-            PacketType::Payload => unreachable!(),
+            PacketType::ExtendedUserDataAck => Ok(Packet::ExtendedUserDataAck),
+            PacketType::Payload | PacketType::ExtendedUserData => unreachable!(),
         }
     }
 
@@ -220,28 +331,34 @@ impl<'a> Packet<'a> {
         protocol_id: u64,
         crypto_info: Option<(u64, &[u8; 32])>,
         encrypted: bool,
+        version: NetcodeVersion,
     ) -> Result<usize, NetcodeError> {
         if matches!(self, Packet::ConnectionRequest { .. }) {
             let mut writer = io::Cursor::new(buffer);
             let prefix_byte = encode_prefix(self.id(), 0);
             writer.write_all(&prefix_byte.to_le_bytes())?;
 
-            self.write(&mut writer)?;
+            self.write(&mut writer, version)?;
             Ok(writer.position() as usize)
         } else if let Some((sequence, private_key)) = crypto_info {
             let (start, end, aad) = {
                 let mut writer = io::Cursor::new(&mut *buffer);
                 let prefix_byte = {
-                    let prefix_byte = encode_prefix(self.id(), sequence);
+                    // Standard netcode always writes at least one sequence byte
+                    let sequence_len = match version {
+                        NetcodeVersion::Renet2 => sequence_bytes_required(sequence),
+                        NetcodeVersion::Standard => sequence_bytes_required(sequence).max(1),
+                    };
+                    let prefix_byte = encode_prefix(self.id(), sequence_len);
                     writer.write_all(&prefix_byte.to_le_bytes())?;
-                    write_sequence(&mut writer, sequence)?;
+                    write_sequence(&mut writer, sequence, sequence_len)?;
                     prefix_byte
                 };
 
                 let start = writer.position() as usize;
-                self.write(&mut writer)?;
+                self.write(&mut writer, version)?;
 
-                let additional_data = get_additional_data(prefix_byte, protocol_id);
+                let additional_data = get_additional_data(prefix_byte, protocol_id, version);
                 (start, writer.position() as usize, additional_data)
             };
 
@@ -269,6 +386,7 @@ impl<'a> Packet<'a> {
         private_key: Option<&[u8; 32]>,
         replay_protection: Option<&mut ReplayProtection>,
         encrypted: bool,
+        version: NetcodeVersion,
     ) -> Result<(u64, Self), NetcodeError> {
         if buffer.len() < 2 + NETCODE_MAC_BYTES {
             return Err(NetcodeError::PacketTooSmall);
@@ -277,6 +395,10 @@ impl<'a> Packet<'a> {
         let prefix_byte = buffer[0];
         let (packet_type, sequence_len) = decode_prefix(prefix_byte);
         let packet_type = PacketType::from_u8(packet_type)?;
+        if version == NetcodeVersion::Standard && matches!(packet_type, PacketType::ExtendedUserData | PacketType::ExtendedUserDataAck) {
+            // Extended user data is a renet2 extension
+            return Err(NetcodeError::InvalidPacketType);
+        }
 
         if matches!(packet_type, PacketType::ConnectionRequest) {
             Ok((0, Packet::read(PacketType::ConnectionRequest, &buffer[1..])?))
@@ -285,7 +407,7 @@ impl<'a> Packet<'a> {
                 let src = &mut io::Cursor::new(&mut buffer);
                 src.set_position(1);
                 let sequence = read_sequence(src, sequence_len)?;
-                let additional_data = get_additional_data(prefix_byte, protocol_id);
+                let additional_data = get_additional_data(prefix_byte, protocol_id, version);
                 (sequence, additional_data, src.position() as usize)
             };
 
@@ -360,9 +482,9 @@ impl ChallengeToken {
     }
 }
 
-fn get_additional_data(prefix: u8, protocol_id: u64) -> [u8; 13 + 8 + 1] {
+fn get_additional_data(prefix: u8, protocol_id: u64, version: NetcodeVersion) -> [u8; 13 + 8 + 1] {
     let mut buffer = [0; 13 + 8 + 1];
-    buffer[..13].copy_from_slice(NETCODE_VERSION_INFO);
+    buffer[..13].copy_from_slice(version.version_info());
     buffer[13..21].copy_from_slice(&protocol_id.to_le_bytes());
     buffer[21] = prefix;
 
@@ -373,8 +495,8 @@ fn decode_prefix(value: u8) -> (u8, usize) {
     ((value & 0xF), (value >> 4) as usize)
 }
 
-fn encode_prefix(value: u8, sequence: u64) -> u8 {
-    value | ((sequence_bytes_required(sequence) as u8) << 4)
+fn encode_prefix(value: u8, sequence_len: usize) -> u8 {
+    value | ((sequence_len as u8) << 4)
 }
 
 fn sequence_bytes_required(sequence: u64) -> usize {
@@ -390,13 +512,15 @@ fn sequence_bytes_required(sequence: u64) -> usize {
     0
 }
 
-fn write_sequence(out: &mut impl io::Write, seq: u64) -> Result<usize, io::Error> {
-    let len = sequence_bytes_required(seq);
+fn write_sequence(out: &mut impl io::Write, seq: u64, len: usize) -> Result<usize, io::Error> {
     let sequence_scratch = seq.to_le_bytes();
     out.write(&sequence_scratch[..len])
 }
 
 fn read_sequence(source: &mut impl io::Read, len: usize) -> Result<u64, io::Error> {
+    if len > 8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "sequence is longer than 8 bytes"));
+    }
     let mut seq_scratch = [0; 8];
     source.read_exact(&mut seq_scratch[0..len])?;
     Ok(u64::from_le_bytes(seq_scratch))
@@ -418,7 +542,7 @@ mod tests {
             data: [5; 1024],
         };
         let mut buffer = Vec::new();
-        connection_request.write(&mut buffer).unwrap();
+        connection_request.write(&mut buffer, NetcodeVersion::Renet2).unwrap();
         let deserialized = Packet::read(PacketType::ConnectionRequest, &buffer).unwrap();
 
         assert_eq!(deserialized, connection_request);
@@ -432,7 +556,7 @@ mod tests {
         };
 
         let mut buffer = Vec::new();
-        connection_challenge.write(&mut buffer).unwrap();
+        connection_challenge.write(&mut buffer, NetcodeVersion::Renet2).unwrap();
         let deserialized = Packet::read(PacketType::Challenge, buffer.as_slice()).unwrap();
 
         assert_eq!(deserialized, connection_challenge);
@@ -446,21 +570,51 @@ mod tests {
         };
 
         let mut buffer = Vec::new();
-        connection_keep_alive.write(&mut buffer).unwrap();
+        connection_keep_alive.write(&mut buffer, NetcodeVersion::Renet2).unwrap();
         let deserialized = Packet::read(PacketType::KeepAlive, buffer.as_slice()).unwrap();
 
         assert_eq!(deserialized, connection_keep_alive);
     }
 
+    #[test]
+    fn extended_user_data_serialization() {
+        let data = [7u8; 100];
+        let extended_user_data = Packet::ExtendedUserData {
+            total_len: 1124,
+            offset: 1024,
+            data: &data,
+        };
+
+        let mut buffer = Vec::new();
+        extended_user_data.write(&mut buffer, NetcodeVersion::Renet2).unwrap();
+        let deserialized = Packet::read(PacketType::ExtendedUserData, buffer.as_slice()).unwrap();
+
+        assert_eq!(deserialized, extended_user_data);
+
+        // Standard netcode doesn't have extended user data
+        let key = b"an example very very secret key."; // 32-bytes
+        for packet in [extended_user_data, Packet::ExtendedUserDataAck] {
+            let mut buffer = [0u8; NETCODE_MAX_PACKET_BYTES];
+            let len = packet
+                .encode(&mut buffer, 12, Some((1, key)), true, NetcodeVersion::Standard)
+                .unwrap();
+            assert!(matches!(
+                Packet::decode(&mut buffer[..len], 12, Some(key), None, true, NetcodeVersion::Standard),
+                Err(NetcodeError::InvalidPacketType)
+            ));
+        }
+    }
+
     #[test]
     fn prefix_sequence() {
         let packet_type = Packet::Disconnect.id();
         let sequence = 99999;
 
         let mut buffer = vec![];
-        write_sequence(&mut buffer, sequence).unwrap();
+        let sequence_len = sequence_bytes_required(sequence);
+        write_sequence(&mut buffer, sequence, sequence_len).unwrap();
 
-        let prefix = encode_prefix(packet_type, sequence);
+        let prefix = encode_prefix(packet_type, sequence_len);
         let (d_packet_type, sequence_len) = decode_prefix(prefix);
         assert_eq!(packet_type, d_packet_type);
         assert_eq!(buffer.len(), sequence_len);
@@ -477,8 +631,11 @@ mod tests {
         let packet = Packet::Disconnect;
         let protocol_id = 12;
         let sequence = 1;
-        let len = packet.encode(&mut buffer, protocol_id, Some((sequence, key)), false).unwrap();
-        let (d_sequence, d_packet) = Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, false).unwrap();
+        let len = packet
+            .encode(&mut buffer, protocol_id, Some((sequence, key)), false, NetcodeVersion::Renet2)
+            .unwrap();
+        let (d_sequence, d_packet) =
+            Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, false, NetcodeVersion::Renet2).unwrap();
         assert_eq!(sequence, d_sequence);
         assert_eq!(packet, d_packet);
     }
@@ -490,8 +647,11 @@ mod tests {
         let packet = Packet::Disconnect;
         let protocol_id = 12;
         let sequence = 1;
-        let len = packet.encode(&mut buffer, protocol_id, Some((sequence, key)), true).unwrap();
-        let (d_sequence, d_packet) = Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true).unwrap();
+        let len = packet
+            .encode(&mut buffer, protocol_id, Some((sequence, key)), true, NetcodeVersion::Renet2)
+            .unwrap();
+        let (d_sequence, d_packet) =
+            Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true, NetcodeVersion::Renet2).unwrap();
         assert_eq!(sequence, d_sequence);
         assert_eq!(packet, d_packet);
     }
@@ -500,13 +660,27 @@ mod tests {
     fn encrypt_decrypt_denied_packet() {
         let mut buffer = [0u8; NETCODE_MAX_PACKET_BYTES];
         let key = b"an example very very secret key."; // 32-bytes
-        let packet = Packet::ConnectionDenied;
+        let packet = Packet::ConnectionDenied {
+            reason: ConnectionDeniedReason::ClientBanned,
+        };
         let protocol_id = 12;
         let sequence = 2;
-        let len = packet.encode(&mut buffer, protocol_id, Some((sequence, key)), true).unwrap();
-        let (d_sequence, d_packet) = Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true).unwrap();
+        let len = packet
+            .encode(&mut buffer, protocol_id, Some((sequence, key)), true, NetcodeVersion::Renet2)
+            .unwrap();
+        let (d_sequence, d_packet) =
+            Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true, NetcodeVersion::Renet2).unwrap();
         assert_eq!(sequence, d_sequence);
         assert_eq!(packet, d_packet);
+
+        // Denied packets from standard netcode servers have no reason
+        let d_packet = Packet::read(PacketType::ConnectionDenied, &[]).unwrap();
+        assert_eq!(
+            d_packet,
+            Packet::ConnectionDenied {
+                reason: ConnectionDeniedReason::Unspecified
+            }
+        );
     }
 
     #[test]
@@ -517,8 +691,11 @@ mod tests {
         let packet = Packet::Payload(&payload);
         let protocol_id = 12;
         let sequence = 2;
-        let len = packet.encode(&mut buffer, protocol_id, Some((sequence, key)), true).unwrap();
-        let (d_sequence, d_packet) = Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true).unwrap();
+        let len = packet
+            .encode(&mut buffer, protocol_id, Some((sequence, key)), true, NetcodeVersion::Renet2)
+            .unwrap();
+        let (d_sequence, d_packet) =
+            Packet::decode(&mut buffer[..len], protocol_id, Some(key), None, true, NetcodeVersion::Renet2).unwrap();
         assert_eq!(sequence, d_sequence);
         match d_packet {
             Packet::Payload(ref p) => assert_eq!(&payload, p),
@@ -547,4 +724,128 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn standard_packet_vectors() {
+        // Inputs of test_vectors/generate.py, the keys are read from the connect token
+        let token = ConnectToken::read(&mut include_bytes!("../test_vectors/connect_token.bin").as_slice()).unwrap();
+        let protocol_id = token.protocol_id;
+        let client_id = 0x0123456789ABCDEF;
+        let user_data: [u8; NETCODE_USER_DATA_BYTES] = std::array::from_fn(|i| (7 + i * 3) as u8);
+        let challenge_key: [u8; NETCODE_KEY_BYTES] = std::array::from_fn(|i| 128 + i as u8);
+        let challenge_sequence = 9;
+        let server_key = token.server_to_client_key;
+        let client_key = token.client_to_server_key;
+
+        let Packet::Challenge {
+            token_sequence,
+            token_data,
+        } = Packet::generate_challenge(client_id, &user_data, challenge_sequence, &challenge_key).unwrap()
+        else {
+            unreachable!()
+        };
+        let response = Packet::Response {
+            token_sequence,
+            token_data,
+        };
+
+        let vectors: [(Packet, _, &[u8]); 8] = [
+            (
+                Packet::connection_request_from_token(&token),
+                None,
+                include_bytes!("../test_vectors/connection_request.bin"),
+            ),
+            (
+                Packet::ConnectionDenied {
+                    reason: ConnectionDeniedReason::Unspecified,
+                },
+                Some((0, &server_key)),
+                include_bytes!("../test_vectors/connection_denied.bin"),
+            ),
+            (
+                Packet::Challenge {
+                    token_sequence,
+                    token_data,
+                },
+                Some((1, &server_key)),
+                include_bytes!("../test_vectors/challenge.bin"),
+            ),
+            (response, Some((0, &client_key)), include_bytes!("../test_vectors/response.bin")),
+            (
+                Packet::KeepAlive {
+                    client_index: 3,
+                    max_clients: 64,
+                },
+                Some((2, &server_key)),
+                include_bytes!("../test_vectors/keep_alive.bin"),
+            ),
+            (
+                Packet::Payload(b"hello netcode"),
+                Some((0x1234, &client_key)),
+                include_bytes!("../test_vectors/payload.bin"),
+            ),
+            (
+                Packet::Disconnect,
+                Some((0x0102030405060708, &client_key)),
+                include_bytes!("../test_vectors/disconnect.bin"),
+            ),
+            // Renet2 reasons are not sent to standard clients
+            (
+                Packet::ConnectionDenied {
+                    reason: ConnectionDeniedReason::ServerFull,
+                },
+                Some((0, &server_key)),
+                include_bytes!("../test_vectors/connection_denied.bin"),
+            ),
+        ];
+
+        for (packet, crypto_info, vector) in vectors {
+            let mut buffer = [0u8; NETCODE_MAX_PACKET_BYTES];
+            let len = packet
+                .encode(&mut buffer, protocol_id, crypto_info, true, NetcodeVersion::Standard)
+                .unwrap();
+            assert_eq!(&buffer[..len], vector, "{:?}", packet.packet_type());
+
+            let mut buffer = vector.to_vec();
+            let key = crypto_info.map(|(_, key)| key);
+            let (sequence, decoded) = Packet::decode(&mut buffer, protocol_id, key, None, true, NetcodeVersion::Standard).unwrap();
+            assert_eq!(sequence, crypto_info.map_or(0, |(sequence, _)| sequence));
+            match packet {
+                Packet::ConnectionDenied { .. } => assert_eq!(
+                    decoded,
+                    Packet::ConnectionDenied {
+                        reason: ConnectionDeniedReason::Unspecified
+                    }
+                ),
+                _ => assert_eq!(decoded, packet),
+            }
+        }
+
+        let mut buffer = include_bytes!("../test_vectors/challenge.bin").to_vec();
+        let (_, packet) = Packet::decode(&mut buffer, protocol_id, Some(&server_key), None, true, NetcodeVersion::Standard).unwrap();
+        let Packet::Challenge {
+            token_sequence,
+            token_data,
+        } = packet
+        else {
+            unreachable!()
+        };
+        let challenge_token = ChallengeToken::decode(token_data, token_sequence, &challenge_key).unwrap();
+        assert_eq!(challenge_token, ChallengeToken::new(client_id, &user_data));
+
+        // Renet2 packets are not accepted in standard mode
+        let mut buffer = [0u8; NETCODE_MAX_PACKET_BYTES];
+        let len = Packet::Disconnect
+            .encode(&mut buffer, protocol_id, Some((1, &client_key)), true, NetcodeVersion::Renet2)
+            .unwrap();
+        assert!(Packet::decode(
+            &mut buffer[..len],
+            protocol_id,
+            Some(&client_key),
+            None,
+            true,
+            NetcodeVersion::Standard
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, net::IpAddr, time::Duration};
+
+use crate::IpRange;
+
+/// Limits how many connection request and response packets are processed from each source.
+///
+/// These packets are the only ones that make the server decrypt data for senders that are not connected,
+/// packets above the limit are dropped before decryption.
+/// Both limits are token buckets that refill continuously and hold up to one second of tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionRequestLimit {
+    /// Maximum number of packets per second from an IP address.
+    pub per_address: u32,
+    /// Maximum number of packets per second from a subnet.
+    pub per_subnet: u32,
+    /// Prefix length of the IPv4 subnets, at most 32.
+    pub ipv4_subnet_prefix: u8,
+    /// Prefix length of the IPv6 subnets, at most 128.
+    pub ipv6_subnet_prefix: u8,
+}
+
+impl Default for ConnectionRequestLimit {
+    fn default() -> Self {
+        // Clients resend their request or response every 250ms, leave room for a few clients per address
+        Self {
+            per_address: 20,
+            per_subnet: 100,
+            ipv4_subnet_prefix: 24,
+            ipv6_subnet_prefix: 64,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_update: Duration,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, current_time: Duration) -> Self {
+        Self {
+            tokens: capacity as f64,
+            last_update: current_time,
+        }
+    }
+
+    fn refill(&mut self, capacity: u32, current_time: Duration) {
+        let elapsed = current_time.saturating_sub(self.last_update).as_secs_f64();
+        self.last_update = current_time;
+        self.tokens = (self.tokens + elapsed * capacity as f64).min(capacity as f64);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ConnectionRequestLimiter {
+    limit: ConnectionRequestLimit,
+    addresses: HashMap<IpAddr, TokenBucket>,
+    subnets: HashMap<IpRange, TokenBucket>,
+}
+
+impl ConnectionRequestLimiter {
+    pub fn new(limit: ConnectionRequestLimit) -> Self {
+        Self {
+            limit,
+            addresses: HashMap::new(),
+            subnets: HashMap::new(),
+        }
+    }
+
+    pub fn limit(&self) -> ConnectionRequestLimit {
+        self.limit
+    }
+
+    fn subnet(&self, addr: IpAddr) -> IpRange {
+        match addr.to_canonical() {
+            IpAddr::V4(_) => IpRange::new(addr, self.limit.ipv4_subnet_prefix.min(32)),
+            IpAddr::V6(_) => IpRange::new(addr, self.limit.ipv6_subnet_prefix.min(128)),
+        }
+    }
+
+    /// Consumes a token from the buckets of the address and its subnet.
+    /// Returns the scope of the limit that was exceeded and consumes nothing if a bucket is empty.
+    pub fn try_consume(&mut self, addr: IpAddr, current_time: Duration) -> Result<(), &'static str> {
+        let addr = addr.to_canonical();
+        let subnet = self.subnet(addr);
+        let limit = self.limit;
+
+        // New addresses start with a full bucket, they are only added once the subnet accepts the packet
+        // so a flood from many addresses of a limited subnet doesn't grow the map
+        if let Some(address_bucket) = self.addresses.get_mut(&addr) {
+            address_bucket.refill(limit.per_address, current_time);
+            if address_bucket.tokens < 1.0 {
+                return Err("address");
+            }
+        }
+
+        let subnet_bucket = self
+            .subnets
+            .entry(subnet)
+            .or_insert_with(|| TokenBucket::new(limit.per_subnet, current_time));
+        subnet_bucket.refill(limit.per_subnet, current_time);
+        if subnet_bucket.tokens < 1.0 {
+            return Err("subnet");
+        }
+
+        let address_bucket = self
+            .addresses
+            .entry(addr)
+            .or_insert_with(|| TokenBucket::new(limit.per_address, current_time));
+        if address_bucket.tokens < 1.0 {
+            // The address limit is below one packet per second
+            return Err("address");
+        }
+
+        subnet_bucket.tokens -= 1.0;
+        address_bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Removes the buckets that are full, they behave the same as new ones.
+    pub fn remove_full_buckets(&mut self, current_time: Duration) {
+        let limit = self.limit;
+        self.addresses.retain(|_, bucket| {
+            bucket.refill(limit.per_address, current_time);
+            bucket.tokens < limit.per_address as f64
+        });
+        self.subnets.retain(|_, bucket| {
+            bucket.refill(limit.per_subnet, current_time);
+            bucket.tokens < limit.per_subnet as f64
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_request_limiter() {
+        let limit = ConnectionRequestLimit {
+            per_address: 2,
+            per_subnet: 3,
+            ipv4_subnet_prefix: 24,
+            ipv6_subnet_prefix: 64,
+        };
+        let mut limiter = ConnectionRequestLimiter::new(limit);
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+
+        // Address limit
+        assert!(limiter.try_consume(addr, Duration::ZERO).is_ok());
+        assert!(limiter.try_consume(addr, Duration::ZERO).is_ok());
+        assert_eq!(limiter.try_consume(addr, Duration::ZERO), Err("address"));
+
+        // Subnet limit
+        assert!(limiter.try_consume("10.0.0.2".parse().unwrap(), Duration::ZERO).is_ok());
+        assert_eq!(limiter.try_consume("10.0.0.3".parse().unwrap(), Duration::ZERO), Err("subnet"));
+        for i in 4..=255 {
+            let addr = IpAddr::from([10, 0, 0, i]);
+            assert_eq!(limiter.try_consume(addr, Duration::ZERO), Err("subnet"));
+        }
+        assert_eq!(limiter.addresses.len(), 2);
+        assert!(limiter.try_consume("10.0.1.1".parse().unwrap(), Duration::ZERO).is_ok());
+
+        // Refills over time
+        assert!(limiter.try_consume(addr, Duration::from_millis(500)).is_ok());
+        assert_eq!(limiter.try_consume(addr, Duration::from_millis(500)), Err("address"));
+
+        limiter.remove_full_buckets(Duration::from_secs(1));
+        assert_eq!(limiter.addresses.len(), 1);
+        limiter.remove_full_buckets(Duration::from_secs(2));
+        assert!(limiter.addresses.is_empty());
+        assert!(limiter.subnets.is_empty());
+    }
+}
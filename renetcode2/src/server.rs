@@ -1,15 +1,48 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
 use crate::{
+    ban::{BanList, IpRange},
+    clock::Clock,
     crypto::generate_random_bytes,
-    packet::{ChallengeToken, Packet},
+    extended_user_data::{open_extended_user_data, ExtendedUserDataReceiver},
+    packet::{ChallengeToken, ConnectionDeniedReason, Packet, PacketType},
+    rate_limit::{ConnectionRequestLimit, ConnectionRequestLimiter},
     replay_protection::ReplayProtection,
-    token::PrivateConnectToken,
-    NetcodeError, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES, NETCODE_KEY_BYTES, NETCODE_MAC_BYTES,
-    NETCODE_MAX_CLIENTS, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES, NETCODE_MAX_PENDING_CLIENTS, NETCODE_SEND_RATE,
-    NETCODE_USER_DATA_BYTES, NETCODE_VERSION_INFO,
+    token::{xnonce_key_id, PrivateConnectToken},
+    NetcodeError, NetcodeVersion, ServerConfigError, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES,
+    NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_MAX_PACKET_BYTES, NETCODE_MAX_PAYLOAD_BYTES, NETCODE_SEND_RATE, NETCODE_USER_DATA_BYTES,
 };
 
+/// Logs an event of a connection, through `tracing` in the span of the connection when that feature is enabled,
+/// or through `log` otherwise.
+macro_rules! connection_event {
+    ($connection:expr, $level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        $connection.span.in_scope(|| tracing::$level!($($arg)+));
+        #[cfg(not(feature = "tracing"))]
+        log::$level!($($arg)+);
+    }};
+}
+
+/// Logs a denied connection request and counts it by reason. The reason is a field of the event when `tracing` is enabled.
+macro_rules! connection_denied {
+    ($reason:expr, $level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "metrics")]
+        metrics::counter!("renetcode2_connection_denied_total", "reason" => $reason).increment(1);
+        #[cfg(feature = "tracing")]
+        tracing::$level!(reason = $reason, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::$level!($($arg)+);
+    }};
+}
+
+/// Minimum number of connect tokens remembered by the server, so a token can't be reused from another address.
+const MIN_CONNECT_TOKEN_ENTRIES: usize = 2048;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ConnectionState {
     Disconnected,
@@ -31,33 +64,123 @@ struct Connection {
     last_packet_send_time: Duration,
     timeout_seconds: i32,
     sequence: u64,
-    expire_timestamp: u64,
+    // Server time at which the connect token expires, for pending clients
+    expire_time: Duration,
     replay_protection: ReplayProtection,
+    // MAC of the connect token, that authenticates the extended user data
+    token_mac: [u8; NETCODE_MAC_BYTES],
+    extended_user_data: ExtendedUserDataReceiver,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 #[derive(Debug, Copy, Clone)]
 struct ConnectTokenEntry {
-    time: Duration,
     socket_id: usize,
     address: SocketAddr,
     mac: [u8; NETCODE_MAC_BYTES],
 }
 
+/// Connected clients, indexed by slot, client id and address.
+///
+/// Slots are the client indices sent to the clients, freed slots are reused lowest first.
+#[derive(Debug, Default)]
+struct ClientSlots {
+    slots: Vec<Option<Connection>>,
+    free_slots: BTreeSet<usize>,
+    by_id: HashMap<u64, usize>,
+    by_addr: HashMap<(usize, SocketAddr), usize>,
+}
+
+impl ClientSlots {
+    fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, &Connection)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(slot, c)| c.as_ref().map(|c| (slot, c)))
+    }
+
+    fn slot_by_id(&self, client_id: u64) -> Option<usize> {
+        self.by_id.get(&client_id).copied()
+    }
+
+    fn get_by_id(&self, client_id: u64) -> Option<&Connection> {
+        let slot = self.slot_by_id(client_id)?;
+        self.slots[slot].as_ref()
+    }
+
+    fn get_mut_by_id(&mut self, client_id: u64) -> Option<&mut Connection> {
+        let slot = self.slot_by_id(client_id)?;
+        self.slots[slot].as_mut()
+    }
+
+    #[cfg(feature = "tracing")]
+    fn get_by_addr(&self, socket_id: usize, addr: SocketAddr) -> Option<&Connection> {
+        let slot = *self.by_addr.get(&(socket_id, addr))?;
+        self.slots[slot].as_ref()
+    }
+
+    fn get_mut_by_addr(&mut self, socket_id: usize, addr: SocketAddr) -> Option<(usize, &mut Connection)> {
+        let slot = *self.by_addr.get(&(socket_id, addr))?;
+        self.slots[slot].as_mut().map(|c| (slot, c))
+    }
+
+    fn get_mut(&mut self, slot: usize) -> Option<&mut Connection> {
+        self.slots.get_mut(slot)?.as_mut()
+    }
+
+    /// Returns the slot the next inserted client will take.
+    fn next_slot(&self) -> usize {
+        self.free_slots.first().copied().unwrap_or(self.slots.len())
+    }
+
+    fn insert(&mut self, connection: Connection) -> usize {
+        let slot = match self.free_slots.pop_first() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(None);
+                self.slots.len() - 1
+            }
+        };
+        self.by_id.insert(connection.client_id, slot);
+        self.by_addr.insert((connection.socket_id, connection.addr), slot);
+        self.slots[slot] = Some(connection);
+        slot
+    }
+
+    fn remove(&mut self, slot: usize) -> Option<Connection> {
+        let connection = self.slots.get_mut(slot)?.take()?;
+        self.by_id.remove(&connection.client_id);
+        self.by_addr.remove(&(connection.socket_id, connection.addr));
+        self.free_slots.insert(slot);
+        Some(connection)
+    }
+}
+
 /// A server that can generate packets from connect clients, that are encrypted, or process
 /// incoming encrypted packets from clients. The server is agnostic from the transport layer, only
 /// consuming and generating bytes that can be transported in any way desired.
 #[derive(Debug)]
 pub struct NetcodeServer {
     sockets: Vec<ServerSocketConfig>,
-    clients: Box<[Option<Connection>]>,
+    clients: ClientSlots,
     pending_clients: HashMap<(usize, SocketAddr), Connection>,
-    connect_token_entries: Box<[Option<ConnectTokenEntry>; NETCODE_MAX_CLIENTS * 2]>,
+    connect_token_entries: HashMap<[u8; NETCODE_MAC_BYTES], ConnectTokenEntry>,
+    // Macs of the connect token entries, oldest first
+    connect_token_order: VecDeque<[u8; NETCODE_MAC_BYTES]>,
     protocol_id: u64,
-    connect_key: [u8; NETCODE_KEY_BYTES],
+    connect_keys: Vec<ServerKey>,
     max_clients: usize,
+    max_pending_clients: usize,
+    ban_list: BanList,
+    request_limiter: Option<ConnectionRequestLimiter>,
+    rate_limited_requests: u64,
     challenge_sequence: u64,
     challenge_key: [u8; NETCODE_KEY_BYTES],
     current_time: Duration,
+    token_time: Duration,
+    clock: Option<Box<dyn Clock>>,
     global_sequence: u64,
     secure: bool,
     out: [u8; NETCODE_MAX_PACKET_BYTES],
@@ -72,7 +195,8 @@ pub enum ServerResult<'a, 's> {
     Error { socket_id: usize, addr: SocketAddr },
     /// A connection request was valid but denied because of connection limits or a token already in use.
     ///
-    /// If there is a payload it should be sent to the address.
+    /// If there is a payload it should be sent to the address. Requests from banned addresses are denied
+    /// before decrypting their connect token, so there is no key to encrypt a reason and they have no payload.
     ConnectionDenied {
         socket_id: usize,
         addr: SocketAddr,
@@ -121,6 +245,14 @@ pub struct ServerSocketConfig {
     pub needs_encryption: bool,
     /// Publicly available addresses to which clients will attempt to connect.
     pub public_addresses: Vec<SocketAddr>,
+    /// Netcode version spoken by the clients of this socket.
+    ///
+    /// Sockets using [`NetcodeVersion::Standard`] must need encryption, see [`ServerConfigError::UnencryptedStandardSocket`],
+    /// and accept the connect tokens
+    /// generated with [`ConnectToken::generate_standard`](crate::ConnectToken::generate_standard).
+    ///
+    /// [`NetcodeVersion::Renet2`] by default.
+    pub version: NetcodeVersion,
 }
 
 impl ServerSocketConfig {
@@ -129,6 +261,7 @@ impl ServerSocketConfig {
         Self {
             needs_encryption: true,
             public_addresses,
+            version: NetcodeVersion::Renet2,
         }
     }
 }
@@ -140,16 +273,56 @@ pub enum ServerAuthentication {
     ///
     /// See also [ClientAuthentication::Secure][crate::ClientAuthentication::Secure]
     Secure { private_key: [u8; NETCODE_KEY_BYTES] },
+    /// Same as [`ServerAuthentication::Secure`], but accepts connect tokens encrypted with any of the keys.
+    ///
+    /// Useful to rotate the private key without invalidating the connect tokens already issued,
+    /// keys can be added and removed at runtime with [`NetcodeServer::add_private_key`] and
+    /// [`NetcodeServer::remove_private_key`].
+    SecureKeys { keys: Vec<ServerKey> },
     /// Establishes unsafe connections with clients, useful for testing and prototyping.
     ///
     /// See also [ClientAuthentication::Unsecure][crate::ClientAuthentication::Unsecure]
     Unsecure,
 }
 
+/// A private key accepted by a secure server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerKey {
+    /// Identifies the key when replacing or removing it.
+    pub id: u64,
+    pub private_key: [u8; NETCODE_KEY_BYTES],
+    /// Token time from which connect tokens encrypted with this key are accepted, see [`NetcodeServer::token_time`].
+    pub valid_from: Option<Duration>,
+    /// Token time from which connect tokens encrypted with this key are no longer accepted.
+    pub valid_until: Option<Duration>,
+}
+
+impl ServerKey {
+    /// Makes a new key that is always valid.
+    pub fn new(id: u64, private_key: [u8; NETCODE_KEY_BYTES]) -> Self {
+        Self {
+            id,
+            private_key,
+            valid_from: None,
+            valid_until: None,
+        }
+    }
+
+    /// Returns whether connect tokens encrypted with this key are accepted at the token time.
+    pub fn is_valid(&self, token_time: Duration) -> bool {
+        self.valid_from.is_none_or(|valid_from| token_time >= valid_from)
+            && self.valid_until.is_none_or(|valid_until| token_time < valid_until)
+    }
+}
+
 pub struct ServerConfig {
     pub current_time: Duration,
     /// Maximum numbers of clients that can be connected at a time
     pub max_clients: usize,
+    /// Maximum numbers of clients that can be in the middle of connecting at a time.
+    ///
+    /// Usually a multiple of `max_clients`, to absorb bursts of connection requests.
+    pub max_pending_clients: usize,
     /// Unique identifier for this particular game/application.
     /// You can use a hash function with the current version of the game to generate this value
     /// so that older versions cannot connect to newer versions.
@@ -161,43 +334,63 @@ pub struct ServerConfig {
 }
 
 impl NetcodeServer {
+    /// Makes a new server.
+    ///
+    /// Panics if the config is invalid, see [`Self::try_new`].
     pub fn new(config: ServerConfig) -> Self {
+        match Self::try_new(config) {
+            Ok(server) => server,
+            Err(err) => panic!("Cannot make a server: {err}."),
+        }
+    }
+
+    /// Same as [`Self::new`], but returns an error instead of panicking if the config is invalid.
+    pub fn try_new(config: ServerConfig) -> Result<Self, ServerConfigError> {
         if config.sockets.is_empty() {
-            panic!("Cannot make a server with no sockets.");
+            return Err(ServerConfigError::NoSockets);
         }
-        if config.max_clients > NETCODE_MAX_CLIENTS {
-            // TODO: do we really need to set a max?
-            //       only using for token entries
-            panic!("The max clients allowed is {}", NETCODE_MAX_CLIENTS);
+        if let Some(socket_id) = config
+            .sockets
+            .iter()
+            .position(|socket| socket.version == NetcodeVersion::Standard && !socket.needs_encryption)
+        {
+            return Err(ServerConfigError::UnencryptedStandardSocket { socket_id });
         }
         let challenge_key = generate_random_bytes();
-        let clients = vec![None; config.max_clients].into_boxed_slice();
-
-        let connect_key = match config.authentication {
-            ServerAuthentication::Unsecure => [0; NETCODE_KEY_BYTES],
-            ServerAuthentication::Secure { private_key } => private_key,
-        };
 
         let secure = match config.authentication {
             ServerAuthentication::Unsecure => false,
-            ServerAuthentication::Secure { .. } => true,
+            ServerAuthentication::Secure { .. } | ServerAuthentication::SecureKeys { .. } => true,
         };
 
-        Self {
+        let connect_keys = match config.authentication {
+            ServerAuthentication::Unsecure => vec![ServerKey::new(0, [0; NETCODE_KEY_BYTES])],
+            ServerAuthentication::Secure { private_key } => vec![ServerKey::new(0, private_key)],
+            ServerAuthentication::SecureKeys { keys } => keys,
+        };
+
+        Ok(Self {
             sockets: config.sockets,
-            clients,
-            connect_token_entries: Box::new([None; NETCODE_MAX_CLIENTS * 2]),
+            clients: ClientSlots::default(),
+            connect_token_entries: HashMap::new(),
+            connect_token_order: VecDeque::new(),
             pending_clients: HashMap::new(),
             protocol_id: config.protocol_id,
-            connect_key,
+            connect_keys,
             max_clients: config.max_clients,
+            max_pending_clients: config.max_pending_clients,
+            ban_list: BanList::default(),
+            request_limiter: None,
+            rate_limited_requests: 0,
             challenge_sequence: 0,
             global_sequence: 0,
             challenge_key,
             current_time: config.current_time,
+            token_time: config.current_time,
+            clock: None,
             secure,
             out: [0u8; NETCODE_MAX_PACKET_BYTES],
-        }
+        })
     }
 
     #[doc(hidden)]
@@ -205,6 +398,7 @@ impl NetcodeServer {
         let config = ServerConfig {
             current_time: Duration::ZERO,
             max_clients: 32,
+            max_pending_clients: 128,
             protocol_id: 0,
             sockets: vec![ServerSocketConfig::new(vec!["127.0.0.1:0".parse().unwrap()])],
             authentication: ServerAuthentication::Unsecure,
@@ -219,57 +413,70 @@ impl NetcodeServer {
         self.sockets[socket_id].public_addresses.clone()
     }
 
+    /// Returns the server time, the sum of the durations given to [`NetcodeServer::update`].
+    ///
+    /// Timeouts, pending connections and bans are measured with it.
     pub fn current_time(&self) -> Duration {
         self.current_time
     }
 
+    /// Returns the time compared to the timestamps of connect tokens and to the validity of private keys.
+    ///
+    /// It is read from the clock if the server has one, see [`NetcodeServer::set_clock`], else it is the server time.
+    pub fn token_time(&self) -> Duration {
+        self.token_time
+    }
+
+    /// Sets the clock that the token time is read from, when the server is updated.
+    ///
+    /// Useful to compare the timestamps of connect tokens to the time of the service that issues them,
+    /// instead of the server time which drifts from it over long uptimes. The clock can go backwards,
+    /// the server time is still used for timeouts.
+    pub fn set_clock(&mut self, clock: impl Clock) {
+        self.token_time = clock.now();
+        self.clock = Some(Box::new(clock));
+    }
+
     fn find_or_add_connect_token_entry(&mut self, new_entry: ConnectTokenEntry) -> bool {
-        let mut min = Duration::MAX;
-        let mut oldest_entry = 0;
-        let mut empty_entry = false;
-        let mut matching_entry = None;
-        for (i, entry) in self.connect_token_entries.iter().enumerate() {
-            match entry {
-                Some(e) => {
-                    if e.mac == new_entry.mac {
-                        matching_entry = Some(e);
-                    }
-                    if !empty_entry && e.time < min {
-                        oldest_entry = i;
-                        min = e.time;
-                    }
-                }
-                None => {
-                    if !empty_entry {
-                        empty_entry = true;
-                        oldest_entry = i;
-                    }
-                }
-            }
+        if let Some(entry) = self.connect_token_entries.get(&new_entry.mac) {
+            return (entry.socket_id == new_entry.socket_id) && (entry.address == new_entry.address);
         }
 
-        if let Some(entry) = matching_entry {
-            return (entry.socket_id == new_entry.socket_id) && (entry.address == new_entry.address);
+        // Keep entries for every connected and pending client, replacing the oldest ones
+        let max_entries = (self.max_clients + self.max_pending_clients).max(MIN_CONNECT_TOKEN_ENTRIES);
+        while self.connect_token_entries.len() >= max_entries {
+            let Some(oldest_mac) = self.connect_token_order.pop_front() else {
+                break;
+            };
+            self.connect_token_entries.remove(&oldest_mac);
         }
 
-        self.connect_token_entries[oldest_entry] = Some(new_entry);
+        self.connect_token_entries.insert(new_entry.mac, new_entry);
+        self.connect_token_order.push_back(new_entry.mac);
 
         true
     }
 
     /// Returns the user data from the connected client.
     pub fn user_data(&self, client_id: u64) -> Option<[u8; NETCODE_USER_DATA_BYTES]> {
-        if let Some(client) = find_client_by_id(&self.clients, client_id) {
+        if let Some(client) = self.clients.get_by_id(client_id) {
             return Some(client.user_data);
         }
 
         None
     }
 
+    /// Returns the extended user data from the connected client, once received.
+    ///
+    /// Clients send it after connecting, so it is not available yet when the client connects.
+    pub fn extended_user_data(&self, client_id: u64) -> Option<&[u8]> {
+        self.clients.get_by_id(client_id)?.extended_user_data.data()
+    }
+
     /// Returns the duration since the connected client last received a packet.
     /// Usefull to detect users that are timing out.
     pub fn time_since_last_received_packet(&self, client_id: u64) -> Option<Duration> {
-        if let Some(client) = find_client_by_id(&self.clients, client_id) {
+        if let Some(client) = self.clients.get_by_id(client_id) {
             let time = self.current_time - client.last_packet_received_time;
             return Some(time);
         }
@@ -279,7 +486,7 @@ impl NetcodeServer {
 
     /// Returns the client socket id and address if connected.
     pub fn client_addr(&self, client_id: u64) -> Option<(usize, SocketAddr)> {
-        if let Some(client) = find_client_by_id(&self.clients, client_id) {
+        if let Some(client) = self.clients.get_by_id(client_id) {
             return Some((client.socket_id, client.addr));
         }
 
@@ -297,7 +504,12 @@ impl NetcodeServer {
         xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES],
         data: [u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES],
     ) -> Result<ServerResult<'a, '_>, NetcodeError> {
-        if version_info != *NETCODE_VERSION_INFO {
+        if socket_id >= self.sockets.len() {
+            return Err(NetcodeError::InvalidSocketId);
+        }
+
+        let version = self.sockets[socket_id].version;
+        if version_info != *version.version_info() {
             return Err(NetcodeError::InvalidVersion);
         }
 
@@ -305,16 +517,24 @@ impl NetcodeServer {
             return Err(NetcodeError::InvalidProtocolID);
         }
 
-        if self.current_time.as_secs() >= expire_timestamp {
+        if self.token_time.as_secs() >= expire_timestamp {
             return Err(NetcodeError::Expired);
         }
 
-        let connect_token = PrivateConnectToken::decode(&data, self.protocol_id, expire_timestamp, &xnonce, &self.connect_key)?;
-
-        if socket_id >= self.sockets.len() {
-            return Err(NetcodeError::InvalidSocketId);
+        // Checked before decrypting the connect token, so banned addresses don't use server resources
+        if self.ban_list.is_address_banned(addr.ip(), self.current_time) {
+            connection_denied!("banned_address", debug, "Connection request denied: address {} is banned.", addr);
+            return Ok(ServerResult::ConnectionDenied {
+                addr,
+                socket_id,
+                payload: None,
+            });
         }
-        if socket_id != connect_token.socket_id as usize {
+
+        let connect_token = self.decode_private_connect_token(&data, expire_timestamp, &xnonce, version)?;
+
+        // Standard connect tokens have no socket id
+        if version == NetcodeVersion::Renet2 && socket_id != connect_token.socket_id as usize {
             return Err(NetcodeError::InvalidSocketId);
         }
 
@@ -331,7 +551,36 @@ impl NetcodeServer {
             }
         }
 
-        if let Some((_, connection)) = find_client_mut_by_addr(&mut self.clients, socket_id, addr) {
+        let mut mac = [0u8; NETCODE_MAC_BYTES];
+        mac.copy_from_slice(&data[NETCODE_CONNECT_TOKEN_PRIVATE_BYTES - NETCODE_MAC_BYTES..]);
+
+        let denied_reason = if self.ban_list.is_client_banned(connect_token.client_id, self.current_time) {
+            Some(("banned_client", ConnectionDeniedReason::ClientBanned))
+        } else if self.ban_list.is_token_revoked(&mac, self.token_time) {
+            Some(("revoked_token", ConnectionDeniedReason::ConnectTokenRevoked))
+        } else {
+            None
+        };
+        if let Some((reason, denied_reason)) = denied_reason {
+            connection_denied!(
+                reason,
+                debug,
+                "Connection request denied: client {} (socket id: {}, address: {}) is not allowed to connect ({}).",
+                connect_token.client_id,
+                socket_id,
+                addr,
+                reason
+            );
+            self.pending_clients.remove(&(socket_id, addr));
+            let len = self.encode_connection_denied(socket_id, &connect_token.server_to_client_key, denied_reason)?;
+            return Ok(ServerResult::ConnectionDenied {
+                socket_id,
+                addr,
+                payload: Some(&mut self.out[..len]),
+            });
+        }
+
+        if let Some((_, connection)) = self.clients.get_mut_by_addr(socket_id, addr) {
             // This branch should be unreachable since connection requests are ignored for already-connected addresses.
 
             if connection.client_id == connect_token.client_id {
@@ -344,7 +593,9 @@ impl NetcodeServer {
 
                 return Ok(ServerResult::None);
             } else {
-                log::debug!(
+                connection_denied!(
+                    "address_in_use",
+                    debug,
                     "Connection request denied: (socket id: {}, address: {}) tried connecting as client {} but is already \
                     client {}.",
                     socket_id,
@@ -359,8 +610,10 @@ impl NetcodeServer {
                     payload: None,
                 });
             }
-        } else if let Some(connection) = find_client_mut_by_id(&mut self.clients, connect_token.client_id) {
-            log::debug!(
+        } else if let Some(connection) = self.clients.get_mut_by_id(connect_token.client_id) {
+            connection_denied!(
+                "client_id_in_use",
+                debug,
                 "Connection request denied: (socket id: {}, address: {}) tried connecting as client {} but a different \
                 address (socket id: {}, address: {}) is connected as that client.",
                 socket_id,
@@ -369,53 +622,70 @@ impl NetcodeServer {
                 connection.socket_id,
                 connection.addr,
             );
+            let len = self.encode_connection_denied(
+                socket_id,
+                &connect_token.server_to_client_key,
+                ConnectionDeniedReason::ClientIdInUse,
+            )?;
             return Ok(ServerResult::ConnectionDenied {
                 addr,
                 socket_id,
-                payload: None,
+                payload: Some(&mut self.out[..len]),
             });
         }
 
-        if !self.pending_clients.contains_key(&(socket_id, addr)) && self.pending_clients.len() >= NETCODE_MAX_PENDING_CLIENTS {
-            log::warn!(
+        if !self.pending_clients.contains_key(&(socket_id, addr)) && self.pending_clients.len() >= self.max_pending_clients {
+            connection_denied!(
+                "too_many_pending_clients",
+                warn,
                 "Connection request denied: reached max amount allowed of pending clients ({}).",
-                NETCODE_MAX_PENDING_CLIENTS
+                self.max_pending_clients
             );
+            let len = self.encode_connection_denied(
+                socket_id,
+                &connect_token.server_to_client_key,
+                ConnectionDeniedReason::TooManyPendingClients,
+            )?;
             return Ok(ServerResult::ConnectionDenied {
                 addr,
                 socket_id,
-                payload: None,
+                payload: Some(&mut self.out[..len]),
             });
         }
 
-        let mut mac = [0u8; NETCODE_MAC_BYTES];
-        mac.copy_from_slice(&data[NETCODE_CONNECT_TOKEN_PRIVATE_BYTES - NETCODE_MAC_BYTES..]);
         let connect_token_entry = ConnectTokenEntry {
             socket_id,
             address: addr,
-            time: self.current_time,
             mac,
         };
 
         if !self.find_or_add_connect_token_entry(connect_token_entry) {
-            log::warn!("Connection request denied: connect token already has an entry for a different address");
+            connection_denied!(
+                "connect_token_in_use",
+                warn,
+                "Connection request denied: connect token already has an entry for a different address"
+            );
+            let len = self.encode_connection_denied(
+                socket_id,
+                &connect_token.server_to_client_key,
+                ConnectionDeniedReason::ConnectTokenInUse,
+            )?;
             return Ok(ServerResult::ConnectionDenied {
                 addr,
                 socket_id,
-                payload: None,
+                payload: Some(&mut self.out[..len]),
             });
         }
 
-        if self.clients.iter().flatten().count() >= self.max_clients {
+        if self.clients.len() >= self.max_clients {
             self.pending_clients.remove(&(socket_id, addr));
-            let packet = Packet::ConnectionDenied;
-            let len = packet.encode(
-                &mut self.out,
-                self.protocol_id,
-                Some((self.global_sequence, &connect_token.server_to_client_key)),
-                self.sockets[socket_id].needs_encryption,
-            )?;
-            self.global_sequence += 1;
+            connection_denied!(
+                "server_full",
+                debug,
+                "Connection request denied: server is full ({} clients).",
+                self.max_clients
+            );
+            let len = self.encode_connection_denied(socket_id, &connect_token.server_to_client_key, ConnectionDeniedReason::ServerFull)?;
             return Ok(ServerResult::ConnectionDenied {
                 socket_id,
                 addr,
@@ -436,6 +706,7 @@ impl NetcodeServer {
             self.protocol_id,
             Some((self.global_sequence, &connect_token.server_to_client_key)),
             self.sockets[socket_id].needs_encryption,
+            self.sockets[socket_id].version,
         )?;
         self.global_sequence += 1;
 
@@ -453,12 +724,17 @@ impl NetcodeServer {
             send_key: connect_token.server_to_client_key,
             receive_key: connect_token.client_to_server_key,
             timeout_seconds: connect_token.timeout_seconds,
-            expire_timestamp,
+            expire_time: self.current_time + Duration::from_secs(expire_timestamp).saturating_sub(self.token_time),
             user_data: connect_token.user_data,
             replay_protection: ReplayProtection::new(),
+            token_mac: mac,
+            extended_user_data: ExtendedUserDataReceiver::default(),
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("netcode_connection", client_id = connect_token.client_id, socket_id, %addr),
         });
         pending.last_packet_received_time = self.current_time;
         pending.last_packet_send_time = self.current_time;
+        connection_event!(pending, trace, "Sent connection challenge to Client {}", pending.client_id);
 
         Ok(ServerResult::ConnectionAccepted {
             client_id: connect_token.client_id,
@@ -468,6 +744,59 @@ impl NetcodeServer {
         })
     }
 
+    /// Decodes the private part of a connect token with the first valid key that can decrypt it.
+    fn decode_private_connect_token(
+        &self,
+        data: &[u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES],
+        expire_timestamp: u64,
+        xnonce: &[u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES],
+        version: NetcodeVersion,
+    ) -> Result<PrivateConnectToken, NetcodeError> {
+        // Tokens without a key id have a random nonce, that starts like a key id with a chance of 1 in 2^32
+        if let Some(key_id) = xnonce_key_id(xnonce) {
+            let Some(key) = self
+                .connect_keys
+                .iter()
+                .find(|key| key.id == key_id && key.is_valid(self.token_time))
+            else {
+                return Err(NetcodeError::UnavailablePrivateKey);
+            };
+            return PrivateConnectToken::decode(data, self.protocol_id, expire_timestamp, xnonce, &key.private_key, version)
+                .map_err(Into::into);
+        }
+
+        let mut result = Err(NetcodeError::UnavailablePrivateKey);
+        // The token doesn't identify its key, try the most recently added keys first
+        for key in self.connect_keys.iter().rev().filter(|key| key.is_valid(self.token_time)) {
+            result = PrivateConnectToken::decode(data, self.protocol_id, expire_timestamp, xnonce, &key.private_key, version)
+                .map_err(Into::into);
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Encodes a connection denied packet in the output buffer, returns its length.
+    fn encode_connection_denied(
+        &mut self,
+        socket_id: usize,
+        send_key: &[u8; NETCODE_KEY_BYTES],
+        reason: ConnectionDeniedReason,
+    ) -> Result<usize, NetcodeError> {
+        let packet = Packet::ConnectionDenied { reason };
+        let len = packet.encode(
+            &mut self.out,
+            self.protocol_id,
+            Some((self.global_sequence, send_key)),
+            self.sockets[socket_id].needs_encryption,
+            self.sockets[socket_id].version,
+        )?;
+        self.global_sequence += 1;
+        Ok(len)
+    }
+
     /// Returns an encoded packet payload to be sent to the client.
     pub fn generate_payload_packet<'s>(
         &'s mut self,
@@ -478,13 +807,14 @@ impl NetcodeServer {
             return Err(NetcodeError::PayloadAboveLimit);
         }
 
-        if let Some(client) = find_client_mut_by_id(&mut self.clients, client_id) {
+        if let Some(client) = self.clients.get_mut_by_id(client_id) {
             let packet = Packet::Payload(payload);
             let len = packet.encode(
                 &mut self.out,
                 self.protocol_id,
                 Some((client.sequence, &client.send_key)),
                 self.sockets[client.socket_id].needs_encryption,
+                self.sockets[client.socket_id].version,
             )?;
             client.sequence += 1;
             client.last_packet_send_time = self.current_time;
@@ -518,13 +848,14 @@ impl NetcodeServer {
         }
 
         // Handle connected client
-        if let Some((slot, client)) = find_client_mut_by_addr(&mut self.clients, socket_id, addr) {
+        if let Some((slot, client)) = self.clients.get_mut_by_addr(socket_id, addr) {
             let (_, packet) = Packet::decode(
                 buffer,
                 self.protocol_id,
                 Some(&client.receive_key),
                 Some(&mut client.replay_protection),
                 self.sockets[socket_id].needs_encryption,
+                self.sockets[socket_id].version,
             )?;
             log::trace!(
                 "Received packet from connected client ({}): {:?}",
@@ -536,10 +867,10 @@ impl NetcodeServer {
             match client.state {
                 ConnectionState::Connected => match packet {
                     Packet::Disconnect => {
+                        connection_event!(client, debug, "Client {} requested to disconnect", client.client_id);
                         client.state = ConnectionState::Disconnected;
                         let client_id = client.client_id;
-                        self.clients[slot] = None;
-                        log::trace!("Client {} requested to disconnect", client_id);
+                        self.clients.remove(slot);
                         return Ok(ServerResult::ClientDisconnected {
                             client_id,
                             socket_id,
@@ -549,7 +880,7 @@ impl NetcodeServer {
                     }
                     Packet::Payload(payload) => {
                         if !client.confirmed {
-                            log::trace!("Confirmed connection for Client {}", client.client_id);
+                            connection_event!(client, trace, "Confirmed connection for Client {}", client.client_id);
                             client.confirmed = true;
                         }
                         return Ok(ServerResult::Payload {
@@ -559,17 +890,90 @@ impl NetcodeServer {
                     }
                     Packet::KeepAlive { .. } => {
                         if !client.confirmed {
-                            log::trace!("Confirmed connection for Client {}", client.client_id);
+                            connection_event!(client, trace, "Confirmed connection for Client {}", client.client_id);
                             client.confirmed = true;
                         }
                         return Ok(ServerResult::None);
                     }
+                    Packet::ExtendedUserData { total_len, offset, data } => {
+                        if !client.confirmed {
+                            connection_event!(client, trace, "Confirmed connection for Client {}", client.client_id);
+                            client.confirmed = true;
+                        }
+                        let valid = match client.extended_user_data.receive_fragment(total_len, offset, data) {
+                            Ok(None) => true,
+                            Ok(Some(sealed)) => {
+                                // The data doesn't identify its key, try the most recently added keys first
+                                let opened = self
+                                    .connect_keys
+                                    .iter()
+                                    .rev()
+                                    .filter(|key| key.is_valid(self.token_time))
+                                    .find_map(|key| {
+                                        open_extended_user_data(&sealed, &client.token_mac, self.protocol_id, &key.private_key).ok()
+                                    });
+                                match opened {
+                                    Some(data) => {
+                                        connection_event!(
+                                            client,
+                                            trace,
+                                            "Received extended user data ({} bytes) from Client {}",
+                                            data.len(),
+                                            client.client_id
+                                        );
+                                        client.extended_user_data.set_data(data);
+                                        true
+                                    }
+                                    None => false,
+                                }
+                            }
+                            Err(()) => false,
+                        };
+                        if !valid {
+                            connection_event!(client, warn, "Client {} disconnected, invalid extended user data", client.client_id);
+                            client.state = ConnectionState::Disconnected;
+                            return Ok(ServerResult::None);
+                        }
+                        if client.extended_user_data.data().is_none() {
+                            return Ok(ServerResult::None);
+                        }
+
+                        // Acknowledge every fragment received after the data, until the client stops sending them
+                        let len = Packet::ExtendedUserDataAck.encode(
+                            &mut self.out,
+                            self.protocol_id,
+                            Some((client.sequence, &client.send_key)),
+                            self.sockets[socket_id].needs_encryption,
+                            self.sockets[socket_id].version,
+                        )?;
+                        client.sequence += 1;
+                        client.last_packet_send_time = self.current_time;
+                        return Ok(ServerResult::PacketToSend {
+                            socket_id,
+                            addr,
+                            payload: &mut self.out[..len],
+                        });
+                    }
                     _ => return Ok(ServerResult::None),
                 },
                 _ => return Ok(ServerResult::None),
             }
         }
 
+        // Limit the packets that make the server decrypt data for clients that are not connected
+        if let Some(limiter) = &mut self.request_limiter {
+            let packet_type = Packet::packet_type_from_buffer(buffer)?;
+            if matches!(packet_type, PacketType::ConnectionRequest | PacketType::Response) {
+                if let Err(scope) = limiter.try_consume(addr.ip(), self.current_time) {
+                    log::trace!("Dropped {:?} packet from {}: {} rate limit exceeded", packet_type, addr, scope);
+                    self.rate_limited_requests += 1;
+                    #[cfg(feature = "metrics")]
+                    metrics::counter!("renetcode2_connection_requests_rate_limited_total", "scope" => scope).increment(1);
+                    return Ok(ServerResult::None);
+                }
+            }
+        }
+
         // Handle pending client
         if let Some(pending) = self.pending_clients.get_mut(&(socket_id, addr)) {
             let (_, packet) = Packet::decode(
@@ -578,6 +982,7 @@ impl NetcodeServer {
                 Some(&pending.receive_key),
                 Some(&mut pending.replay_protection),
                 self.sockets[socket_id].needs_encryption,
+                self.sockets[socket_id].version,
             )?;
             pending.last_packet_received_time = self.current_time;
             log::trace!("Received packet from pending client ({}): {:?}", addr, packet.packet_type());
@@ -597,24 +1002,23 @@ impl NetcodeServer {
                 } => {
                     let challenge_token = ChallengeToken::decode(token_data, token_sequence, &self.challenge_key)?;
                     let mut pending = self.pending_clients.remove(&(socket_id, addr)).unwrap();
-                    if find_client_slot_by_id(&self.clients, challenge_token.client_id).is_some() {
+                    if self.clients.slot_by_id(challenge_token.client_id).is_some() {
                         log::debug!(
                             "Ignored connection response for Client {}, already connected.",
                             challenge_token.client_id
                         );
                         return Ok(ServerResult::None);
                     }
-                    match self.clients.iter().position(|c| c.is_none()) {
+                    match (self.clients.len() < self.max_clients).then(|| self.clients.next_slot()) {
                         None => {
-                            let packet = Packet::ConnectionDenied;
-                            let len = packet.encode(
-                                &mut self.out,
-                                self.protocol_id,
-                                Some((self.global_sequence, &pending.send_key)),
-                                self.sockets[socket_id].needs_encryption,
-                            )?;
+                            connection_denied!(
+                                "server_full",
+                                debug,
+                                "Connection response denied: server is full ({} clients).",
+                                self.max_clients
+                            );
+                            let len = self.encode_connection_denied(socket_id, &pending.send_key, ConnectionDeniedReason::ServerFull)?;
                             pending.state = ConnectionState::Disconnected;
-                            self.global_sequence += 1;
                             pending.last_packet_send_time = self.current_time;
                             return Ok(ServerResult::ConnectionDenied {
                                 socket_id,
@@ -636,12 +1040,21 @@ impl NetcodeServer {
                                 self.protocol_id,
                                 Some((pending.sequence, &pending.send_key)),
                                 self.sockets[socket_id].needs_encryption,
+                                self.sockets[socket_id].version,
                             )?;
                             pending.sequence += 1;
 
+                            connection_event!(
+                                pending,
+                                info,
+                                "Client {} connected with client index {}",
+                                pending.client_id,
+                                client_index
+                            );
+
                             let client_id: u64 = pending.client_id;
                             let user_data: [u8; NETCODE_USER_DATA_BYTES] = pending.user_data;
-                            self.clients[client_index] = Some(pending);
+                            self.clients.insert(pending);
 
                             return Ok(ServerResult::ClientConnected {
                                 client_id,
@@ -658,7 +1071,14 @@ impl NetcodeServer {
         }
 
         // Handle new client
-        let (_, packet) = Packet::decode(buffer, self.protocol_id, None, None, self.sockets[socket_id].needs_encryption)?;
+        let (_, packet) = Packet::decode(
+            buffer,
+            self.protocol_id,
+            None,
+            None,
+            self.sockets[socket_id].needs_encryption,
+            self.sockets[socket_id].version,
+        )?;
         match packet {
             Packet::ConnectionRequest {
                 data,
@@ -672,16 +1092,12 @@ impl NetcodeServer {
     }
 
     pub fn clients_slot(&self) -> Vec<usize> {
-        self.clients
-            .iter()
-            .enumerate()
-            .filter_map(|(index, slot)| if slot.is_some() { Some(index) } else { None })
-            .collect()
+        self.clients.iter().map(|(slot, _)| slot).collect()
     }
 
     /// Returns the ids from the connected clients (iterator).
     pub fn clients_id_iter(&self) -> impl Iterator<Item = u64> + '_ {
-        self.clients.iter().filter_map(|slot| slot.as_ref().map(|client| client.client_id))
+        self.clients.iter().map(|(_, client)| client.client_id)
     }
 
     /// Returns the ids from the connected clients.
@@ -700,29 +1116,63 @@ impl NetcodeServer {
     /// does not disconnect clients. So [`NetcodeServer::connected_clients()`] can return a
     /// higher value than [`NetcodeServer::max_clients()`].
     pub fn set_max_clients(&mut self, max_clients: usize) {
-        let max_clients = max_clients.min(NETCODE_MAX_CLIENTS);
         log::debug!("Netcode max_clients set to {}", max_clients);
 
         self.max_clients = max_clients;
     }
 
+    /// Returns the maximum number of clients that can be in the middle of connecting.
+    pub fn max_pending_clients(&self) -> usize {
+        self.max_pending_clients
+    }
+
+    /// Update the maximum numbers of clients that can be in the middle of connecting.
+    ///
+    /// Changing the `max_pending_clients` to a lower value than the current number of pending clients
+    /// does not drop them, new connection requests are denied until enough of them connect or expire.
+    pub fn set_max_pending_clients(&mut self, max_pending_clients: usize) {
+        log::debug!("Netcode max_pending_clients set to {}", max_pending_clients);
+
+        self.max_pending_clients = max_pending_clients;
+    }
+
     /// Returns current number of clients connected.
     pub fn connected_clients(&self) -> usize {
-        self.clients.iter().filter(|slot| slot.is_some()).count()
+        self.clients.len()
+    }
+
+    /// Returns current number of clients that are in the middle of connecting.
+    pub fn pending_clients(&self) -> usize {
+        self.pending_clients.len()
     }
 
     /// Advance the server current time, and remove any pending connections that have expired.
+    ///
+    /// The token time is read from the clock if the server has one, see [`NetcodeServer::set_clock`].
     pub fn update(&mut self, duration: Duration) {
         self.current_time += duration;
+        self.token_time = match &self.clock {
+            Some(clock) => clock.now(),
+            None => self.token_time + duration,
+        };
 
         for client in self.pending_clients.values_mut() {
-            if self.current_time.as_secs() > client.expire_timestamp {
-                log::debug!("Pending Client {} disconnected, connection token expired.", client.client_id);
+            if self.current_time >= client.expire_time {
+                connection_event!(
+                    client,
+                    debug,
+                    "Pending Client {} disconnected, connection token expired.",
+                    client.client_id
+                );
                 client.state = ConnectionState::Disconnected;
             }
         }
 
         self.pending_clients.retain(|_, c| c.state != ConnectionState::Disconnected);
+        self.ban_list.remove_expired(self.current_time, self.token_time);
+        if let Some(limiter) = &mut self.request_limiter {
+            limiter.remove_full_buckets(self.current_time);
+        }
     }
 
     /// Updates the client, returns a ServerResult.
@@ -740,16 +1190,16 @@ impl NetcodeServer {
     /// # fn send_to(p: &[u8], socket_id: usize, addr: std::net::SocketAddr) {}
     /// ```
     pub fn update_client(&mut self, client_id: u64) -> ServerResult<'_, '_> {
-        let slot = match find_client_slot_by_id(&self.clients, client_id) {
+        let slot = match self.clients.slot_by_id(client_id) {
             None => return ServerResult::None,
             Some(slot) => slot,
         };
 
-        if let Some(client) = &mut self.clients[slot] {
+        if let Some(client) = self.clients.get_mut(slot) {
             let connection_timed_out = client.timeout_seconds > 0
                 && (client.last_packet_received_time + Duration::from_secs(client.timeout_seconds as u64) < self.current_time);
             if connection_timed_out {
-                log::debug!("Client {} disconnected, connection timed out", client.client_id);
+                connection_event!(client, debug, "Client {} disconnected, connection timed out", client.client_id);
                 client.state = ConnectionState::Disconnected;
             }
             let socket_id = client.socket_id;
@@ -759,13 +1209,14 @@ impl NetcodeServer {
                 let sequence = client.sequence;
                 let send_key = client.send_key;
                 let addr = client.addr;
-                self.clients[slot] = None;
+                self.clients.remove(slot);
 
                 let len = match packet.encode(
                     &mut self.out,
                     self.protocol_id,
                     Some((sequence, &send_key)),
                     self.sockets[socket_id].needs_encryption,
+                    self.sockets[socket_id].version,
                 ) {
                     Err(e) => {
                         log::error!("Failed to encode disconnect packet: {}", e);
@@ -798,6 +1249,7 @@ impl NetcodeServer {
                     self.protocol_id,
                     Some((client.sequence, &client.send_key)),
                     self.sockets[socket_id].needs_encryption,
+                    self.sockets[socket_id].version,
                 ) {
                     Err(e) => {
                         log::error!("Failed to encode keep alive packet: {}", e);
@@ -818,8 +1270,119 @@ impl NetcodeServer {
         ServerResult::None
     }
 
+    /// Returns the span of a connected client.
+    #[cfg(feature = "tracing")]
+    pub fn client_span(&self, client_id: u64) -> Option<tracing::Span> {
+        self.clients.get_by_id(client_id).map(|client| client.span.clone())
+    }
+
+    /// Returns the span of the connected or pending client at an address.
+    #[cfg(feature = "tracing")]
+    pub fn connection_span(&self, socket_id: usize, addr: SocketAddr) -> Option<tracing::Span> {
+        self.clients
+            .get_by_addr(socket_id, addr)
+            .or_else(|| self.pending_clients.get(&(socket_id, addr)))
+            .map(|client| client.span.clone())
+    }
+
     pub fn is_client_connected(&self, client_id: u64) -> bool {
-        find_client_slot_by_id(&self.clients, client_id).is_some()
+        self.clients.slot_by_id(client_id).is_some()
+    }
+
+    /// Denies the connection requests of a client until the server time `until`.
+    ///
+    /// Does not disconnect the client if it is already connected, see [`NetcodeServer::disconnect`].
+    pub fn ban_client(&mut self, client_id: u64, until: Duration) {
+        log::debug!("Banned client {} until {:?}", client_id, until);
+        self.ban_list.ban_client(client_id, until);
+    }
+
+    /// Lifts the ban of a client, returns `false` if it wasn't banned.
+    pub fn unban_client(&mut self, client_id: u64) -> bool {
+        self.ban_list.unban_client(client_id)
+    }
+
+    /// Returns whether the connection requests of a client are denied.
+    pub fn is_client_banned(&self, client_id: u64) -> bool {
+        self.ban_list.is_client_banned(client_id, self.current_time)
+    }
+
+    /// Denies the connection requests from a range of addresses until the server time `until`.
+    ///
+    /// Does not disconnect the clients already connected from these addresses.
+    pub fn ban_address(&mut self, range: impl Into<IpRange>, until: Duration) {
+        let range = range.into();
+        log::debug!("Banned address range {} until {:?}", range, until);
+        self.ban_list.ban_address(range, until);
+    }
+
+    /// Lifts the ban of a range of addresses, returns `false` if it wasn't banned.
+    ///
+    /// The range must be the same one that was banned, ranges that overlap it are not affected.
+    pub fn unban_address(&mut self, range: impl Into<IpRange>) -> bool {
+        self.ban_list.unban_address(range.into())
+    }
+
+    /// Returns whether the connection requests from an address are denied.
+    pub fn is_address_banned(&self, addr: IpAddr) -> bool {
+        self.ban_list.is_address_banned(addr, self.current_time)
+    }
+
+    /// Denies the connection requests that use a connect token until the token time `until`, see [`NetcodeServer::token_time`].
+    ///
+    /// The token is identified by its MAC, see [`ConnectToken::mac`](crate::ConnectToken::mac).
+    /// Since expired tokens are already denied, the token expire timestamp is enough for `until`.
+    pub fn revoke_token(&mut self, mac: [u8; NETCODE_MAC_BYTES], until: Duration) {
+        self.ban_list.revoke_token(mac, until);
+    }
+
+    /// Returns whether the connection requests that use a connect token are denied.
+    pub fn is_token_revoked(&self, mac: &[u8; NETCODE_MAC_BYTES]) -> bool {
+        self.ban_list.is_token_revoked(mac, self.token_time)
+    }
+
+    /// Adds a private key accepted when decrypting connect tokens, replacing the key with the same id.
+    ///
+    /// The key is used even if the server was created with [`ServerAuthentication::Unsecure`],
+    /// whose clients use a key with id 0.
+    pub fn add_private_key(&mut self, key: ServerKey) {
+        log::debug!("Added netcode private key {}", key.id);
+        self.connect_keys.retain(|k| k.id != key.id);
+        self.connect_keys.push(key);
+    }
+
+    /// Removes a private key, returns `false` if there was no key with the id.
+    ///
+    /// Removing all the keys denies every connection request.
+    pub fn remove_private_key(&mut self, id: u64) -> bool {
+        let len = self.connect_keys.len();
+        self.connect_keys.retain(|k| k.id != id);
+        len != self.connect_keys.len()
+    }
+
+    /// Returns the ids of the private keys, from the least to the most recently added.
+    ///
+    /// A server created with a single key has a key with id 0.
+    pub fn private_key_ids(&self) -> Vec<u64> {
+        self.connect_keys.iter().map(|k| k.id).collect()
+    }
+
+    /// Returns the limit of connection request and response packets per source, if any.
+    pub fn connection_request_limit(&self) -> Option<ConnectionRequestLimit> {
+        self.request_limiter.as_ref().map(|limiter| limiter.limit())
+    }
+
+    /// Sets the limit of connection request and response packets per source, or removes it with `None`.
+    ///
+    /// No limit is set by default.
+    pub fn set_connection_request_limit(&mut self, limit: Option<ConnectionRequestLimit>) {
+        log::debug!("Netcode connection request limit set to {:?}", limit);
+        self.request_limiter = limit.map(ConnectionRequestLimiter::new);
+    }
+
+    /// Returns the number of connection request and response packets dropped by the connection request limit.
+    pub fn rate_limited_requests(&self) -> u64 {
+        self.rate_limited_requests
     }
 
     /// Disconnect an client and returns its address and a disconnect packet to be sent to them.
@@ -827,8 +1390,9 @@ impl NetcodeServer {
     //       but the library user would need to be aware that he has to run
     //       the same code as Result::ClientDisconnected
     pub fn disconnect(&mut self, client_id: u64) -> ServerResult<'_, '_> {
-        if let Some(slot) = find_client_slot_by_id(&self.clients, client_id) {
-            let client = self.clients[slot].take().unwrap();
+        if let Some(slot) = self.clients.slot_by_id(client_id) {
+            let client = self.clients.remove(slot).unwrap();
+            connection_event!(client, debug, "Client {} disconnected by the server", client.client_id);
             let packet = Packet::Disconnect;
 
             let len = match packet.encode(
@@ -836,6 +1400,7 @@ impl NetcodeServer {
                 self.protocol_id,
                 Some((client.sequence, &client.send_key)),
                 self.sockets[client.socket_id].needs_encryption,
+                self.sockets[client.socket_id].version,
             ) {
                 Err(e) => {
                     log::error!("Failed to encode disconnect packet: {}", e);
@@ -860,31 +1425,9 @@ impl NetcodeServer {
     }
 }
 
-fn find_client_mut_by_id(clients: &mut [Option<Connection>], client_id: u64) -> Option<&mut Connection> {
-    clients.iter_mut().flatten().find(|c| c.client_id == client_id)
-}
-
-fn find_client_by_id(clients: &[Option<Connection>], client_id: u64) -> Option<&Connection> {
-    clients.iter().flatten().find(|c| c.client_id == client_id)
-}
-
-fn find_client_slot_by_id(clients: &[Option<Connection>], client_id: u64) -> Option<usize> {
-    clients.iter().enumerate().find_map(|(i, c)| match c {
-        Some(c) if c.client_id == client_id => Some(i),
-        _ => None,
-    })
-}
-
-fn find_client_mut_by_addr(clients: &mut [Option<Connection>], socket_id: usize, addr: SocketAddr) -> Option<(usize, &mut Connection)> {
-    clients.iter_mut().enumerate().find_map(|(i, c)| match c {
-        Some(c) if (c.socket_id == socket_id) && (c.addr == addr) => Some((i, c)),
-        _ => None,
-    })
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::{client::NetcodeClient, token::ConnectToken, ClientAuthentication};
+    use crate::{client::NetcodeClient, clock::ManualClock, token::ConnectToken, ClientAuthentication, DisconnectReason};
 
     use super::*;
 
@@ -895,6 +1438,7 @@ mod tests {
         let config = ServerConfig {
             current_time: Duration::ZERO,
             max_clients: 16,
+            max_pending_clients: 64,
             protocol_id: TEST_PROTOCOL_ID,
             sockets: vec![ServerSocketConfig::new(vec!["127.0.0.1:5000".parse().unwrap()])],
             authentication: ServerAuthentication::Secure { private_key: *TEST_KEY },
@@ -1002,13 +1546,458 @@ mod tests {
         assert!(!server.is_client_connected(client_id));
     }
 
+    fn new_connect_token(server: &NetcodeServer, client_id: u64) -> ConnectToken {
+        ConnectToken::generate(
+            server.token_time(),
+            TEST_PROTOCOL_ID,
+            30,
+            client_id,
+            5,
+            0,
+            server.addresses(0),
+            None,
+            TEST_KEY,
+        )
+        .unwrap()
+    }
+
+    /// Runs the connection handshake of a new client, returns whether it connected.
+    fn connect_client(server: &mut NetcodeServer, client_id: u64, client_addr: SocketAddr) -> bool {
+        let connect_token = new_connect_token(server, client_id);
+        connect_with_token(server, connect_token, client_addr)
+    }
+
+    fn connect_with_token(server: &mut NetcodeServer, connect_token: ConnectToken, client_addr: SocketAddr) -> bool {
+        let client_id = connect_token.client_id;
+        let client_auth = ClientAuthentication::Secure { connect_token };
+        let mut client = NetcodeClient::new(server.current_time(), client_auth).unwrap();
+
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        match server.process_packet(0, client_addr, client_packet) {
+            ServerResult::ConnectionAccepted { payload, .. } => client.process_packet(payload),
+            _ => return false,
+        };
+
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        matches!(
+            server.process_packet(0, client_addr, client_packet),
+            ServerResult::ClientConnected { client_id: id, .. } if id == client_id
+        )
+    }
+
+    #[test]
+    fn many_clients() {
+        let max_clients = 1100;
+        let mut server = NetcodeServer::new(ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients,
+            max_pending_clients: 4,
+            protocol_id: TEST_PROTOCOL_ID,
+            sockets: vec![ServerSocketConfig::new(vec!["127.0.0.1:5000".parse().unwrap()])],
+            authentication: ServerAuthentication::Secure { private_key: *TEST_KEY },
+        });
+        let client_addr = |client_id: u64| SocketAddr::new("127.0.0.1".parse().unwrap(), 10000 + client_id as u16);
+
+        for client_id in 0..max_clients as u64 {
+            assert!(connect_client(&mut server, client_id, client_addr(client_id)));
+        }
+        assert_eq!(server.connected_clients(), max_clients);
+        assert_eq!(server.pending_clients(), 0);
+        assert!(server.is_client_connected(1050));
+        assert_eq!(server.client_addr(1050), Some((0, client_addr(1050))));
+
+        // Server is full
+        assert!(!connect_client(&mut server, 2000, client_addr(2000)));
+
+        // Freed slots are reused
+        assert!(matches!(server.disconnect(7), ServerResult::ClientDisconnected { .. }));
+        assert!(!server.is_client_connected(7));
+        assert!(!server.clients_slot().contains(&7));
+        assert!(connect_client(&mut server, 2000, client_addr(2000)));
+        assert!(server.clients_slot().contains(&7));
+        assert_eq!(server.connected_clients(), max_clients);
+    }
+
+    #[test]
+    fn banned_connections() {
+        let mut server = new_server();
+        let client_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+        // Banned client id
+        server.ban_client(1, Duration::from_secs(10));
+        assert!(server.is_client_banned(1));
+        assert!(!connect_client(&mut server, 1, client_addr));
+        server.update(Duration::from_secs(10));
+        assert!(!server.is_client_banned(1));
+        assert!(connect_client(&mut server, 1, client_addr));
+
+        // Banned address range
+        let client_addr: SocketAddr = "127.0.0.2:3000".parse().unwrap();
+        server.ban_address(IpRange::new("127.0.0.0".parse().unwrap(), 24), Duration::MAX);
+        assert!(server.is_address_banned(client_addr.ip()));
+        assert!(!connect_client(&mut server, 2, client_addr));
+        assert!(!server.unban_address(client_addr.ip()));
+        assert!(server.unban_address(IpRange::new("127.0.0.0".parse().unwrap(), 24)));
+        assert!(connect_client(&mut server, 2, client_addr));
+
+        // Revoked connect token
+        let client_addr: SocketAddr = "127.0.0.3:3000".parse().unwrap();
+        let connect_token = new_connect_token(&server, 3);
+        server.revoke_token(connect_token.mac(), Duration::from_secs(connect_token.expire_timestamp));
+        assert!(server.is_token_revoked(&connect_token.mac()));
+        assert!(!connect_with_token(&mut server, connect_token, client_addr));
+        assert!(connect_client(&mut server, 3, client_addr));
+
+        assert_eq!(server.connected_clients(), 3);
+    }
+
+    #[test]
+    fn connection_request_limit() {
+        let mut server = new_server();
+        server.set_connection_request_limit(Some(ConnectionRequestLimit {
+            per_address: 2,
+            ..Default::default()
+        }));
+        let client_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let client_auth = ClientAuthentication::Secure {
+            connect_token: new_connect_token(&server, 1),
+        };
+        let mut client = NetcodeClient::new(Duration::ZERO, client_auth).unwrap();
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        let request = client_packet.to_vec();
+        let (mut dropped_request, mut later_request) = (request.clone(), request.clone());
+
+        // Resent requests also count towards the limit
+        for _ in 0..2 {
+            let mut request = request.clone();
+            let result = server.process_packet(0, client_addr, &mut request);
+            assert!(matches!(result, ServerResult::ConnectionAccepted { .. }));
+        }
+        let result = server.process_packet(0, client_addr, &mut dropped_request);
+        assert_eq!(result, ServerResult::None);
+        assert_eq!(server.rate_limited_requests(), 1);
+
+        // Other addresses have their own limit
+        let other_addr: SocketAddr = "127.0.0.2:3000".parse().unwrap();
+        assert!(connect_client(&mut server, 2, other_addr));
+
+        server.update(Duration::from_secs(1));
+        let result = server.process_packet(0, client_addr, &mut later_request);
+        assert!(matches!(result, ServerResult::ConnectionAccepted { .. }));
+        assert_eq!(server.rate_limited_requests(), 1);
+    }
+
+    #[test]
+    fn connection_denied_reason() {
+        let mut server = new_server();
+        server.ban_client(1, Duration::MAX);
+        let client_auth = ClientAuthentication::Secure {
+            connect_token: new_connect_token(&server, 1),
+        };
+        let mut client = NetcodeClient::new(Duration::ZERO, client_auth).unwrap();
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+
+        match server.process_packet(0, "127.0.0.1:3000".parse().unwrap(), client_packet) {
+            ServerResult::ConnectionDenied {
+                payload: Some(payload), ..
+            } => assert!(client.process_packet(payload).is_none()),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            client.disconnect_reason(),
+            Some(DisconnectReason::ConnectionDenied(ConnectionDeniedReason::ClientBanned))
+        );
+
+        let deny = |server: &mut NetcodeServer, client_id: u64, client_addr: &str| {
+            let client_auth = ClientAuthentication::Secure {
+                connect_token: new_connect_token(server, client_id),
+            };
+            let mut client = NetcodeClient::new(Duration::ZERO, client_auth).unwrap();
+            let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+            match server.process_packet(0, client_addr.parse().unwrap(), client_packet) {
+                ServerResult::ConnectionDenied {
+                    payload: Some(payload), ..
+                } => assert!(client.process_packet(payload).is_none()),
+                _ => unreachable!(),
+            }
+            client.disconnect_reason()
+        };
+
+        assert!(connect_client(&mut server, 2, "127.0.0.2:3000".parse().unwrap()));
+        assert_eq!(
+            deny(&mut server, 2, "127.0.0.3:3000"),
+            Some(DisconnectReason::ConnectionDenied(ConnectionDeniedReason::ClientIdInUse))
+        );
+
+        server.set_max_pending_clients(0);
+        assert_eq!(
+            deny(&mut server, 3, "127.0.0.3:3000"),
+            Some(DisconnectReason::ConnectionDenied(ConnectionDeniedReason::TooManyPendingClients))
+        );
+    }
+
+    #[test]
+    fn invalid_server_config() {
+        let config = |sockets| ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients: 1,
+            max_pending_clients: 4,
+            protocol_id: TEST_PROTOCOL_ID,
+            sockets,
+            authentication: ServerAuthentication::Secure { private_key: *TEST_KEY },
+        };
+        assert!(matches!(NetcodeServer::try_new(config(vec![])), Err(ServerConfigError::NoSockets)));
+
+        let mut socket = ServerSocketConfig::new(vec!["127.0.0.1:5001".parse().unwrap()]);
+        socket.needs_encryption = false;
+        socket.version = NetcodeVersion::Standard;
+        let sockets = vec![ServerSocketConfig::new(vec!["127.0.0.1:5000".parse().unwrap()]), socket];
+        assert!(matches!(
+            NetcodeServer::try_new(config(sockets)),
+            Err(ServerConfigError::UnencryptedStandardSocket { socket_id: 1 })
+        ));
+    }
+
+    #[test]
+    fn standard_netcode_connection() {
+        let mut socket = ServerSocketConfig::new(vec!["127.0.0.1:5000".parse().unwrap()]);
+        socket.version = NetcodeVersion::Standard;
+        let mut server = NetcodeServer::new(ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients: 1,
+            max_pending_clients: 4,
+            protocol_id: TEST_PROTOCOL_ID,
+            sockets: vec![socket],
+            authentication: ServerAuthentication::Secure { private_key: *TEST_KEY },
+        });
+
+        let standard_token = |client_id| {
+            ConnectToken::generate_standard(
+                Duration::ZERO,
+                TEST_PROTOCOL_ID,
+                30,
+                client_id,
+                5,
+                server.addresses(0),
+                None,
+                TEST_KEY,
+            )
+            .unwrap()
+        };
+        let first_token = standard_token(1);
+        let second_token = standard_token(2);
+        let renet2_token = new_connect_token(&server, 3);
+
+        assert!(!connect_with_token(&mut server, renet2_token, "127.0.0.1:3000".parse().unwrap()));
+        assert!(connect_with_token(&mut server, first_token, "127.0.0.1:3001".parse().unwrap()));
+
+        // Standard denied packets have no reason
+        let mut client = NetcodeClient::new(
+            Duration::ZERO,
+            ClientAuthentication::Secure {
+                connect_token: second_token,
+            },
+        )
+        .unwrap();
+        assert_eq!(client.version(), NetcodeVersion::Standard);
+        let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+        match server.process_packet(0, "127.0.0.1:3002".parse().unwrap(), client_packet) {
+            ServerResult::ConnectionDenied {
+                payload: Some(payload), ..
+            } => assert!(client.process_packet(payload).is_none()),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            client.disconnect_reason(),
+            Some(DisconnectReason::ConnectionDenied(ConnectionDeniedReason::Unspecified))
+        );
+    }
+
+    #[test]
+    fn extended_user_data() {
+        let mut server = new_server();
+        let data: Vec<u8> = (0..3000).map(|i| i as u8).collect();
+
+        let connect = |server: &mut NetcodeServer, connect_token: ConnectToken, sealed: Vec<u8>, client_addr: SocketAddr| {
+            let client_id = connect_token.client_id;
+            let mut client = NetcodeClient::new(Duration::ZERO, ClientAuthentication::Secure { connect_token }).unwrap();
+            client.set_extended_user_data(sealed).unwrap();
+
+            let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+            match server.process_packet(0, client_addr, client_packet) {
+                ServerResult::ConnectionAccepted { payload, .. } => client.process_packet(payload),
+                _ => unreachable!(),
+            };
+            let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+            match server.process_packet(0, client_addr, client_packet) {
+                ServerResult::ClientConnected { payload, .. } => client.process_packet(payload),
+                _ => unreachable!(),
+            };
+            assert!(client.is_connected());
+            assert_eq!(server.extended_user_data(client_id), None);
+
+            // The sealed data is sent in 3 fragments without waiting for the send rate
+            let mut results = Vec::new();
+            for _ in 0..3 {
+                let (client_packet, _) = client.update(Duration::ZERO).unwrap();
+                let result = match server.process_packet(0, client_addr, client_packet) {
+                    ServerResult::PacketToSend { payload, .. } => {
+                        client.process_packet(payload);
+                        true
+                    }
+                    ServerResult::None => false,
+                    _ => unreachable!(),
+                };
+                results.push(result);
+            }
+            assert!(client.update(Duration::ZERO).is_none());
+
+            (client, results)
+        };
+
+        let connect_token = new_connect_token(&server, 1);
+        let sealed = connect_token.seal_extended_user_data(&data, TEST_KEY).unwrap();
+        let (mut client, acks) = connect(&mut server, connect_token, sealed.clone(), "127.0.0.1:3000".parse().unwrap());
+        assert_eq!(acks, [false, false, true]);
+        assert!(client.is_extended_user_data_acked());
+        assert_eq!(server.extended_user_data(1), Some(&data[..]));
+
+        // Keep alive packets are sent after the acknowledgement
+        let (client_packet, _) = client.update(NETCODE_SEND_RATE).unwrap();
+        assert_eq!(
+            server.process_packet(0, "127.0.0.1:3000".parse().unwrap(), client_packet),
+            ServerResult::None
+        );
+
+        // Data sealed for a different token disconnects the client
+        let connect_token = new_connect_token(&server, 2);
+        let (_, acks) = connect(&mut server, connect_token, sealed, "127.0.0.1:3001".parse().unwrap());
+        assert_eq!(acks, [false, false, false]);
+        assert!(matches!(
+            server.update_client(2),
+            ServerResult::ClientDisconnected { client_id: 2, .. }
+        ));
+        assert!(!server.is_client_connected(2));
+    }
+
+    #[test]
+    fn server_clock() {
+        const UPTIME: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let mut server = new_server();
+        server.set_clock(clock.clone());
+        assert_eq!(server.token_time(), Duration::from_secs(1000));
+        assert_eq!(server.current_time(), Duration::ZERO);
+
+        // Tokens are compared to the clock, so tokens issued with it don't look expired after a long uptime
+        clock.advance(UPTIME);
+        server.update(Duration::from_millis(16));
+        assert_eq!(server.token_time(), clock.now());
+        assert_eq!(server.current_time(), Duration::from_millis(16));
+        assert!(connect_client(&mut server, 1, "127.0.0.1:3000".parse().unwrap()));
+
+        // Clock steps backwards don't affect the server time
+        clock.set(Duration::from_secs(500));
+        server.update(Duration::from_secs(1));
+        assert_eq!(server.token_time(), Duration::from_secs(500));
+        assert_eq!(server.current_time(), Duration::from_millis(1016));
+        assert!(matches!(server.update_client(1), ServerResult::PacketToSend { .. }));
+        assert!(connect_client(&mut server, 2, "127.0.0.1:3001".parse().unwrap()));
+
+        // Connected clients time out with the server time, even if the clock stops
+        server.update(Duration::from_secs(6));
+        assert!(matches!(
+            server.update_client(1),
+            ServerResult::ClientDisconnected { client_id: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn private_key_rotation() {
+        const OLD_KEY: &[u8; NETCODE_KEY_BYTES] = b"an old example very secret key.."; // 32-bytes
+        const FUTURE_KEY: &[u8; NETCODE_KEY_BYTES] = b"a future example very secret key"; // 32-bytes
+        let mut server = NetcodeServer::new(ServerConfig {
+            current_time: Duration::ZERO,
+            max_clients: 16,
+            max_pending_clients: 64,
+            protocol_id: TEST_PROTOCOL_ID,
+            sockets: vec![ServerSocketConfig::new(vec!["127.0.0.1:5000".parse().unwrap()])],
+            authentication: ServerAuthentication::SecureKeys {
+                keys: vec![ServerKey {
+                    valid_until: Some(Duration::from_secs(10)),
+                    ..ServerKey::new(1, *OLD_KEY)
+                }],
+            },
+        });
+        server.add_private_key(ServerKey::new(2, *TEST_KEY));
+        server.add_private_key(ServerKey {
+            valid_from: Some(Duration::from_secs(5)),
+            ..ServerKey::new(3, *FUTURE_KEY)
+        });
+        assert_eq!(server.private_key_ids(), vec![1, 2, 3]);
+
+        let connect_with_key = |server: &mut NetcodeServer, client_id: u64, private_key: &[u8; NETCODE_KEY_BYTES]| {
+            // Tokens without a key id are decrypted with each key
+            let connect_token = ConnectToken::generate(
+                server.current_time(),
+                TEST_PROTOCOL_ID,
+                30,
+                client_id,
+                5,
+                0,
+                server.addresses(0),
+                None,
+                private_key,
+            )
+            .unwrap();
+            let client_addr = SocketAddr::new("127.0.0.1".parse().unwrap(), 3000 + client_id as u16);
+            connect_with_token(server, connect_token, client_addr)
+        };
+
+        // Tokens of any valid key are accepted
+        assert!(connect_with_key(&mut server, 1, OLD_KEY));
+        assert!(connect_with_key(&mut server, 2, TEST_KEY));
+        assert!(!connect_with_key(&mut server, 3, FUTURE_KEY));
+
+        // Validity windows
+        server.update(Duration::from_secs(10));
+        assert!(!connect_with_key(&mut server, 4, OLD_KEY));
+        assert!(connect_with_key(&mut server, 5, FUTURE_KEY));
+
+        // Removed keys
+        assert!(server.remove_private_key(2));
+        assert!(!server.remove_private_key(2));
+        assert!(!connect_with_key(&mut server, 6, TEST_KEY));
+        assert_eq!(server.private_key_ids(), vec![1, 3]);
+
+        // Tokens with a key id are only decrypted with the key of that id
+        let connect_with_key_id = |server: &mut NetcodeServer, client_id: u64, key: ServerKey| {
+            let connect_token = ConnectToken::generate_for_key(
+                server.current_time(),
+                TEST_PROTOCOL_ID,
+                30,
+                client_id,
+                5,
+                0,
+                server.addresses(0),
+                None,
+                &key,
+            )
+            .unwrap();
+            let client_addr = SocketAddr::new("127.0.0.1".parse().unwrap(), 3000 + client_id as u16);
+            connect_with_token(server, connect_token, client_addr)
+        };
+        assert!(connect_with_key_id(&mut server, 7, ServerKey::new(3, *FUTURE_KEY)));
+        assert!(!connect_with_key_id(&mut server, 8, ServerKey::new(1, *FUTURE_KEY)));
+        assert!(!connect_with_key_id(&mut server, 9, ServerKey::new(2, *FUTURE_KEY)));
+    }
+
     #[test]
     fn connect_token_already_used() {
         let mut server = new_server();
 
         let client_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
         let mut connect_token = ConnectTokenEntry {
-            time: Duration::ZERO,
             socket_id: 0,
             address: client_addr,
             mac: generate_random_bytes(),
@@ -1026,5 +2015,19 @@ mod tests {
         // Don't allow same token with different address
         connect_token.address = "127.0.0.1:3001".parse().unwrap();
         assert!(!server.find_or_add_connect_token_entry(connect_token));
+
+        // Small servers still remember the minimum number of tokens
+        server.set_max_clients(1);
+        server.set_max_pending_clients(1);
+        for i in 1..MIN_CONNECT_TOKEN_ENTRIES {
+            let mut mac = [0; NETCODE_MAC_BYTES];
+            mac[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            assert!(server.find_or_add_connect_token_entry(ConnectTokenEntry {
+                socket_id: 0,
+                address: client_addr,
+                mac,
+            }));
+        }
+        assert!(!server.find_or_add_connect_token_entry(connect_token));
     }
 }
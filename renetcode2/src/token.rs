@@ -1,20 +1,25 @@
 use std::{
     error::Error,
     fmt,
-    io::{self, Cursor},
+    io::{self, Cursor, Write},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     time::Duration,
 };
 
 use crate::{
     crypto::{dencrypted_in_place_xnonce, encrypt_in_place_xnonce, generate_random_bytes},
+    extended_user_data::seal_extended_user_data,
     serialize::*,
-    NetcodeError, NETCODE_ADDITIONAL_DATA_SIZE, NETCODE_ADDRESS_IPV4, NETCODE_ADDRESS_IPV6, NETCODE_ADDRESS_NONE,
-    NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES,
+    NetcodeError, NetcodeVersion, ServerKey, NETCODE_ADDITIONAL_DATA_SIZE, NETCODE_ADDRESS_IPV4, NETCODE_ADDRESS_IPV6,
+    NETCODE_ADDRESS_NONE, NETCODE_CONNECT_TOKEN_BYTES, NETCODE_CONNECT_TOKEN_PRIVATE_BYTES, NETCODE_CONNECT_TOKEN_XNONCE_BYTES,
+    NETCODE_KEY_BYTES, NETCODE_MAC_BYTES, NETCODE_MAX_EXTENDED_USER_DATA_BYTES, NETCODE_STANDARD_VERSION_INFO, NETCODE_USER_DATA_BYTES,
     NETCODE_VERSION_INFO,
 };
 use chacha20poly1305::aead::Error as CryptoError;
 
+// Starts the nonce of connect tokens that identify their key, followed by the key id and 12 random bytes
+const KEY_ID_XNONCE_TAG: &[u8; 4] = b"RN2K";
+
 /// A public connect token that the client receives to start connecting to the server.
 /// How the client receives ConnectToken is up to you, could be from a matchmaking
 /// system or from a call to a REST API as an example.
@@ -23,6 +28,7 @@ pub struct ConnectToken {
     // NOTE: On the netcode standard the client id is not available in the public part of the
     // ConnectToken. But having it acessible here makes it easier to consume the token, and the
     // server still uses the client_id from the private part.
+    // Standard tokens don't serialize it, so it is 0 after reading one.
     pub client_id: u64,
     pub version_info: [u8; 13],
     pub protocol_id: u64,
@@ -55,6 +61,15 @@ pub enum TokenGenerationError {
     CryptoError,
     IoError(io::Error),
     NoServerAddressAvailable,
+    /// The socket id has no server addresses in the [`TokenIssuer`](crate::TokenIssuer).
+    InvalidSocketId,
+    /// The client id is in the range allocated by the [`TokenIssuer`](crate::TokenIssuer).
+    ReservedClientId,
+    /// Every client id allocated by the [`TokenIssuer`](crate::TokenIssuer) was already used.
+    ClientIdsExhausted,
+    /// The extended user data is larger than
+    /// [`NETCODE_MAX_EXTENDED_USER_DATA_BYTES`](crate::NETCODE_MAX_EXTENDED_USER_DATA_BYTES).
+    ExtendedUserDataTooLarge,
 }
 
 impl From<io::Error> for TokenGenerationError {
@@ -80,6 +95,14 @@ impl fmt::Display for TokenGenerationError {
             CryptoError => write!(fmt, "error while encoding or decoding the connect token"),
             IoError(ref io_err) => write!(fmt, "{}", io_err),
             NoServerAddressAvailable => write!(fmt, "connect token must have at least one server address"),
+            InvalidSocketId => write!(fmt, "socket id has no server addresses"),
+            ReservedClientId => write!(fmt, "client id is reserved for allocated client ids"),
+            ClientIdsExhausted => write!(fmt, "all the allocated client ids were used"),
+            ExtendedUserDataTooLarge => write!(
+                fmt,
+                "extended user data is larger than {} bytes",
+                NETCODE_MAX_EXTENDED_USER_DATA_BYTES
+            ),
         }
     }
 }
@@ -98,17 +121,109 @@ impl ConnectToken {
         server_addresses: Vec<SocketAddr>,
         user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
         private_key: &[u8; NETCODE_KEY_BYTES],
+    ) -> Result<Self, TokenGenerationError> {
+        Self::generate_with_version(
+            NetcodeVersion::Renet2,
+            current_time,
+            protocol_id,
+            expire_seconds,
+            client_id,
+            timeout_seconds,
+            socket_id,
+            server_addresses,
+            user_data,
+            None,
+            private_key,
+        )
+    }
+
+    /// Same as [`ConnectToken::generate`], but the token identifies the key that encrypts it, so a server with
+    /// [`ServerAuthentication::SecureKeys`](crate::ServerAuthentication::SecureKeys) only decrypts it with the key
+    /// of the same id, instead of trying each key.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_for_key(
+        current_time: Duration,
+        protocol_id: u64,
+        expire_seconds: u64,
+        client_id: u64,
+        timeout_seconds: i32,
+        socket_id: u8,
+        server_addresses: Vec<SocketAddr>,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+        key: &ServerKey,
+    ) -> Result<Self, TokenGenerationError> {
+        Self::generate_with_version(
+            NetcodeVersion::Renet2,
+            current_time,
+            protocol_id,
+            expire_seconds,
+            client_id,
+            timeout_seconds,
+            socket_id,
+            server_addresses,
+            user_data,
+            Some(key.id),
+            &key.private_key,
+        )
+    }
+
+    /// Generate a standard netcode 1.02 token, that can be used by any netcode implementation.
+    ///
+    /// The token is only accepted by server sockets using [`NetcodeVersion::Standard`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_standard(
+        current_time: Duration,
+        protocol_id: u64,
+        expire_seconds: u64,
+        client_id: u64,
+        timeout_seconds: i32,
+        server_addresses: Vec<SocketAddr>,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+        private_key: &[u8; NETCODE_KEY_BYTES],
+    ) -> Result<Self, TokenGenerationError> {
+        Self::generate_with_version(
+            NetcodeVersion::Standard,
+            current_time,
+            protocol_id,
+            expire_seconds,
+            client_id,
+            timeout_seconds,
+            0,
+            server_addresses,
+            user_data,
+            None,
+            private_key,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_with_version(
+        version: NetcodeVersion,
+        current_time: Duration,
+        protocol_id: u64,
+        expire_seconds: u64,
+        client_id: u64,
+        timeout_seconds: i32,
+        socket_id: u8,
+        server_addresses: Vec<SocketAddr>,
+        user_data: Option<&[u8; NETCODE_USER_DATA_BYTES]>,
+        key_id: Option<u64>,
+        private_key: &[u8; NETCODE_KEY_BYTES],
     ) -> Result<Self, TokenGenerationError> {
         let expire_timestamp = current_time.as_secs() + expire_seconds;
 
         let private_connect_token = PrivateConnectToken::generate(client_id, timeout_seconds, socket_id, server_addresses, user_data)?;
         let mut private_data = [0u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES];
-        let xnonce = generate_random_bytes();
-        private_connect_token.encode(&mut private_data, protocol_id, expire_timestamp, &xnonce, private_key)?;
+        let mut xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES] = generate_random_bytes();
+        if let Some(key_id) = key_id {
+            xnonce[..4].copy_from_slice(KEY_ID_XNONCE_TAG);
+            xnonce[4..12].copy_from_slice(&key_id.to_le_bytes());
+        }
+        private_connect_token.encode(&mut private_data, protocol_id, expire_timestamp, &xnonce, private_key, version)?;
 
         Ok(Self {
             client_id,
-            version_info: *NETCODE_VERSION_INFO,
+            version_info: *version.version_info(),
             protocol_id,
             private_data,
             create_timestamp: current_time.as_secs(),
@@ -122,7 +237,37 @@ impl ConnectToken {
         })
     }
 
+    /// Returns the MAC of the encrypted private data, that identifies the token in the server.
+    ///
+    /// Used to revoke the token with [`NetcodeServer::revoke_token`](crate::NetcodeServer::revoke_token).
+    pub fn mac(&self) -> [u8; NETCODE_MAC_BYTES] {
+        let mut mac = [0u8; NETCODE_MAC_BYTES];
+        mac.copy_from_slice(&self.private_data[NETCODE_CONNECT_TOKEN_PRIVATE_BYTES - NETCODE_MAC_BYTES..]);
+        mac
+    }
+
+    /// Encrypts extended user data for this token, with the private key of the server.
+    ///
+    /// The client sends the sealed data to the server after connecting, see
+    /// [`NetcodeClient::set_extended_user_data`](crate::NetcodeClient::set_extended_user_data). The server only
+    /// accepts it from the client connected with this token, and makes it available with
+    /// [`NetcodeServer::extended_user_data`](crate::NetcodeServer::extended_user_data).
+    pub fn seal_extended_user_data(&self, data: &[u8], private_key: &[u8; NETCODE_KEY_BYTES]) -> Result<Vec<u8>, TokenGenerationError> {
+        seal_extended_user_data(data, &self.mac(), self.protocol_id, private_key)
+    }
+
+    /// Returns the netcode version of the token, if known.
+    pub fn version(&self) -> Option<NetcodeVersion> {
+        NetcodeVersion::from_version_info(&self.version_info)
+    }
+
+    /// Writes the token, standard tokens are written with the standard netcode layout of
+    /// [`NETCODE_CONNECT_TOKEN_BYTES`](crate::NETCODE_CONNECT_TOKEN_BYTES) bytes.
     pub fn write(&self, writer: &mut impl io::Write) -> Result<(), io::Error> {
+        if self.version() == Some(NetcodeVersion::Standard) {
+            return self.write_standard(writer);
+        }
+
         writer.write_all(&self.client_id.to_le_bytes())?;
         writer.write_all(&self.version_info)?;
         writer.write_all(&self.protocol_id.to_le_bytes())?;
@@ -132,16 +277,43 @@ impl ConnectToken {
         writer.write_all(&self.private_data)?;
         writer.write_all(&self.timeout_seconds.to_le_bytes())?;
         writer.write_all(&self.socket_id.to_le_bytes())?;
-        write_server_adresses(writer, &self.server_addresses)?;
+        write_server_adresses(writer, &self.server_addresses, NetcodeVersion::Renet2)?;
         writer.write_all(&self.client_to_server_key)?;
         writer.write_all(&self.server_to_client_key)?;
 
         Ok(())
     }
 
+    fn write_standard(&self, writer: &mut impl io::Write) -> Result<(), io::Error> {
+        // Standard tokens are padded with zeros
+        let mut buffer = [0u8; NETCODE_CONNECT_TOKEN_BYTES];
+        let cursor = &mut Cursor::new(&mut buffer[..]);
+        cursor.write_all(&self.version_info)?;
+        cursor.write_all(&self.protocol_id.to_le_bytes())?;
+        cursor.write_all(&self.create_timestamp.to_le_bytes())?;
+        cursor.write_all(&self.expire_timestamp.to_le_bytes())?;
+        cursor.write_all(&self.xnonce)?;
+        cursor.write_all(&self.private_data)?;
+        cursor.write_all(&self.timeout_seconds.to_le_bytes())?;
+        write_server_adresses(cursor, &self.server_addresses, NetcodeVersion::Standard)?;
+        cursor.write_all(&self.client_to_server_key)?;
+        cursor.write_all(&self.server_to_client_key)?;
+
+        writer.write_all(&buffer)
+    }
+
+    /// Reads a token written by [`ConnectToken::write`], or a standard netcode token.
     pub fn read(src: &mut impl io::Read) -> Result<Self, NetcodeError> {
-        let client_id = read_u64(src)?;
-        let version_info: [u8; 13] = read_bytes(src)?;
+        // Standard tokens start with the version info, renet2 tokens with the client id
+        let start: [u8; 13] = read_bytes(src)?;
+        if &start == NETCODE_STANDARD_VERSION_INFO {
+            return Self::read_standard(src);
+        }
+
+        let client_id = u64::from_le_bytes(start[..8].try_into().unwrap());
+        let mut version_info = [0u8; 13];
+        version_info[..5].copy_from_slice(&start[8..]);
+        src.read_exact(&mut version_info[5..])?;
         if &version_info != NETCODE_VERSION_INFO {
             return Err(NetcodeError::InvalidVersion);
         }
@@ -154,7 +326,7 @@ impl ConnectToken {
         let private_data: [u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES] = read_bytes(src)?;
         let timeout_seconds = read_i32(src)?;
         let socket_id = read_u8(src)?;
-        let server_addresses = read_server_addresses(src)?;
+        let server_addresses = read_server_addresses(src, NetcodeVersion::Renet2)?;
         let client_to_server_key: [u8; NETCODE_KEY_BYTES] = read_bytes(src)?;
         let server_to_client_key: [u8; NETCODE_KEY_BYTES] = read_bytes(src)?;
 
@@ -173,6 +345,39 @@ impl ConnectToken {
             timeout_seconds,
         })
     }
+
+    fn read_standard(src: &mut impl io::Read) -> Result<Self, NetcodeError> {
+        // Read the rest of the token with its padding
+        let mut buffer = [0u8; NETCODE_CONNECT_TOKEN_BYTES - 13];
+        src.read_exact(&mut buffer)?;
+        let src = &mut Cursor::new(&buffer[..]);
+
+        let protocol_id = read_u64(src)?;
+        let create_timestamp = read_u64(src)?;
+        let expire_timestamp = read_u64(src)?;
+        let xnonce = read_bytes(src)?;
+
+        let private_data: [u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES] = read_bytes(src)?;
+        let timeout_seconds = read_i32(src)?;
+        let server_addresses = read_server_addresses(src, NetcodeVersion::Standard)?;
+        let client_to_server_key: [u8; NETCODE_KEY_BYTES] = read_bytes(src)?;
+        let server_to_client_key: [u8; NETCODE_KEY_BYTES] = read_bytes(src)?;
+
+        Ok(Self {
+            client_id: 0,
+            version_info: *NETCODE_STANDARD_VERSION_INFO,
+            protocol_id,
+            create_timestamp,
+            expire_timestamp,
+            xnonce,
+            private_data,
+            socket_id: 0,
+            server_addresses,
+            client_to_server_key,
+            server_to_client_key,
+            timeout_seconds,
+        })
+    }
 }
 
 impl PrivateConnectToken {
@@ -214,11 +419,13 @@ impl PrivateConnectToken {
         })
     }
 
-    fn write(&self, writer: &mut impl io::Write) -> Result<(), io::Error> {
+    fn write(&self, writer: &mut impl io::Write, version: NetcodeVersion) -> Result<(), io::Error> {
         writer.write_all(&self.client_id.to_le_bytes())?;
         writer.write_all(&self.timeout_seconds.to_le_bytes())?;
-        writer.write_all(&self.socket_id.to_le_bytes())?;
-        write_server_adresses(writer, &self.server_addresses)?;
+        if version == NetcodeVersion::Renet2 {
+            writer.write_all(&self.socket_id.to_le_bytes())?;
+        }
+        write_server_adresses(writer, &self.server_addresses, version)?;
         writer.write_all(&self.client_to_server_key)?;
         writer.write_all(&self.server_to_client_key)?;
         writer.write_all(&self.user_data)?;
@@ -226,11 +433,14 @@ impl PrivateConnectToken {
         Ok(())
     }
 
-    fn read(src: &mut impl io::Read) -> Result<Self, io::Error> {
+    fn read(src: &mut impl io::Read, version: NetcodeVersion) -> Result<Self, io::Error> {
         let client_id = read_u64(src)?;
         let timeout_seconds = read_i32(src)?;
-        let socket_id = read_u8(src)?;
-        let server_addresses = read_server_addresses(src)?;
+        let socket_id = match version {
+            NetcodeVersion::Renet2 => read_u8(src)?,
+            NetcodeVersion::Standard => 0,
+        };
+        let server_addresses = read_server_addresses(src, version)?;
         let mut client_to_server_key = [0u8; 32];
         src.read_exact(&mut client_to_server_key)?;
 
@@ -258,9 +468,10 @@ impl PrivateConnectToken {
         expire_timestamp: u64,
         xnonce: &[u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES],
         private_key: &[u8; NETCODE_KEY_BYTES],
+        version: NetcodeVersion,
     ) -> Result<(), TokenGenerationError> {
-        let aad = get_additional_data(protocol_id, expire_timestamp);
-        self.write(&mut Cursor::new(&mut buffer[..]))?;
+        let aad = get_additional_data(protocol_id, expire_timestamp, version);
+        self.write(&mut Cursor::new(&mut buffer[..]), version)?;
 
         encrypt_in_place_xnonce(buffer, xnonce, private_key, &aad)?;
 
@@ -273,8 +484,9 @@ impl PrivateConnectToken {
         expire_timestamp: u64,
         xnonce: &[u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES],
         private_key: &[u8; NETCODE_KEY_BYTES],
+        version: NetcodeVersion,
     ) -> Result<Self, TokenGenerationError> {
-        let aad = get_additional_data(protocol_id, expire_timestamp);
+        let aad = get_additional_data(protocol_id, expire_timestamp, version);
 
         let mut temp_buffer = [0u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES];
         temp_buffer.copy_from_slice(buffer);
@@ -282,11 +494,24 @@ impl PrivateConnectToken {
         dencrypted_in_place_xnonce(&mut temp_buffer, xnonce, private_key, &aad)?;
 
         let src = &mut io::Cursor::new(&temp_buffer[..]);
-        Ok(Self::read(src)?)
+        Ok(Self::read(src, version)?)
     }
 }
 
-fn write_server_adresses(writer: &mut impl io::Write, server_addresses: &[Option<SocketAddr>; 32]) -> Result<(), io::Error> {
+/// Returns the id of the key that encrypted a connect token, if it was generated with [`ConnectToken::generate_for_key`].
+pub(crate) fn xnonce_key_id(xnonce: &[u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES]) -> Option<u64> {
+    if xnonce[..4] != KEY_ID_XNONCE_TAG[..] {
+        return None;
+    }
+
+    Some(u64::from_le_bytes(xnonce[4..12].try_into().unwrap()))
+}
+
+fn write_server_adresses(
+    writer: &mut impl io::Write,
+    server_addresses: &[Option<SocketAddr>; 32],
+    version: NetcodeVersion,
+) -> Result<(), io::Error> {
     let num_server_addresses: u32 = server_addresses.iter().filter(|a| a.is_some()).count() as u32;
     writer.write_all(&num_server_addresses.to_le_bytes())?;
 
@@ -300,8 +525,14 @@ fn write_server_adresses(writer: &mut impl io::Write, server_addresses: &[Option
             }
             SocketAddr::V6(addr) => {
                 writer.write_all(&NETCODE_ADDRESS_IPV6.to_le_bytes())?;
-                for i in addr.ip().octets() {
-                    writer.write_all(&i.to_le_bytes())?;
+                match version {
+                    NetcodeVersion::Renet2 => writer.write_all(&addr.ip().octets())?,
+                    // Standard netcode writes the address as little-endian 16-bit segments
+                    NetcodeVersion::Standard => {
+                        for segment in addr.ip().segments() {
+                            writer.write_all(&segment.to_le_bytes())?;
+                        }
+                    }
                 }
             }
         }
@@ -311,7 +542,7 @@ fn write_server_adresses(writer: &mut impl io::Write, server_addresses: &[Option
     Ok(())
 }
 
-fn read_server_addresses(src: &mut impl io::Read) -> Result<[Option<SocketAddr>; 32], io::Error> {
+fn read_server_addresses(src: &mut impl io::Read, version: NetcodeVersion) -> Result<[Option<SocketAddr>; 32], io::Error> {
     let mut server_addresses = [None; 32];
     let num_server_addresses = read_u32(src)? as usize;
     for server_address in server_addresses.iter_mut().take(num_server_addresses) {
@@ -325,10 +556,18 @@ fn read_server_addresses(src: &mut impl io::Read) -> Result<[Option<SocketAddr>;
                 *server_address = Some(addr);
             }
             NETCODE_ADDRESS_IPV6 => {
-                let mut ip = [0u8; 16];
-                src.read_exact(&mut ip)?;
+                let ip = match version {
+                    NetcodeVersion::Renet2 => Ipv6Addr::from(read_bytes::<16>(src)?),
+                    NetcodeVersion::Standard => {
+                        let mut segments = [0u16; 8];
+                        for segment in segments.iter_mut() {
+                            *segment = read_u16(src)?;
+                        }
+                        Ipv6Addr::from(segments)
+                    }
+                };
                 let port = read_u16(src)?;
-                let addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port);
+                let addr = SocketAddr::new(IpAddr::V6(ip), port);
                 *server_address = Some(addr);
             }
             NETCODE_ADDRESS_NONE => {} // skip
@@ -346,9 +585,9 @@ fn read_server_addresses(src: &mut impl io::Read) -> Result<[Option<SocketAddr>;
     Ok(server_addresses)
 }
 
-fn get_additional_data(protocol_id: u64, expire_timestamp: u64) -> [u8; NETCODE_ADDITIONAL_DATA_SIZE] {
+fn get_additional_data(protocol_id: u64, expire_timestamp: u64, version: NetcodeVersion) -> [u8; NETCODE_ADDITIONAL_DATA_SIZE] {
     let mut buffer = [0; NETCODE_ADDITIONAL_DATA_SIZE];
-    buffer[..13].copy_from_slice(NETCODE_VERSION_INFO);
+    buffer[..13].copy_from_slice(version.version_info());
     buffer[13..21].copy_from_slice(&protocol_id.to_le_bytes());
     buffer[21..29].copy_from_slice(&expire_timestamp.to_le_bytes());
 
@@ -366,8 +605,8 @@ mod tests {
         let token = PrivateConnectToken::generate(1, 5, socket_id, hosts, Some(&generate_random_bytes())).unwrap();
         let mut buffer: Vec<u8> = vec![];
 
-        token.write(&mut buffer).unwrap();
-        let result = PrivateConnectToken::read(&mut buffer.as_slice()).unwrap();
+        token.write(&mut buffer, NetcodeVersion::Renet2).unwrap();
+        let result = PrivateConnectToken::read(&mut buffer.as_slice(), NetcodeVersion::Renet2).unwrap();
 
         assert_eq!(token, result);
     }
@@ -382,9 +621,11 @@ mod tests {
         let expire_timestamp = 0;
         let mut buffer = [0u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES];
         let xnonce = generate_random_bytes();
-        token.encode(&mut buffer, protocol_id, expire_timestamp, &xnonce, key).unwrap();
+        token
+            .encode(&mut buffer, protocol_id, expire_timestamp, &xnonce, key, NetcodeVersion::Renet2)
+            .unwrap();
 
-        let result = PrivateConnectToken::decode(&buffer, protocol_id, expire_timestamp, &xnonce, key).unwrap();
+        let result = PrivateConnectToken::decode(&buffer, protocol_id, expire_timestamp, &xnonce, key, NetcodeVersion::Renet2).unwrap();
         assert_eq!(token, result);
     }
 
@@ -423,6 +664,7 @@ mod tests {
             result.expire_timestamp,
             &result.xnonce,
             private_key,
+            NetcodeVersion::Renet2,
         )
         .unwrap();
         assert_eq!(timeout_seconds, private.timeout_seconds);
@@ -433,4 +675,109 @@ mod tests {
         assert_eq!(token.client_to_server_key, private.client_to_server_key);
         assert_eq!(token.server_to_client_key, private.server_to_client_key);
     }
+
+    #[test]
+    fn standard_connect_token_vector() {
+        // Inputs of test_vectors/generate.py
+        let vector = include_bytes!("../test_vectors/connect_token.bin");
+        let private_key: [u8; NETCODE_KEY_BYTES] = std::array::from_fn(|i| i as u8);
+        let protocol_id = 0x1122334455667788;
+        let create_timestamp = 1_700_000_000;
+        let expire_timestamp = create_timestamp + 30;
+        let xnonce: [u8; NETCODE_CONNECT_TOKEN_XNONCE_BYTES] = std::array::from_fn(|i| 100 + i as u8);
+        let mut server_addresses = [None; 32];
+        server_addresses[0] = Some("127.0.0.1:40000".parse().unwrap());
+        server_addresses[1] = Some("[2001:db8::1:2]:50000".parse().unwrap());
+        let private = PrivateConnectToken {
+            client_id: 0x0123456789ABCDEF,
+            timeout_seconds: 5,
+            socket_id: 0,
+            server_addresses,
+            client_to_server_key: std::array::from_fn(|i| 32 + i as u8),
+            server_to_client_key: std::array::from_fn(|i| 64 + i as u8),
+            user_data: std::array::from_fn(|i| (7 + i * 3) as u8),
+        };
+
+        let mut private_data = [0u8; NETCODE_CONNECT_TOKEN_PRIVATE_BYTES];
+        private
+            .encode(
+                &mut private_data,
+                protocol_id,
+                expire_timestamp,
+                &xnonce,
+                &private_key,
+                NetcodeVersion::Standard,
+            )
+            .unwrap();
+        let token = ConnectToken {
+            client_id: 0,
+            version_info: *NETCODE_STANDARD_VERSION_INFO,
+            protocol_id,
+            create_timestamp,
+            expire_timestamp,
+            xnonce,
+            socket_id: 0,
+            server_addresses,
+            client_to_server_key: private.client_to_server_key,
+            server_to_client_key: private.server_to_client_key,
+            private_data,
+            timeout_seconds: 5,
+        };
+
+        let mut buffer: Vec<u8> = vec![];
+        token.write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), NETCODE_CONNECT_TOKEN_BYTES);
+        assert_eq!(&buffer[..], &vector[..]);
+
+        let result = ConnectToken::read(&mut vector.as_slice()).unwrap();
+        assert_eq!(result, token);
+        assert_eq!(result.version(), Some(NetcodeVersion::Standard));
+        let decoded = PrivateConnectToken::decode(
+            &result.private_data,
+            protocol_id,
+            expire_timestamp,
+            &xnonce,
+            &private_key,
+            NetcodeVersion::Standard,
+        )
+        .unwrap();
+        assert_eq!(decoded, private);
+    }
+
+    #[test]
+    fn standard_connect_token_generation() {
+        let server_addresses: Vec<SocketAddr> = vec!["[::1]:5000".parse().unwrap()];
+        let private_key = b"an example very very secret key."; // 32-bytes
+        let token = ConnectToken::generate_standard(Duration::ZERO, 2, 30, 4, 5, server_addresses, None, private_key).unwrap();
+
+        let mut buffer: Vec<u8> = vec![];
+        token.write(&mut buffer).unwrap();
+        assert_eq!(&buffer[..13], NETCODE_STANDARD_VERSION_INFO);
+
+        // The client id is only in the private part of standard tokens
+        let result = ConnectToken::read(&mut buffer.as_slice()).unwrap();
+        assert_eq!(result.client_id, 0);
+        assert_eq!(result.server_addresses, token.server_addresses);
+        let private = PrivateConnectToken::decode(
+            &result.private_data,
+            2,
+            result.expire_timestamp,
+            &result.xnonce,
+            private_key,
+            NetcodeVersion::Standard,
+        )
+        .unwrap();
+        assert_eq!(private.client_id, 4);
+
+        // Renet2 private tokens are rejected
+        assert!(PrivateConnectToken::decode(
+            &result.private_data,
+            2,
+            result.expire_timestamp,
+            &result.xnonce,
+            private_key,
+            NetcodeVersion::Renet2,
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,99 @@
+use std::{error::Error, fmt};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::NETCODE_USER_DATA_BYTES;
+
+/// The number of bytes of the length prefix of encoded user data.
+const USER_DATA_LENGTH_BYTES: usize = 2;
+
+/// The maximum number of bytes of a value encoded in user data.
+pub const NETCODE_MAX_ENCODED_USER_DATA_BYTES: usize = NETCODE_USER_DATA_BYTES - USER_DATA_LENGTH_BYTES;
+
+/// Errors when encoding or decoding typed user data.
+#[derive(Debug)]
+pub enum UserDataError {
+    /// The encoded value is larger than [`NETCODE_MAX_ENCODED_USER_DATA_BYTES`].
+    TooLarge { len: usize },
+    /// The length prefix of the user data is larger than [`NETCODE_MAX_ENCODED_USER_DATA_BYTES`].
+    InvalidLength { len: usize },
+    /// The value could not be serialized or deserialized.
+    Bincode(bincode::Error),
+}
+
+impl Error for UserDataError {}
+
+impl fmt::Display for UserDataError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use UserDataError::*;
+
+        match *self {
+            TooLarge { len } => write!(
+                fmt,
+                "encoded user data is {} bytes, the maximum is {} bytes",
+                len, NETCODE_MAX_ENCODED_USER_DATA_BYTES
+            ),
+            InvalidLength { len } => write!(fmt, "user data has an invalid length of {} bytes", len),
+            Bincode(ref err) => write!(fmt, "{}", err),
+        }
+    }
+}
+
+impl From<bincode::Error> for UserDataError {
+    fn from(inner: bincode::Error) -> Self {
+        UserDataError::Bincode(inner)
+    }
+}
+
+/// Encodes a value in connect token user data, as its length in 2 little-endian bytes followed by the value
+/// serialized with `bincode`.
+pub fn encode_user_data<T: Serialize + ?Sized>(value: &T) -> Result<[u8; NETCODE_USER_DATA_BYTES], UserDataError> {
+    let len = bincode::serialized_size(value)? as usize;
+    if len > NETCODE_MAX_ENCODED_USER_DATA_BYTES {
+        return Err(UserDataError::TooLarge { len });
+    }
+
+    let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+    user_data[..USER_DATA_LENGTH_BYTES].copy_from_slice(&(len as u16).to_le_bytes());
+    bincode::serialize_into(&mut user_data[USER_DATA_LENGTH_BYTES..], value)?;
+
+    Ok(user_data)
+}
+
+/// Decodes a value encoded with [`encode_user_data`].
+pub fn decode_user_data<T: DeserializeOwned>(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Result<T, UserDataError> {
+    let len = u16::from_le_bytes([user_data[0], user_data[1]]) as usize;
+    if len > NETCODE_MAX_ENCODED_USER_DATA_BYTES {
+        return Err(UserDataError::InvalidLength { len });
+    }
+
+    Ok(bincode::deserialize(
+        &user_data[USER_DATA_LENGTH_BYTES..USER_DATA_LENGTH_BYTES + len],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_data_encoding() {
+        let value = (7u64, String::from("player one"), vec![1u16, 2, 3]);
+        let user_data = encode_user_data(&value).unwrap();
+        let decoded: (u64, String, Vec<u16>) = decode_user_data(&user_data).unwrap();
+        assert_eq!(decoded, value);
+
+        let too_large = vec![0u8; NETCODE_MAX_ENCODED_USER_DATA_BYTES];
+        assert!(matches!(encode_user_data(&too_large), Err(UserDataError::TooLarge { len }) if len == too_large.len() + 8));
+
+        let invalid = [0xFF; NETCODE_USER_DATA_BYTES];
+        assert!(matches!(
+            decode_user_data::<u64>(&invalid),
+            Err(UserDataError::InvalidLength { len: 0xFFFF })
+        ));
+        assert!(matches!(
+            decode_user_data::<String>(&encode_user_data(&1u8).unwrap()),
+            Err(UserDataError::Bincode(_))
+        ));
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{NETCODE_STANDARD_VERSION_INFO, NETCODE_VERSION_INFO};
+
+/// The netcode protocol version used by a client or a server socket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NetcodeVersion {
+    /// Netcode 1.02 with the `renet2` extensions ("NETCODE 1.a2"), see `NETCODE_EXTENSIONS.md`.
+    #[default]
+    Renet2,
+    /// Standard netcode 1.02 ("NETCODE 1.02"), compatible with the reference implementation
+    /// and other netcode.io implementations.
+    ///
+    /// Packets are always encrypted and connect tokens have no socket id or public client id.
+    Standard,
+}
+
+impl NetcodeVersion {
+    /// Returns the version info written in connect tokens and connection requests.
+    pub fn version_info(self) -> &'static [u8; 13] {
+        match self {
+            NetcodeVersion::Renet2 => NETCODE_VERSION_INFO,
+            NetcodeVersion::Standard => NETCODE_STANDARD_VERSION_INFO,
+        }
+    }
+
+    /// Returns the version with the version info, if known.
+    pub fn from_version_info(version_info: &[u8; 13]) -> Option<Self> {
+        match version_info {
+            NETCODE_VERSION_INFO => Some(NetcodeVersion::Renet2),
+            NETCODE_STANDARD_VERSION_INFO => Some(NetcodeVersion::Standard),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+"""Generates the standard netcode 1.02 test vectors.
+
+Written from the netcode STANDARD.md independently of renetcode2, so the vectors check the
+Rust implementation against the standard instead of against itself.
+
+Requires the `cryptography` package. Run from this directory: `python3 generate.py`.
+"""
+
+import ipaddress
+import struct
+
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+VERSION_INFO = b"NETCODE 1.02\0"
+PROTOCOL_ID = 0x1122334455667788
+CREATE_TIMESTAMP = 1_700_000_000
+EXPIRE_TIMESTAMP = CREATE_TIMESTAMP + 30
+TIMEOUT_SECONDS = 5
+CLIENT_ID = 0x0123456789ABCDEF
+PRIVATE_KEY = bytes(range(32))
+XNONCE = bytes(range(100, 124))
+CLIENT_TO_SERVER_KEY = bytes(range(32, 64))
+SERVER_TO_CLIENT_KEY = bytes(range(64, 96))
+USER_DATA = bytes(i % 256 for i in range(7, 7 + 256 * 3, 3))
+SERVER_ADDRESSES = [("127.0.0.1", 40000), ("2001:db8::1:2", 50000)]
+CHALLENGE_KEY = bytes(range(128, 160))
+CHALLENGE_SEQUENCE = 9
+
+CONNECT_TOKEN_BYTES = 2048
+CONNECT_TOKEN_PRIVATE_BYTES = 1024
+CHALLENGE_TOKEN_BYTES = 300
+MAC_BYTES = 16
+
+
+def rotl32(value, shift):
+    return ((value << shift) & 0xFFFFFFFF) | (value >> (32 - shift))
+
+
+def quarter_round(state, a, b, c, d):
+    state[a] = (state[a] + state[b]) & 0xFFFFFFFF
+    state[d] = rotl32(state[d] ^ state[a], 16)
+    state[c] = (state[c] + state[d]) & 0xFFFFFFFF
+    state[b] = rotl32(state[b] ^ state[c], 12)
+    state[a] = (state[a] + state[b]) & 0xFFFFFFFF
+    state[d] = rotl32(state[d] ^ state[a], 8)
+    state[c] = (state[c] + state[d]) & 0xFFFFFFFF
+    state[b] = rotl32(state[b] ^ state[c], 7)
+
+
+def hchacha20(key, nonce):
+    state = list(struct.unpack("<4I", b"expand 32-byte k") + struct.unpack("<8I", key) + struct.unpack("<4I", nonce))
+    for _ in range(10):
+        quarter_round(state, 0, 4, 8, 12)
+        quarter_round(state, 1, 5, 9, 13)
+        quarter_round(state, 2, 6, 10, 14)
+        quarter_round(state, 3, 7, 11, 15)
+        quarter_round(state, 0, 5, 10, 15)
+        quarter_round(state, 1, 6, 11, 12)
+        quarter_round(state, 2, 7, 8, 13)
+        quarter_round(state, 3, 4, 9, 14)
+    return struct.pack("<8I", *(state[0:4] + state[12:16]))
+
+
+def encrypt_xchacha(message, aad, xnonce, key):
+    subkey = hchacha20(key, xnonce[:16])
+    return ChaCha20Poly1305(subkey).encrypt(bytes(4) + xnonce[16:], message, aad)
+
+
+def encrypt_chacha(message, aad, sequence, key):
+    return ChaCha20Poly1305(key).encrypt(struct.pack("<IQ", 0, sequence), message, aad)
+
+
+def write_addresses():
+    out = struct.pack("<I", len(SERVER_ADDRESSES))
+    for host, port in SERVER_ADDRESSES:
+        ip = ipaddress.ip_address(host)
+        if ip.version == 4:
+            out += struct.pack("<B", 1) + ip.packed
+        else:
+            # 8 little-endian 16-bit segments
+            out += struct.pack("<B", 2) + struct.pack("<8H", *struct.unpack(">8H", ip.packed))
+        out += struct.pack("<H", port)
+    return out
+
+
+def pad(data, size):
+    assert len(data) <= size
+    return data + bytes(size - len(data))
+
+
+def private_connect_token():
+    data = struct.pack("<QI", CLIENT_ID, TIMEOUT_SECONDS) + write_addresses()
+    data += CLIENT_TO_SERVER_KEY + SERVER_TO_CLIENT_KEY + USER_DATA
+    aad = VERSION_INFO + struct.pack("<QQ", PROTOCOL_ID, EXPIRE_TIMESTAMP)
+    return encrypt_xchacha(pad(data, CONNECT_TOKEN_PRIVATE_BYTES - MAC_BYTES), aad, XNONCE, PRIVATE_KEY)
+
+
+def connect_token(private_data):
+    data = VERSION_INFO + struct.pack("<QQQ", PROTOCOL_ID, CREATE_TIMESTAMP, EXPIRE_TIMESTAMP) + XNONCE + private_data
+    data += struct.pack("<I", TIMEOUT_SECONDS) + write_addresses() + CLIENT_TO_SERVER_KEY + SERVER_TO_CLIENT_KEY
+    return pad(data, CONNECT_TOKEN_BYTES)
+
+
+def connection_request(private_data):
+    return bytes([0]) + VERSION_INFO + struct.pack("<QQ", PROTOCOL_ID, EXPIRE_TIMESTAMP) + XNONCE + private_data
+
+
+def encrypted_packet(packet_type, sequence, data, key):
+    sequence_bytes = max(1, (sequence.bit_length() + 7) // 8)
+    prefix = packet_type | (sequence_bytes << 4)
+    aad = VERSION_INFO + struct.pack("<QB", PROTOCOL_ID, prefix)
+    return bytes([prefix]) + sequence.to_bytes(sequence_bytes, "little") + encrypt_chacha(data, aad, sequence, key)
+
+
+def challenge_token():
+    data = pad(struct.pack("<Q", CLIENT_ID) + USER_DATA, CHALLENGE_TOKEN_BYTES - MAC_BYTES)
+    return struct.pack("<Q", CHALLENGE_SEQUENCE) + encrypt_chacha(data, b"", CHALLENGE_SEQUENCE, CHALLENGE_KEY)
+
+
+def main():
+    private_data = private_connect_token()
+    vectors = {
+        "connect_token.bin": connect_token(private_data),
+        "connection_request.bin": connection_request(private_data),
+        "connection_denied.bin": encrypted_packet(1, 0, b"", SERVER_TO_CLIENT_KEY),
+        "challenge.bin": encrypted_packet(2, 1, challenge_token(), SERVER_TO_CLIENT_KEY),
+        "response.bin": encrypted_packet(3, 0, challenge_token(), CLIENT_TO_SERVER_KEY),
+        "keep_alive.bin": encrypted_packet(4, 2, struct.pack("<II", 3, 64), SERVER_TO_CLIENT_KEY),
+        "payload.bin": encrypted_packet(5, 0x1234, b"hello netcode", CLIENT_TO_SERVER_KEY),
+        "disconnect.bin": encrypted_packet(6, 0x0102030405060708, b"", CLIENT_TO_SERVER_KEY),
+    }
+    for name, data in vectors.items():
+        with open(name, "wb") as file:
+            file.write(data)
+
+
+if __name__ == "__main__":
+    main()